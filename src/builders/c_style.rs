@@ -13,7 +13,7 @@ use tokeniser::{ Tokeniser, ParseEscapes };
 /// Creates a Tokeniser that provides C-style quoting.
 ///
 /// This recognises pairs of " as delineating words, and parses
-/// `\n`, `\r`, `\"`, `\'`, and `\t` as their C equivalents.
+/// `\n`, `\r`, `\"`, `\'`, `\\`, `\t` and `\xHH` as their C equivalents.
 ///
 /// # Return value
 ///
@@ -41,20 +41,65 @@ pub fn c_style_tokeniser() -> StockTokeniser {
 }
 
 
+/// Quotes a string as a C string literal, which `c_style_tokeniser` reads
+/// as one word.
+///
+/// The string is put in double quotes, with newlines, carriage returns,
+/// tabs, double quotes and backslashes written as escape sequences.  All
+/// other characters, including non-ASCII characters, are written as they
+/// are, as `c_style_tokeniser` has no escape for them.
+///
+/// # Return value
+///
+/// The quoted string.
+///
+/// # Example
+///
+/// ```rust
+/// use russet::c_quote;
+///
+/// assert_eq!(c_quote("hello\nworld"), "\"hello\\nworld\"".into_string());
+/// assert_eq!(c_quote("say \"hi\""), "\"say \\\"hi\\\"\"".into_string());
+/// ```
+#[experimental]
+pub fn c_quote(word: &str) -> String {
+    let mut quoted = String::from_char(1, '\"');
+    for c in word.chars() {
+        match c {
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c    => quoted.push_char(c)
+        }
+    }
+    quoted.push_char('\"');
+    quoted
+}
+
+
 #[cfg(test)]
 mod test {
-    use super::c_style_tokeniser;
+    use std::collections::hashmap::HashMap;
+
+    use super::{ c_quote, c_style_tokeniser };
+    use builders::types::{ StockEscapeMap, StockQuoteMap };
+    use escape_scheme::MapEscape;
     use line::LineTokeniser;
+    use tokeniser::{ Tokeniser, ParseEscapes };
     use tokeniser::{ UnmatchedQuote, UnfinishedEscape };
 
     #[test]
     fn c_style_unmatched_quote() {
-        assert_eq!(c_style_tokeniser.line("\"abcde"), Err(UnmatchedQuote));
+        assert_eq!(c_style_tokeniser.line("\"abcde"),
+                   Err(UnmatchedQuote(0)));
     }
 
     #[test]
     fn c_style_unfinished_escape() {
-        assert_eq!(c_style_tokeniser.line("zxcvbn m\\"), Err(UnfinishedEscape));
+        assert_eq!(c_style_tokeniser.line("zxcvbn m\\"),
+                   Err(UnfinishedEscape(8)));
     }
 
     #[test]
@@ -92,4 +137,39 @@ mod test {
         assert_eq!(c_style_tokeniser.line("\"abc\\nde\""),
                    Ok(vec![ "abc\nde".into_string() ]));
     }
+
+    /// The hand-built C-style tokeniser in the README should agree with the
+    /// stock one.
+    #[test]
+    fn c_style_readme_example() {
+        let quote_map: StockQuoteMap =
+            vec![ ( '\"', ( '\"', ParseEscapes ) ) ].move_iter().collect();
+        let escape_pairs: HashMap<char, char> =
+            vec![ ( 'n',  '\n' ),
+                  ( 'r',  '\r' ),
+                  ( '\"', '\"' ),
+                  ( '\'', '\'' ),
+                  ( '\\', '\\' ),
+                  ( 't',  '\t' ) ].move_iter().collect();
+        let escape_map: StockEscapeMap =
+            vec![ ( '\\', MapEscape(escape_pairs) ) ].move_iter().collect();
+        let readme = Tokeniser::new(quote_map, escape_map);
+
+        let line = "a\\tb \"c\\\"d\\\\e\" f\\'\\r";
+        assert_eq!(readme.add_line(line).into_strings(),
+                   c_style_tokeniser.line(line));
+    }
+
+    #[test]
+    fn c_quote_escapes() {
+        assert_eq!(c_quote("a\tb\r\n"), "\"a\\tb\\r\\n\"".into_string());
+        assert_eq!(c_quote("\\\"'é"), "\"\\\\\\\"'é\"".into_string());
+        assert_eq!(c_quote(""), "\"\"".into_string());
+    }
+
+    #[quickcheck]
+    fn c_quote_round_trip(s: String) -> bool {
+        c_style_tokeniser.line(c_quote(s.as_slice()).as_slice())
+            == Ok(vec![ s ])
+    }
 }
@@ -0,0 +1,78 @@
+//! Builder for the colon-split stock tokeniser.
+#![experimental]
+
+use builders::types::{
+    StockEscapeMap,
+    StockQuoteMap,
+    StockTokeniser
+};
+use escape_scheme::LiteralEscape;
+use tokeniser::{ Tokeniser, IgnoreEscapes, ParseEscapes, SeparatorChars };
+
+
+/// Creates a Tokeniser that splits words at colons, as in `PATH`.
+///
+/// Quotes and escapes are as in `shell_style_tokeniser`, so a colon can be
+/// part of a word by quoting or escaping it.  Whitespace is part of words.
+///
+/// # Return value
+///
+/// A Tokeniser splitting at colons, with shell-style quoting.
+///
+/// # Example
+///
+/// ```rust
+/// use russet::colon_split_tokeniser;
+///
+/// let tok = colon_split_tokeniser();
+/// let tok2 = tok.add_line("/bin:/usr/local/bin:'/opt/my:app'");
+/// assert_eq!(tok2.into_strings(), Ok(vec!("/bin".into_string(),
+///                                         "/usr/local/bin".into_string(),
+///                                         "/opt/my:app".into_string())));
+/// ```
+#[experimental]
+pub fn colon_split_tokeniser() -> StockTokeniser {
+    let quote_map: StockQuoteMap =
+        vec![ ( '\"', ( '\"', ParseEscapes ) ),
+              ( '\'', ( '\'', IgnoreEscapes ) ) ].move_iter().collect();
+    let escape_map: StockEscapeMap =
+        vec![ ( '\\', LiteralEscape ) ].move_iter().collect();
+    Tokeniser::new_with_separator(quote_map, escape_map,
+                                  SeparatorChars(vec![ ':' ]))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::colon_split_tokeniser;
+    use line::LineTokeniser;
+    use tokeniser::UnmatchedQuote;
+
+    #[test]
+    fn colon_split_quoted_separators() {
+        assert_eq!(colon_split_tokeniser.line("a:\"b:c\":'d:e'"),
+                   Ok(vec![ "a".into_string(),
+                            "b:c".into_string(),
+                            "d:e".into_string() ]));
+    }
+
+    #[test]
+    fn colon_split_escaped_separator() {
+        assert_eq!(colon_split_tokeniser.line("C\\:/Windows:/bin"),
+                   Ok(vec![ "C:/Windows".into_string(),
+                            "/bin".into_string() ]));
+    }
+
+    #[test]
+    fn colon_split_whitespace_in_words() {
+        assert_eq!(colon_split_tokeniser.line("my dir::other dir"),
+                   Ok(vec![ "my dir".into_string(),
+                            "other dir".into_string() ]));
+    }
+
+    #[test]
+    fn colon_split_unmatched_quote() {
+        assert_eq!(colon_split_tokeniser.line("a:'b"),
+                   Err(UnmatchedQuote(2)));
+    }
+}
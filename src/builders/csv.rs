@@ -0,0 +1,101 @@
+//! Builder for the CSV stock tokeniser.
+#![experimental]
+
+use std::collections::hashmap::HashMap;
+
+use builders::types::{
+    StockEscapeMap,
+    StockQuoteMap,
+    StockTokeniser
+};
+use tokeniser::{ Tokeniser, DoubledEscape, SeparatorBeforeQuote };
+use tokeniser::SeparatorChars;
+
+
+/// Creates a Tokeniser that reads a record of comma-separated values.
+///
+/// This follows [RFC 4180][rfc]: fields are separated by commas, and may be
+/// quoted with ", inside which a doubled "" stands for a single ".  Quoted
+/// fields may contain commas and line breaks.  Empty fields are kept, and
+/// spaces are part of fields.  A quote may only open a field.
+///
+/// The Tokeniser reads a single record; `add_line` removes the line ending
+/// after it.
+///
+/// [rfc]: https://tools.ietf.org/html/rfc4180
+///
+/// # Return value
+///
+/// A Tokeniser with CSV quoting.
+///
+/// # Example
+///
+/// ```rust
+/// use russet::csv_tokeniser;
+///
+/// let tok = csv_tokeniser().add_line("1997,Ford,\"E350, \"\"Super\"\"\",,");
+/// assert_eq!(tok.into_strings(), Ok(vec!("1997".into_string(),
+///                                        "Ford".into_string(),
+///                                        "E350, \"Super\"".into_string(),
+///                                        "".into_string(),
+///                                        "".into_string())));
+/// ```
+#[experimental]
+pub fn csv_tokeniser() -> StockTokeniser {
+    let quote_map: StockQuoteMap =
+        vec![ ( '\"', ( '\"', DoubledEscape ) ) ].move_iter().collect();
+    let escape_map: StockEscapeMap = HashMap::new();
+    Tokeniser::new(quote_map, escape_map)
+        .with_separators(SeparatorChars(vec![ ',' ]))
+        .with_preserve_empty_fields(true)
+        .with_before_quote(SeparatorBeforeQuote)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::csv_tokeniser;
+    use line::LineTokeniser;
+    use tokeniser::{ QuoteNotAtFieldStart, UnmatchedQuote };
+
+    /// Converts string slices into Strings.
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.into_string()).collect()
+    }
+
+    #[test]
+    fn csv_rfc_4180() {
+        assert_eq!(csv_tokeniser.line("aaa,bbb,ccc"),
+                   Ok(strings([ "aaa", "bbb", "ccc" ])));
+        assert_eq!(csv_tokeniser.line("\"aaa\",\"bbb\",\"ccc\""),
+                   Ok(strings([ "aaa", "bbb", "ccc" ])));
+        assert_eq!(csv_tokeniser.line("\"aaa\",\"b\r\nbb\",\"ccc\""),
+                   Ok(strings([ "aaa", "b\r\nbb", "ccc" ])));
+        assert_eq!(csv_tokeniser.line("\"aaa\",\"b\"\"bb\",\"ccc\""),
+                   Ok(strings([ "aaa", "b\"bb", "ccc" ])));
+    }
+
+    #[test]
+    fn csv_empty_fields() {
+        assert_eq!(csv_tokeniser.line("a,,b"), Ok(strings([ "a", "", "b" ])));
+        assert_eq!(csv_tokeniser.line(",a,"), Ok(strings([ "", "a", "" ])));
+        assert_eq!(csv_tokeniser.line(",,"), Ok(strings([ "", "", "" ])));
+        assert_eq!(csv_tokeniser.line("a,\"\""), Ok(strings([ "a", "" ])));
+        assert_eq!(csv_tokeniser.line("\"\""), Ok(strings([ "" ])));
+        assert_eq!(csv_tokeniser.line(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn csv_spaces_kept() {
+        assert_eq!(csv_tokeniser().add_string(" a , b ").into_strings(),
+                   Ok(strings([ " a ", " b " ])));
+    }
+
+    #[test]
+    fn csv_bad_quotes() {
+        assert_eq!(csv_tokeniser.line("a,b\"c\""),
+                   Err(QuoteNotAtFieldStart(3)));
+        assert_eq!(csv_tokeniser.line("a,\"b\"\"c"),
+                   Err(UnmatchedQuote(2)));
+    }
+}
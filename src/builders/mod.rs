@@ -1,11 +1,95 @@
 //! Builder functions for common tokeniser configurations.
+//!
+//! Examples of each builder are listed in `src/builders/examples.txt`, and
+//! are run as tests.
 #![experimental]
 
-pub use builders::c_style::c_style_tokeniser;
-pub use builders::whitespace_split::whitespace_split_tokeniser;
-pub use builders::shell_style::shell_style_tokeniser;
+pub use builders::c_style::{ c_quote, c_style_tokeniser };
+pub use builders::colon_split::colon_split_tokeniser;
+pub use builders::csv::csv_tokeniser;
+pub use builders::null_split::null_split_tokeniser;
+pub use builders::python_shlex::python_shlex_tokeniser;
+pub use builders::quote_pairs::QuotePairParseError;
+pub use builders::whitespace_split::{
+    tab_split_tokeniser,
+    whitespace_split_tokeniser
+};
+pub use builders::shell_style::{
+    shell_join,
+    shell_quote,
+    shell_style_tokeniser,
+    shell_style_tokeniser_with_comments
+};
 
 pub mod c_style;
+pub mod colon_split;
+pub mod csv;
+pub mod null_split;
+pub mod python_shlex;
+pub mod quote_pairs;
 pub mod whitespace_split;
 pub mod shell_style;
 pub mod types;
+
+
+#[cfg(test)]
+mod test {
+    use builders::types::StockTokeniser;
+    use line::LineTokeniser;
+    use super::{
+        c_style_tokeniser,
+        colon_split_tokeniser,
+        csv_tokeniser,
+        null_split_tokeniser,
+        python_shlex_tokeniser,
+        shell_style_tokeniser,
+        shell_style_tokeniser_with_comments,
+        tab_split_tokeniser,
+        whitespace_split_tokeniser
+    };
+
+    /// Examples of each builder, as lines of builder name, input, and
+    /// expected words.
+    static EXAMPLES: &'static str = include_str!("examples.txt");
+
+    /// Finds the builder with the given name in EXAMPLES.
+    fn builder(name: &str) -> Option<fn() -> StockTokeniser> {
+        match name {
+            "c_style"          => Some(c_style_tokeniser),
+            "colon_split"      => Some(colon_split_tokeniser),
+            "csv"              => Some(csv_tokeniser),
+            "null_split"       => Some(null_split_tokeniser),
+            "python_shlex"     => Some(python_shlex_tokeniser),
+            "shell_style"      => Some(shell_style_tokeniser),
+            "shell_style_with_comments" =>
+                Some(shell_style_tokeniser_with_comments),
+            "tab_split"        => Some(tab_split_tokeniser),
+            "whitespace_split" => Some(whitespace_split_tokeniser),
+            _                  => None
+        }
+    }
+
+    #[test]
+    fn examples() {
+        for ( i, line ) in EXAMPLES.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with("#") { continue; }
+
+            let fields = match c_style_tokeniser.line(line) {
+                Ok(ref fs) if fs.len() >= 2 => fs.clone(),
+                _ => fail!("examples.txt:{}: malformed example", i + 1)
+            };
+            let name = fields.get(0).as_slice();
+            let tok = match builder(name) {
+                Some(b) => b,
+                None    => fail!("examples.txt:{}: no builder {}", i + 1, name)
+            };
+
+            let expected = Ok(fields.slice_from(2).to_vec());
+            let actual = tok.line(fields.get(1).as_slice());
+            if actual != expected {
+                fail!("examples.txt:{}: expected {}, got {}",
+                      i + 1, expected, actual);
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Builder for the NUL-split stock tokeniser.
+#![experimental]
+
+use std::collections::hashmap::HashMap;
+
+use builders::types::{
+    StockEscapeMap,
+    StockQuoteMap,
+    StockTokeniser
+};
+use tokeniser::{ Tokeniser, SeparatorChars };
+
+
+/// Creates a Tokeniser that splits words at NUL characters, as in the
+/// output of `find -print0` and the input of `xargs -0`.
+///
+/// There is no quoting or escaping, so every other character, including
+/// whitespace, is part of a word.  A NUL ending the last word is not an
+/// empty word.
+///
+/// `add_line` removes a newline from the end of its input, which would
+/// change the last word, so use `add_string` instead.
+///
+/// # Return value
+///
+/// A Tokeniser splitting at NULs, with no quoting or escaping.
+///
+/// # Example
+///
+/// ```rust
+/// use russet::null_split_tokeniser;
+///
+/// let tok = null_split_tokeniser().add_string("./a b\0./c\n\0");
+/// assert_eq!(tok.into_strings(), Ok(vec!("./a b".into_string(),
+///                                        "./c\n".into_string())));
+/// ```
+#[experimental]
+pub fn null_split_tokeniser() -> StockTokeniser {
+    let quote_map: StockQuoteMap = HashMap::new();
+    let escape_map: StockEscapeMap = HashMap::new();
+    Tokeniser::new_with_separator(quote_map, escape_map,
+                                  SeparatorChars(vec![ '\0' ]))
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::io::MemReader;
+
+    use super::null_split_tokeniser;
+
+    use tokeniser::Unbounded;
+
+    #[test]
+    fn null_split_find_print0() {
+        // As printed by `find . -type f -print0`.
+        let output = "./my file.txt\0./tab\there\0./new\nline\0\
+                      ./'quoted' \"name\"\0./back\\slash\0";
+        let tok = null_split_tokeniser().add_string(output);
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "./my file.txt".into_string(),
+                            "./tab\there".into_string(),
+                            "./new\nline".into_string(),
+                            "./'quoted' \"name\"".into_string(),
+                            "./back\\slash".into_string() ]));
+    }
+
+    #[test]
+    fn null_split_keeps_whitespace_at_ends() {
+        let tok = null_split_tokeniser().add_string(" a \0\0\n");
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ " a ".into_string(), "\n".into_string() ]));
+    }
+
+    #[test]
+    fn null_split_reader() {
+        let reader = MemReader::new(b"x y\0z\0".to_vec());
+        let tok = null_split_tokeniser().add_reader(reader, Unbounded)
+                                        .unwrap();
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "x y".into_string(), "z".into_string() ]));
+    }
+}
@@ -0,0 +1,122 @@
+//! Builder for the Python shlex-compatible stock tokeniser.
+#![experimental]
+
+use std::collections::hashmap::HashMap;
+
+use builders::types::{
+    StockEscapeMap,
+    StockQuoteMap,
+    StockTokeniser
+};
+use escape_scheme::{ LiteralEscape, PartialEscape };
+use tokeniser::{ Tokeniser, Custom, IgnoreEscapes, SeparatorChars };
+
+
+/// Creates a Tokeniser that splits words as Python's `shlex.split` does.
+///
+/// This follows `shlex.split` in its default POSIX mode, without comments.
+/// Words are separated by spaces, tabs, carriage returns and newlines
+/// only.  Anything in single quotes is returned verbatim.  In double
+/// quotes, `\` escapes only `"` and `\`, and is kept before any other
+/// character, including a newline.  Outside quotes, `\` escapes any
+/// character.
+///
+/// `shell_style_tokeniser` differs in reading `\` as an escape before any
+/// character in double quotes, and in separating words at any whitespace.
+///
+/// # Return value
+///
+/// A Tokeniser compatible with `shlex.split`.
+///
+/// # Example
+///
+/// ```rust
+/// use russet::python_shlex_tokeniser;
+///
+/// let tok = python_shlex_tokeniser();
+/// let tok2 = tok.add_line("grep \"a\\.b\\\"\" 'c\\d' e\\ f");
+/// assert_eq!(tok2.into_strings(), Ok(vec!("grep".into_string(),
+///                                         "a\\.b\"".into_string(),
+///                                         "c\\d".into_string(),
+///                                         "e f".into_string())));
+/// ```
+#[experimental]
+pub fn python_shlex_tokeniser() -> StockTokeniser {
+    let in_double: HashMap<char, char> =
+        vec![ ( '\"', '\"' ), ( '\\', '\\' ) ].move_iter().collect();
+    let quote_map: StockQuoteMap =
+        vec![ ( '\"', ( '\"', Custom('\\', PartialEscape('\\', in_double)) ) ),
+              ( '\'', ( '\'', IgnoreEscapes ) ) ].move_iter().collect();
+    let escape_map: StockEscapeMap =
+        vec![ ( '\\', LiteralEscape ) ].move_iter().collect();
+    Tokeniser::new_with_separator(quote_map, escape_map,
+                                  SeparatorChars(vec![ ' ', '\t', '\r', '\n' ]))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::python_shlex_tokeniser;
+    use tokeniser::{ UnfinishedEscape, UnmatchedQuote };
+
+    /// Inputs, and the words Python 3.11's `shlex.split` gives for them.
+    #[test]
+    fn matches_python_shlex_split() {
+        let vectors = vec![
+            ( "a b  c",              vec![ "a", "b", "c" ] ),
+            ( "  lead trail  ",      vec![ "lead", "trail" ] ),
+            ( "a\tb\nc\rd",          vec![ "a", "b", "c", "d" ] ),
+            ( "a\u00a0b",            vec![ "a\u00a0b" ] ),
+            ( "'a b' \"c d\"",       vec![ "a b", "c d" ] ),
+            ( "a'b'\"c\"d",          vec![ "abcd" ] ),
+            ( "''",                  vec![ "" ] ),
+            ( "\"\"",                vec![ "" ] ),
+            ( "a '' b",              vec![ "a", "", "b" ] ),
+            ( "\\a\\ b",             vec![ "a b" ] ),
+            ( "a\\\\b",              vec![ "a\\b" ] ),
+            ( "a\\\nb",              vec![ "a\nb" ] ),
+            ( "\\'a",                vec![ "'a" ] ),
+            ( "\\\"a",               vec![ "\"a" ] ),
+            ( "'a\\b'",              vec![ "a\\b" ] ),
+            ( "'a'\\''b'",           vec![ "a'b" ] ),
+            ( "\"a\\b\"",            vec![ "a\\b" ] ),
+            ( "\"a\\\\b\"",          vec![ "a\\b" ] ),
+            ( "\"a\\\"b\"",          vec![ "a\"b" ] ),
+            ( "\"a\\'b\"",           vec![ "a\\'b" ] ),
+            ( "\"a\\$b\"",           vec![ "a\\$b" ] ),
+            ( "\"a\\\nb\"",          vec![ "a\\\nb" ] ),
+            ( "\"a\\\tb\"",          vec![ "a\\\tb" ] ),
+            ( "\"\\\\\\\"\"",        vec![ "\\\"" ] ),
+            ( "'a\"b' \"a'b\"",      vec![ "a\"b", "a'b" ] ),
+            ( "\"$HOME\" '$HOME'",   vec![ "$HOME", "$HOME" ] ),
+            ( "x=\"1 2\" y",         vec![ "x=1 2", "y" ] ),
+            ( "a#b # c",             vec![ "a#b", "#", "c" ] ),
+            ( "a;b|c",               vec![ "a;b|c" ] ),
+            ( "é 'éè'",              vec![ "é", "éè" ] )
+        ];
+
+        for &( input, ref expected ) in vectors.iter() {
+            let words = python_shlex_tokeniser().add_string(input)
+                                                .into_strings();
+            let expected: Vec<String> =
+                expected.iter().map(|w| w.into_string()).collect();
+            assert_eq!(( input, words ), ( input, Ok(expected) ));
+        }
+    }
+
+    /// Inputs for which `shlex.split` raises ValueError.
+    #[test]
+    fn fails_where_python_shlex_split_does() {
+        let words = |l: &str| python_shlex_tokeniser().add_string(l)
+                                                      .into_strings();
+
+        // No closing quotation
+        assert_eq!(words("'a"), Err(UnmatchedQuote(0)));
+        assert_eq!(words("a \"b"), Err(UnmatchedQuote(2)));
+        assert_eq!(words("\"a\\\""), Err(UnmatchedQuote(0)));
+
+        // No escaped character
+        assert_eq!(words("\\"), Err(UnfinishedEscape(0)));
+        assert_eq!(words("a\\"), Err(UnfinishedEscape(1)));
+    }
+}
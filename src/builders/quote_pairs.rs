@@ -0,0 +1,225 @@
+//! Builder for tokenisers whose quotes are read from a table.
+#![experimental]
+
+use std::collections::hashmap::HashMap;
+use std::error;
+use std::fmt;
+
+use builders::types::{
+    StockEscapeMap,
+    StockEscapeScheme,
+    StockQuoteMap,
+    StockTokeniser
+};
+use escape_scheme::LiteralEscape;
+use tokeniser::{ Tokeniser, QuoteMode };
+use tokeniser::{ IgnoreEscapes, ParseEscapes, FoldWhitespace, DoubledEscape };
+
+
+/// An error raised when a quote pair table cannot be read.
+///
+/// Each field is the character offset of the malformed part of the table.
+#[deriving(Clone, Eq, PartialEq)]
+pub enum QuotePairParseError {
+    /// A quote pair was not of the form `O:C(Mode)`.
+    MalformedQuotePair(uint),
+
+    /// A quote pair named a quote mode that does not exist.
+    UnknownQuoteMode(uint),
+
+    /// A quote pair used an opening character already used by an earlier
+    /// pair.
+    DuplicateOpeningQuote(uint)
+}
+
+impl error::Error for QuotePairParseError {
+    fn description(&self) -> &str {
+        match *self {
+            MalformedQuotePair(..)    => "malformed quote pair",
+            UnknownQuoteMode(..)      => "unknown quote mode",
+            DuplicateOpeningQuote(..) => "opening quote used twice"
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        None
+    }
+}
+
+impl fmt::Show for QuotePairParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use std::error::Error;
+
+        match *self {
+            MalformedQuotePair(at) | UnknownQuoteMode(at)
+                | DuplicateOpeningQuote(at) =>
+                write!(f, "{} at character {}", self.description(), at)
+        }
+    }
+}
+
+
+impl Tokeniser<StockQuoteMap, StockEscapeMap, StockEscapeScheme> {
+    /// Creates a Tokeniser whose quotes are given by a table.
+    ///
+    /// The table is a comma-separated list of quote pairs, each written
+    /// `O:C(Mode)`: the opening character, a colon, the closing character,
+    /// and the name of the quote mode in brackets.  The modes are
+    /// `IgnoreEscapes`, `ParseEscapes`, `FoldWhitespace` and
+    /// `DoubledEscape`.  An empty table gives a Tokeniser without quotes.
+    ///
+    /// As in `shell_style_tokeniser`, `\` escapes the next character
+    /// outside quotes and in quotes that parse escapes.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The table of quote pairs.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the Tokeniser if the table was valid, and a
+    /// QuotePairParseError naming the malformed part of the table otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::Tokeniser;
+    /// use russet::builders::quote_pairs::UnknownQuoteMode;
+    ///
+    /// let table = "\":\"(ParseEscapes),':'(IgnoreEscapes)";
+    /// let tok = Tokeniser::with_quote_pairs_from_str(table).unwrap();
+    /// let tok2 = tok.add_line("a\\ b \"c\\\"\" 'd\\'");
+    /// assert_eq!(tok2.into_strings(), Ok(vec!("a b".into_string(),
+    ///                                         "c\"".into_string(),
+    ///                                         "d\\".into_string())));
+    ///
+    /// assert_eq!(Tokeniser::with_quote_pairs_from_str("<:>(Angles)").err(),
+    ///            Some(UnknownQuoteMode(4)));
+    /// ```
+    #[experimental]
+    pub fn with_quote_pairs_from_str(table: &str)
+      -> Result<StockTokeniser, QuotePairParseError> {
+        let quote_map = try!(parse_quote_pairs(table));
+        let escape_map: StockEscapeMap =
+            vec![ ( '\\', LiteralEscape ) ].move_iter().collect();
+        Ok(Tokeniser::new(quote_map, escape_map))
+    }
+}
+
+
+/// Reads a table of quote pairs into a quote map.
+fn parse_quote_pairs(table: &str)
+  -> Result<StockQuoteMap, QuotePairParseError> {
+    let chars: Vec<char> = table.chars().collect();
+    let chars = chars.as_slice();
+    let mut quote_map: StockQuoteMap = HashMap::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        // A pair is at least `O:C(` and one character of mode name.
+        let start = pos;
+        if chars.len() < start + 5
+           || chars[start + 1] != ':'
+           || chars[start + 3] != '(' {
+            return Err(MalformedQuotePair(start));
+        }
+
+        let name_start = start + 4;
+        let name_end = match chars.slice_from(name_start)
+                                  .iter()
+                                  .position(|&c| c == ')') {
+            Some(len) if 0 < len => name_start + len,
+            _                    => return Err(MalformedQuotePair(start))
+        };
+        let name = String::from_chars(chars.slice(name_start, name_end));
+        let mode = match quote_mode(name.as_slice()) {
+            Some(m) => m,
+            None    => return Err(UnknownQuoteMode(name_start))
+        };
+
+        let ( open, close ) = ( chars[start], chars[start + 2] );
+        if quote_map.contains_key(&open) {
+            return Err(DuplicateOpeningQuote(start));
+        }
+        quote_map.insert(open, ( close, mode ));
+
+        // Pairs are separated by commas, and there is none after the last.
+        pos = name_end + 1;
+        if pos < chars.len() {
+            if chars[pos] != ',' || pos + 1 == chars.len() {
+                return Err(MalformedQuotePair(pos));
+            }
+            pos += 1;
+        }
+    }
+
+    Ok(quote_map)
+}
+
+
+/// Finds the quote mode with the given name, if any.
+fn quote_mode(name: &str) -> Option<QuoteMode<StockEscapeScheme>> {
+    match name {
+        "IgnoreEscapes"  => Some(IgnoreEscapes),
+        "ParseEscapes"   => Some(ParseEscapes),
+        "FoldWhitespace" => Some(FoldWhitespace),
+        "DoubledEscape"  => Some(DoubledEscape),
+        _                => None
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use builders::shell_style_tokeniser;
+    use tokeniser::{ Tokeniser, UnmatchedQuote };
+    use super::{ MalformedQuotePair, UnknownQuoteMode };
+    use super::DuplicateOpeningQuote;
+
+    #[test]
+    fn quote_pairs_match_shell_style() {
+        let table = "\":\"(ParseEscapes),':'(IgnoreEscapes)";
+        let line = "a \"b\\\" c\" 'd\\' e\\ f";
+        let tok = Tokeniser::with_quote_pairs_from_str(table).unwrap();
+        assert_eq!(tok.add_line(line).into_strings(),
+                   shell_style_tokeniser().add_line(line).into_strings());
+    }
+
+    #[test]
+    fn quote_pairs_asymmetric_and_punctuation() {
+        let table = "<:>(IgnoreEscapes),,:,(DoubledEscape)";
+        let tok = Tokeniser::with_quote_pairs_from_str(table).unwrap();
+        assert_eq!(tok.add_line("<a b> ,c,,d,").into_strings(),
+                   Ok(vec![ "a b".into_string(), "c,d".into_string() ]));
+
+        let tok = Tokeniser::with_quote_pairs_from_str(table).unwrap();
+        assert_eq!(tok.add_line("x <y").into_strings(),
+                   Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn quote_pairs_empty_table() {
+        let tok = Tokeniser::with_quote_pairs_from_str("").unwrap();
+        assert_eq!(tok.add_line("'a b'").into_strings(),
+                   Ok(vec![ "'a".into_string(), "b'".into_string() ]));
+    }
+
+    #[test]
+    fn quote_pairs_errors() {
+        let parse = |table: &str| {
+            Tokeniser::with_quote_pairs_from_str(table).err()
+        };
+        assert_eq!(parse("\"\"(ParseEscapes)"), Some(MalformedQuotePair(0)));
+        assert_eq!(parse("\":\"ParseEscapes"), Some(MalformedQuotePair(0)));
+        assert_eq!(parse("\":\"(ParseEscapes"), Some(MalformedQuotePair(0)));
+        assert_eq!(parse("\":\"()"), Some(MalformedQuotePair(0)));
+        assert_eq!(parse("\":\"(ParseEscapes),"),
+                   Some(MalformedQuotePair(17)));
+        assert_eq!(parse("\":\"(ParseEscapes);':'(IgnoreEscapes)"),
+                   Some(MalformedQuotePair(17)));
+        assert_eq!(parse("':'(IgnoreEscapes),\":\"(Parse)"),
+                   Some(UnknownQuoteMode(23)));
+        assert_eq!(parse("':'(IgnoreEscapes),':'(ParseEscapes)"),
+                   Some(DuplicateOpeningQuote(19)));
+    }
+}
@@ -7,6 +7,7 @@ use builders::types::{
     StockTokeniser
 };
 use escape_scheme::LiteralEscape;
+use quote::is_shell_safe;
 use tokeniser::{ Tokeniser, IgnoreEscapes, ParseEscapes };
 
 
@@ -43,28 +44,121 @@ pub fn shell_style_tokeniser() -> StockTokeniser {
 }
 
 
+/// Creates a Tokeniser that provides shell-style quoting and comments.
+///
+/// This is `shell_style_tokeniser`, where a `#` at the start of a word
+/// starts a comment running to the end of the line.
+///
+/// # Return value
+///
+/// A Tokeniser with shell-style quoting and comments.
+///
+/// # Example
+///
+/// ```rust
+/// use russet::shell_style_tokeniser_with_comments;
+///
+/// let tok = shell_style_tokeniser_with_comments();
+/// let tok2 = tok.add_line("echo hi \"#1\" # greet");
+/// assert_eq!(tok2.into_strings(), Ok(vec!("echo".into_string(),
+///                                         "hi".into_string(),
+///                                         "#1".into_string())));
+/// ```
+#[experimental]
+pub fn shell_style_tokeniser_with_comments() -> StockTokeniser {
+    shell_style_tokeniser().with_comment_chars(vec![ '#' ])
+}
+
+
+/// Quotes a string so that `shell_style_tokeniser` reads it as one word.
+///
+/// The string is put in single quotes, with each single quote in it
+/// written as `'\''`: a closing quote, an escaped quote, and an opening
+/// quote.  This is also safe to pass to a POSIX shell.  For the shortest
+/// quoting, see `shell_quoter`.
+///
+/// # Return value
+///
+/// The quoted string.
+///
+/// # Example
+///
+/// ```rust
+/// use russet::shell_quote;
+///
+/// assert_eq!(shell_quote("it's here"), "'it'\\''s here'".into_string());
+/// assert_eq!(shell_quote(""), "''".into_string());
+/// ```
+#[experimental]
+pub fn shell_quote(word: &str) -> String {
+    let mut quoted = String::from_char(1, '\'');
+    for c in word.chars() {
+        match c {
+            '\'' => quoted.push_str("'\\''"),
+            c    => quoted.push_char(c)
+        }
+    }
+    quoted.push_char('\'');
+    quoted
+}
+
+
+/// Joins words into a line that `shell_style_tokeniser` reads back into
+/// the same words.
+///
+/// Words made only of characters that are safe in a shell word are left
+/// bare; any other word, including the empty word, is quoted with
+/// `shell_quote`.  The words are separated by single spaces.
+///
+/// # Return value
+///
+/// The joined line.
+///
+/// # Example
+///
+/// ```rust
+/// use russet::shell_join;
+///
+/// assert_eq!(shell_join([ "ls", "-la", "my file" ]),
+///            "ls -la 'my file'".into_string());
+/// ```
+#[experimental]
+pub fn shell_join<T: Str>(words: &[T]) -> String {
+    let quoted: Vec<String> = words.iter().map(|w| {
+        let word = w.as_slice();
+        if !word.is_empty() && word.chars().all(is_shell_safe) {
+            word.into_string()
+        } else {
+            shell_quote(word)
+        }
+    }).collect();
+    quoted.connect(" ")
+}
+
+
 #[cfg(test)]
 mod test {
-    use super::shell_style_tokeniser;
+    use super::{ shell_style_tokeniser, shell_style_tokeniser_with_comments };
+    use super::{ shell_join, shell_quote };
     use line::LineTokeniser;
     use tokeniser::{ UnmatchedQuote, UnfinishedEscape };
 
     #[test]
     fn shell_style_unmatched_single_quote() {
         assert_eq!(shell_style_tokeniser.line("\'abcde"),
-                   Err(UnmatchedQuote));
+                   Err(UnmatchedQuote(0)));
     }
 
     #[test]
     fn shell_style_unmatched_double_quote() {
         assert_eq!(shell_style_tokeniser.line("\"abcde"),
-                   Err(UnmatchedQuote));
+                   Err(UnmatchedQuote(0)));
     }
 
     #[test]
     fn shell_style_unfinished_escape() {
         assert_eq!(shell_style_tokeniser.line("zxcvbn m\\"),
-                   Err(UnfinishedEscape));
+                   Err(UnfinishedEscape(8)));
     }
 
     #[test]
@@ -124,4 +218,74 @@ mod test {
         assert_eq!(shell_style_tokeniser.line("\'abc\\\nde\'"),
                    Ok(vec![ "abc\\\nde".into_string() ]));
     }
+
+    #[test]
+    fn shell_style_comment() {
+        assert_eq!(shell_style_tokeniser_with_comments.line(
+                       "echo hi # comment"),
+                   Ok(vec![ "echo".into_string(), "hi".into_string() ]));
+    }
+
+    #[test]
+    fn shell_style_quoted_comment() {
+        assert_eq!(shell_style_tokeniser_with_comments.line(
+                       "echo \"#notacomment\" \\#nor#this"),
+                   Ok(vec![ "echo".into_string(),
+                            "#notacomment".into_string(),
+                            "#nor#this".into_string() ]));
+    }
+
+    #[test]
+    fn shell_style_only_comment() {
+        assert_eq!(shell_style_tokeniser_with_comments.line("# echo hi"),
+                   Ok(vec![]));
+    }
+
+    #[test]
+    fn shell_style_comment_ends_at_newline() {
+        let tok = shell_style_tokeniser_with_comments();
+        assert_eq!(tok.add_string("a # 'b\nc").into_strings(),
+                   Ok(vec![ "a".into_string(), "c".into_string() ]));
+    }
+
+    #[test]
+    fn shell_quote_edge_cases() {
+        assert_eq!(shell_quote(""), "''".into_string());
+        for s in [ "a 'b' \"c\"", "'", "\\\n$x", "  " ].iter() {
+            assert_eq!(shell_style_tokeniser.line(shell_quote(*s).as_slice()),
+                       Ok(vec![ s.into_string() ]));
+        }
+    }
+
+    #[quickcheck]
+    fn shell_quote_round_trip(s: String) -> bool {
+        shell_style_tokeniser.line(shell_quote(s.as_slice()).as_slice())
+            == Ok(vec![ s ])
+    }
+
+    /// With LiteralEscape, an escaped space is part of the word.
+    #[test]
+    fn shell_style_literal_escape() {
+        assert_eq!(shell_style_tokeniser.line("word\\ 2"),
+                   Ok(vec![ "word 2".into_string() ]));
+    }
+
+    #[test]
+    fn shell_join_empty() {
+        let words: [&str, ..0] = [];
+        assert_eq!(shell_join(words), "".into_string());
+        assert_eq!(shell_join([ "a", "" ]), "a ''".into_string());
+    }
+
+    #[test]
+    fn shell_join_quotes_only_unsafe_words() {
+        assert_eq!(shell_join([ "cp", "-r", "a b", "it's", "$HOME", "x/y.z" ]),
+                   "cp -r 'a b' 'it'\\''s' '$HOME' x/y.z".into_string());
+    }
+
+    #[quickcheck]
+    fn shell_join_round_trip(words: Vec<String>) -> bool {
+        shell_style_tokeniser.line(shell_join(words.as_slice()).as_slice())
+            == Ok(words)
+    }
 }
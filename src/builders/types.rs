@@ -3,12 +3,13 @@
 
 use std::collections::hashmap::HashMap;
 
+use chunked::ChunkedTokeniser;
 use escape_scheme::SimpleEscapeScheme;
 use tokeniser::{ Tokeniser, QuoteMode };
 
 
 /// A type for quote-maps used by Russet builders.
-pub type StockQuoteMap = HashMap<char, ( char, QuoteMode )>;
+pub type StockQuoteMap = HashMap<char, ( char, QuoteMode<StockEscapeScheme> )>;
 
 
 /// A type for escape schemes used by Russet builders.
@@ -22,3 +23,8 @@ pub type StockEscapeMap = HashMap<char, StockEscapeScheme>;
 /// A type for tokenisers returned by Russet builders.
 pub type StockTokeniser =
     Tokeniser<StockQuoteMap, StockEscapeMap, StockEscapeScheme>;
+
+
+/// A type for chunked tokenisers wrapping Russet builders.
+pub type StockChunkedTokeniser =
+    ChunkedTokeniser<StockQuoteMap, StockEscapeMap, StockEscapeScheme>;
@@ -8,7 +8,7 @@ use builders::types::{
     StockQuoteMap,
     StockTokeniser
 };
-use tokeniser::Tokeniser;
+use tokeniser::{ Tokeniser, DoubledEscape, SeparatorChars };
 
 
 /// Creates a Tokeniser that doesn't support quoting or escaping.
@@ -40,9 +40,40 @@ pub fn whitespace_split_tokeniser() -> StockTokeniser {
 }
 
 
+/// Creates a Tokeniser that splits words at tabs, as in TSV files.
+///
+/// Each tab separates two fields, so empty fields are kept, and spaces are
+/// part of fields.  Fields may be quoted with ", inside which a doubled ""
+/// stands for a single ", as in `csv_tokeniser`.  There are no escapes.
+///
+/// # Return value
+///
+/// A Tokeniser splitting at tabs, with CSV-style quoting.
+///
+/// # Example
+///
+/// ```rust
+/// use russet::tab_split_tokeniser;
+///
+/// let tok = tab_split_tokeniser().add_line("hello world\t\t\"a\tb\"");
+/// assert_eq!(tok.into_strings(), Ok(vec!("hello world".into_string(),
+///                                        "".into_string(),
+///                                        "a\tb".into_string())));
+/// ```
+#[experimental]
+pub fn tab_split_tokeniser() -> StockTokeniser {
+    let quote_map: StockQuoteMap =
+        vec![ ( '\"', ( '\"', DoubledEscape ) ) ].move_iter().collect();
+    let escape_map: StockEscapeMap = HashMap::new();
+    Tokeniser::new_with_separator(quote_map, escape_map,
+                                  SeparatorChars(vec![ '\t' ]))
+        .with_preserve_empty_fields(true)
+}
+
+
 #[cfg(test)]
 mod test {
-    use super::whitespace_split_tokeniser;
+    use super::{ tab_split_tokeniser, whitespace_split_tokeniser };
     use line::LineTokeniser;
     use tokeniser::Error;
 
@@ -59,4 +90,37 @@ mod test {
 
         lhs == rhs
     }
+
+    #[test]
+    fn whitespace_split_readme_example() {
+        assert_eq!(whitespace_split_tokeniser.line("the quick brown fox"),
+                   Ok(vec![ "the".into_string(),
+                            "quick".into_string(),
+                            "brown".into_string(),
+                            "fox".into_string() ]));
+    }
+
+    /// With an empty escape map, the backslash is an ordinary character.
+    #[test]
+    fn whitespace_split_empty_escape_map_is_not_literal_escape() {
+        assert_eq!(whitespace_split_tokeniser.line("word\\ 2"),
+                   Ok(vec![ "word\\".into_string(), "2".into_string() ]));
+    }
+
+    #[test]
+    fn tab_split_keeps_spaces() {
+        assert_eq!(tab_split_tokeniser.line("hello world\there"),
+                   Ok(vec![ "hello world".into_string(),
+                            "here".into_string() ]));
+    }
+
+    #[test]
+    fn tab_split_quoted_and_empty_fields() {
+        let tok = tab_split_tokeniser().add_string("\t\"a\tb\"\"\"\t\t");
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "".into_string(),
+                            "a\tb\"".into_string(),
+                            "".into_string(),
+                            "".into_string() ]));
+    }
 }
@@ -0,0 +1,284 @@
+//! The _ChunkedTokeniser_ class, for tokenising chunks of bytes.
+#![experimental]
+
+use std::str;
+
+use escape_scheme::EscapeScheme;
+use tokeniser::{ BufferPolicy, Error, InvalidUtf8, QuoteMode, Tokeniser };
+
+
+/// A tokeniser that is fed chunks of UTF-8 bytes, and returns words as soon
+/// as they are complete.
+///
+/// Chunks may split characters; the bytes of a split character are held
+/// until the rest of it arrives.
+pub struct ChunkedTokeniser<Q, E, S> {
+    /// The Tokeniser doing the work.
+    tokeniser: Tokeniser<Q, E, S>,
+
+    /// How long the word being read may grow.
+    policy: BufferPolicy,
+
+    /// The bytes of an incomplete character at the end of the last chunk.
+    pending: Vec<u8>,
+
+    /// The number of bytes decoded so far.
+    byte_count: uint,
+
+    /// The error the ChunkedTokeniser has failed with, if any.
+    error: Option<Error>
+}
+
+impl<Q, E, S> ChunkedTokeniser<Q, E, S>
+    where Q: Map<char, ( char, QuoteMode<S> )>,
+          E: Map<char, S>,
+          S: EscapeScheme,
+          Q: Clone,
+          E: Clone,
+          S: Clone,
+          Q: Collection {
+    /// Creates a new ChunkedTokeniser.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokeniser` - The Tokeniser to feed decoded characters into.
+    /// * `policy`    - How long the word being read may grow.
+    ///
+    /// # Return value
+    ///
+    /// A new ChunkedTokeniser.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ ChunkedTokeniser, Unbounded, shell_style_tokeniser };
+    ///
+    /// let mut chunked =
+    ///     ChunkedTokeniser::new(shell_style_tokeniser(), Unbounded);
+    /// assert_eq!(chunked.feed(b"ls 'my f"), vec![ Ok("ls".into_string()) ]);
+    /// assert_eq!(chunked.feed(b"ile'"), vec![]);
+    /// assert_eq!(chunked.finish(), Ok(vec![ "my file".into_string() ]));
+    /// ```
+    pub fn new(tokeniser: Tokeniser<Q, E, S>, policy: BufferPolicy)
+      -> ChunkedTokeniser<Q, E, S> {
+        ChunkedTokeniser {
+            tokeniser: tokeniser,
+            policy: policy,
+            pending: vec![],
+            byte_count: 0,
+            error: None
+        }
+    }
+
+    /// Feeds a chunk of bytes into the ChunkedTokeniser.
+    ///
+    /// # Return value
+    ///
+    /// The words completed by this chunk, each as an Ok item.  A word
+    /// outgrowing the buffer policy is a `LimitExceeded` item, after which
+    /// reading resumes on the next line.  If the input so far is invalid,
+    /// the last item is the Error, and it is the only item for every later
+    /// chunk.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Result<String, Error>> {
+        let mut items = vec![];
+
+        if self.error.is_none() {
+            let mut bytes = vec![];
+            bytes.push_all(self.pending.as_slice());
+            bytes.push_all(chunk);
+            self.pending.clear();
+
+            let end = complete_len(bytes.as_slice());
+            let valid = valid_len(bytes.slice_to(end));
+
+            // valid_len guarantees this slice is valid UTF-8.
+            let decoded = str::from_utf8(bytes.slice_to(valid)).unwrap();
+            for chr in decoded.chars() {
+                self.tokeniser.push_char(chr);
+                match self.tokeniser.enforce_buffer_policy(&self.policy) {
+                    Err(e) => {
+                        items.push_all_move(self.drain_items());
+                        items.push(Err(e));
+                    },
+                    Ok(()) => ()
+                }
+            }
+
+            if valid < end {
+                self.error = Some(InvalidUtf8(self.byte_count + valid));
+            } else {
+                self.pending.push_all(bytes.slice_from(end));
+            }
+            self.byte_count += valid;
+
+            if self.error.is_none() {
+                self.error = self.tokeniser.error().map(|e| e.clone());
+            }
+        }
+
+        items.push_all_move(self.drain_items());
+        match self.error {
+            Some(ref e) => items.push(Err(e.clone())),
+            None        => ()
+        }
+        items
+    }
+
+    /// Retrieves the number of bytes allocated for the word currently being
+    /// read.
+    pub fn buffer_capacity(&self) -> uint {
+        self.tokeniser.buffer_capacity()
+    }
+
+    /// Destroys the ChunkedTokeniser, finishing the last word.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the words not yet returned by `feed` if the
+    /// input was valid and ended in a valid state, and an Error otherwise.
+    /// Input ending part of the way through a character is invalid.
+    pub fn finish(self) -> Result<Vec<String>, Error> {
+        match self.error {
+            Some(e) => Err(e),
+            None if !self.pending.is_empty() =>
+                Err(InvalidUtf8(self.byte_count)),
+            None => self.tokeniser.into_strings()
+        }
+    }
+
+    /// Removes the completed words from the Tokeniser, as Ok items.
+    fn drain_items(&mut self) -> Vec<Result<String, Error>> {
+        self.tokeniser.drain_completed().move_iter().map(|w| Ok(w)).collect()
+    }
+}
+
+
+/// Finds the length of `bytes` without any incomplete character at its end.
+fn complete_len(bytes: &[u8]) -> uint {
+    let len = bytes.len();
+
+    // A UTF-8 character is at most four bytes, so only the last three bytes
+    // can belong to an incomplete one.
+    for back in range(1u, 4) {
+        if back > len { break; }
+
+        let b = bytes[len - back];
+        if !is_continuation(b) {
+            let width = str::utf8_char_width(b);
+            return if width > back { len - back } else { len };
+        }
+    }
+    len
+}
+
+
+/// Finds the length of the longest valid UTF-8 prefix of `bytes`.
+fn valid_len(bytes: &[u8]) -> uint {
+    let mut i = 0u;
+    while i < bytes.len() {
+        let width = str::utf8_char_width(bytes[i]);
+        if width == 0
+           || i + width > bytes.len()
+           || str::from_utf8(bytes.slice(i, i + width)).is_none() {
+            break;
+        }
+        i += width;
+    }
+    i
+}
+
+
+/// Checks whether `b` is a UTF-8 continuation byte.
+fn is_continuation(b: u8) -> bool {
+    b & 0xC0 == 0x80
+}
+
+
+#[cfg(test)]
+mod test {
+    use builders::{ c_style_tokeniser, shell_style_tokeniser };
+    use builders::types::StockChunkedTokeniser;
+    use tokeniser::{ Error, BadEscape, InvalidUtf8, UnmatchedQuote };
+    use tokeniser::{ Bounded, LimitExceeded, Unbounded };
+    use super::ChunkedTokeniser;
+
+    /// Feeds `input` to a shell-style ChunkedTokeniser in chunks of `size`
+    /// bytes, collecting all of the words.
+    fn feed_in_chunks(input: &[u8], size: uint) -> Result<Vec<String>, Error> {
+        let mut chunked: StockChunkedTokeniser =
+            ChunkedTokeniser::new(shell_style_tokeniser(), Unbounded);
+        let mut words = vec![];
+        for chunk in input.chunks(size) {
+            for item in chunked.feed(chunk).move_iter() {
+                words.push(try!(item));
+            }
+        }
+        words.push_all_move(try!(chunked.finish()));
+        Ok(words)
+    }
+
+    /// Creates an unbounded shell-style ChunkedTokeniser.
+    fn unbounded() -> StockChunkedTokeniser {
+        ChunkedTokeniser::new(shell_style_tokeniser(), Unbounded)
+    }
+
+    #[test]
+    fn one_byte_at_a_time() {
+        let line = "échô 'a \"quoted\" wörd' \"and\\ another\" 🍎";
+        let expected = shell_style_tokeniser().add_line(line).into_strings();
+        assert_eq!(feed_in_chunks(line.as_bytes(), 1), expected);
+        assert_eq!(feed_in_chunks(line.as_bytes(), 3), expected);
+    }
+
+    #[test]
+    fn words_returned_when_complete() {
+        let mut chunked = unbounded();
+        assert_eq!(chunked.feed(b"ab c"), vec![ Ok("ab".into_string()) ]);
+        assert_eq!(chunked.feed(b"d e"), vec![ Ok("cd".into_string()) ]);
+        assert_eq!(chunked.finish(), Ok(vec![ "e".into_string() ]));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut chunked = unbounded();
+        assert_eq!(chunked.feed(b"ab c"), vec![ Ok("ab".into_string()) ]);
+        assert_eq!(chunked.feed(b"d\xFFe"), vec![ Err(InvalidUtf8(5)) ]);
+        assert_eq!(chunked.feed(b"f g"), vec![ Err(InvalidUtf8(5)) ]);
+    }
+
+    #[test]
+    fn truncated_character() {
+        let mut chunked = unbounded();
+        assert_eq!(chunked.feed(b"ab \xC3"), vec![ Ok("ab".into_string()) ]);
+        assert_eq!(chunked.finish(), Err(InvalidUtf8(3)));
+    }
+
+    #[test]
+    fn tokeniser_errors() {
+        let mut chunked = ChunkedTokeniser::new(c_style_tokeniser(), Unbounded);
+        assert_eq!(chunked.feed(b"a \\q b"),
+                   vec![ Ok("a".into_string()), Err(BadEscape) ]);
+
+        let mut chunked = ChunkedTokeniser::new(c_style_tokeniser(), Unbounded);
+        assert_eq!(chunked.feed(b"a \"b c"), vec![ Ok("a".into_string()) ]);
+        assert_eq!(chunked.finish(), Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn bounded_endless_quote() {
+        let mut chunked =
+            ChunkedTokeniser::new(shell_style_tokeniser(), Bounded(64));
+        assert_eq!(chunked.feed(b"ab 'cd"), vec![ Ok("ab".into_string()) ]);
+
+        let mut items = vec![];
+        for _ in range(0u, 100) {
+            items.push_all_move(chunked.feed(&[ b'x', ..50 ]));
+            assert!(chunked.buffer_capacity() <= 128);
+        }
+        assert_eq!(items, vec![ Err(LimitExceeded(64)) ]);
+
+        assert_eq!(chunked.feed(b"x'\nef 'g"), vec![ Ok("ef".into_string()) ]);
+        assert_eq!(chunked.feed(b"h'"), vec![]);
+        assert_eq!(chunked.finish(), Ok(vec![ "gh".into_string() ]));
+    }
+}
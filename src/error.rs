@@ -0,0 +1,333 @@
+//! The _RussetError_ class, gathering every error Russet can raise.
+#![experimental]
+
+use std::error;
+use std::error::FromError;
+use std::fmt;
+use std::io::IoError;
+
+use builders::quote_pairs::QuotePairParseError;
+use builders::quote_pairs::{
+    DuplicateOpeningQuote,
+    MalformedQuotePair,
+    UnknownQuoteMode
+};
+use policy::PolicyViolation;
+use policy::{ ControlEscape, ForbiddenBare, Malformed };
+use policy::{ TooManyWords, WordTooLong };
+use quote::{ TableError, UnrepresentableWord, WrapError };
+use quote::{ CellTooWide, CellUnrepresentable, SeparatorUnrepresentable };
+use quote::{ WordTooWide, WordUnrepresentable };
+use tokeniser::Error;
+use tokeniser::{
+    BadEscape,
+    DeadlineExceeded,
+    InvalidUtf8,
+    LimitExceeded,
+    LineTooLong,
+    NulInPath,
+    QuoteNotAtFieldStart,
+    TotalLengthExceeded,
+    TrailingGarbageAfterQuote,
+    UnfinishedEscape,
+    UnmatchedQuote
+};
+
+
+/// Any error raised by Russet.
+///
+/// Russet's own functions return their specific error types: tokenising
+/// raises Error, quoting raises UnrepresentableWord, WrapError or
+/// TableError, reading raises IoError, reading a quote pair table raises
+/// QuotePairParseError, and checking a line against a Policy raises
+/// PolicyViolation.  RussetError exists for applications that pass all of
+/// these up to one place; each converts into it with `FromError`, so
+/// `try!` can be used directly.
+///
+/// Each error has a stable numeric code, grouped by kind:
+///
+/// * 1xx - The input was malformed (`Input`);
+/// * 2xx - A tokeniser configuration was malformed (`Config`);
+/// * 3xx - Input could not be read (`Io`);
+/// * 4xx - The input exceeded a configured limit (`Limit`);
+/// * 5xx - Words could not be quoted or laid out (`Unrepresentable`,
+///   `Wrap` and `Table`);
+/// * 6xx - A line broke a Policy (`Violation`).
+#[deriving(Clone, Eq, PartialEq)]
+pub enum RussetError {
+    /// The input was malformed.
+    Input(Error),
+
+    /// A word could not be quoted.
+    Unrepresentable(UnrepresentableWord),
+
+    /// Words could not be wrapped into lines.
+    Wrap(WrapError),
+
+    /// Words could not be laid out as a table.
+    Table(TableError),
+
+    /// Input could not be read.
+    Io(IoError),
+
+    /// The input exceeded a configured limit.
+    ///
+    /// The Error is a LineTooLong, TotalLengthExceeded, LimitExceeded or
+    /// DeadlineExceeded.
+    Limit(Error),
+
+    /// A tokeniser configuration was malformed.
+    Config(QuotePairParseError),
+
+    /// A line broke a Policy.
+    Violation(PolicyViolation)
+}
+
+impl RussetError {
+    /// Retrieves the stable numeric code of the error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::error::FromError;
+    /// use russet::RussetError;
+    /// use russet::tokeniser::{ LineTooLong, UnmatchedQuote };
+    ///
+    /// let input: RussetError = FromError::from_error(UnmatchedQuote(3));
+    /// assert_eq!(input.code(), 101);
+    /// let limit: RussetError = FromError::from_error(LineTooLong(80, 81));
+    /// assert_eq!(limit.code(), 401);
+    /// ```
+    pub fn code(&self) -> uint {
+        match *self {
+            Input(ref e) | Limit(ref e) => match *e {
+                UnmatchedQuote(..)       => 101,
+                UnfinishedEscape(..)     => 102,
+                BadEscape                => 103,
+                QuoteNotAtFieldStart(..) => 104,
+                InvalidUtf8(..)          => 105,
+                NulInPath(..)            => 106,
+                TrailingGarbageAfterQuote(..) => 107,
+                LineTooLong(..)          => 401,
+                TotalLengthExceeded(..)  => 402,
+                LimitExceeded(..)        => 403,
+                DeadlineExceeded(..)     => 404
+            },
+            Config(MalformedQuotePair(..))    => 201,
+            Config(UnknownQuoteMode(..))      => 202,
+            Config(DuplicateOpeningQuote(..)) => 203,
+            Io(..)                            => 300,
+            Unrepresentable(..)               => 501,
+            Wrap(WordTooWide(..))             => 502,
+            Wrap(WordUnrepresentable(..))     => 503,
+            Table(CellTooWide(..))            => 504,
+            Table(CellUnrepresentable(..))    => 505,
+            Table(SeparatorUnrepresentable)   => 506,
+            Violation(ref v) => match v.rule {
+                ForbiddenBare(..) => 601,
+                ControlEscape(..) => 602,
+                TooManyWords(..)  => 603,
+                WordTooLong(..)   => 604,
+                Malformed(..)     => 605
+            }
+        }
+    }
+}
+
+impl error::Error for RussetError {
+    fn description(&self) -> &str {
+        match *self {
+            Input(..)           => "malformed input",
+            Unrepresentable(..) | Wrap(..) | Table(..) =>
+                "words cannot be quoted",
+            Io(..)              => "input cannot be read",
+            Limit(..)           => "input exceeds limit",
+            Config(..)          => "malformed configuration",
+            Violation(..)       => "input breaks policy"
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        Some(match *self {
+            Input(ref e) | Limit(ref e) => e as &error::Error,
+            Unrepresentable(ref e)      => e as &error::Error,
+            Wrap(ref e)                 => e as &error::Error,
+            Table(ref e)                => e as &error::Error,
+            Io(ref e)                   => e as &error::Error,
+            Config(ref e)               => e as &error::Error,
+            Violation(ref e)            => e as &error::Error
+        })
+    }
+}
+
+impl fmt::Show for RussetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use std::error::Error;
+
+        let prefix = self.description();
+        match *self {
+            Input(ref e) | Limit(ref e) => write!(f, "{}: {}", prefix, e),
+            Unrepresentable(ref e)      => write!(f, "{}: {}", prefix, e),
+            Wrap(ref e)                 => write!(f, "{}: {}", prefix, e),
+            Table(ref e)                => write!(f, "{}: {}", prefix, e),
+            Io(ref e)                   => write!(f, "{}: {}", prefix, e),
+            Config(ref e)               => write!(f, "{}: {}", prefix, e),
+            Violation(ref e)            => write!(f, "{}: {}", prefix, e)
+        }
+    }
+}
+
+impl FromError<Error> for RussetError {
+    /// Converts a tokeniser Error, sorting limit errors from malformed
+    /// input.
+    fn from_error(err: Error) -> RussetError {
+        match err {
+            LineTooLong(..) | TotalLengthExceeded(..) | LimitExceeded(..)
+                | DeadlineExceeded(..) => Limit(err),
+            _ => Input(err)
+        }
+    }
+}
+
+impl FromError<UnrepresentableWord> for RussetError {
+    fn from_error(err: UnrepresentableWord) -> RussetError {
+        Unrepresentable(err)
+    }
+}
+
+impl FromError<WrapError> for RussetError {
+    fn from_error(err: WrapError) -> RussetError {
+        Wrap(err)
+    }
+}
+
+impl FromError<TableError> for RussetError {
+    fn from_error(err: TableError) -> RussetError {
+        Table(err)
+    }
+}
+
+impl FromError<IoError> for RussetError {
+    fn from_error(err: IoError) -> RussetError {
+        Io(err)
+    }
+}
+
+impl FromError<QuotePairParseError> for RussetError {
+    fn from_error(err: QuotePairParseError) -> RussetError {
+        Config(err)
+    }
+}
+
+impl FromError<PolicyViolation> for RussetError {
+    fn from_error(err: PolicyViolation) -> RussetError {
+        Violation(err)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::error::{ Error, FromError };
+    use std::io::{ IoError, MemReader };
+
+    use builders::shell_style_tokeniser;
+    use builders::quote_pairs::UnknownQuoteMode;
+    use policy::{ ForbiddenBare, Malformed, PolicyViolation };
+    use quote::{ UnrepresentableWord, WordTooWide, SeparatorUnrepresentable };
+    use tokeniser::{ BadEscape, InvalidUtf8, LimitExceeded, LineTooLong };
+    use tokeniser::{ DeadlineExceeded, NulInPath, Unbounded };
+    use tokeniser::{ QuoteNotAtFieldStart, TotalLengthExceeded };
+    use tokeniser::TrailingGarbageAfterQuote;
+    use tokeniser::{ Span, UnfinishedEscape, UnmatchedQuote };
+    use super::{ RussetError, Input, Io, Limit, Table, Unrepresentable };
+    use super::{ Config, Violation, Wrap };
+
+    /// Tokenises `line`, converting any error with `try!`.
+    fn words(line: &str) -> Result<Vec<String>, RussetError> {
+        let reader = MemReader::new(line.as_bytes().to_vec());
+        let tok = try!(shell_style_tokeniser().add_reader(reader, Unbounded));
+        Ok(try!(tok.into_strings()))
+    }
+
+    #[test]
+    fn from_conversions() {
+        assert_eq!(words("a 'b"), Err(Input(UnmatchedQuote(2))));
+        assert_eq!(words("a b"),
+                   Ok(vec![ "a".into_string(), "b".into_string() ]));
+
+        let limit: RussetError = FromError::from_error(LimitExceeded(64));
+        assert_eq!(limit, Limit(LimitExceeded(64)));
+        let wrap: RussetError = FromError::from_error(WordTooWide(2));
+        assert_eq!(wrap, Wrap(WordTooWide(2)));
+        let table: RussetError =
+            FromError::from_error(SeparatorUnrepresentable);
+        assert_eq!(table, Table(SeparatorUnrepresentable));
+        let config: RussetError = FromError::from_error(UnknownQuoteMode(4));
+        assert_eq!(config, Config(UnknownQuoteMode(4)));
+        assert_eq!(config.code(), 202);
+    }
+
+    #[test]
+    fn io_conversion() {
+        let err = IoError::from_errno(2, false);
+        let io: RussetError = FromError::from_error(err.clone());
+        assert_eq!(io, Io(err));
+        assert_eq!(io.code(), 300);
+    }
+
+    #[test]
+    fn policy_conversion() {
+        let violation = PolicyViolation {
+            rule: ForbiddenBare(";".into_string()),
+            span: Span { start: 1, end: 2 }
+        };
+        let policy: RussetError = FromError::from_error(violation.clone());
+        assert_eq!(policy, Violation(violation.clone()));
+        assert_eq!(policy.code(), 601);
+        assert_eq!(format!("{}", policy),
+                   format!("input breaks policy: {}", violation));
+
+        let malformed = PolicyViolation {
+            rule: Malformed(UnmatchedQuote(0)),
+            span: Span { start: 0, end: 2 }
+        };
+        assert_eq!(Violation(malformed).code(), 605);
+    }
+
+    #[test]
+    fn show_wraps_inner_error() {
+        let input: RussetError = FromError::from_error(UnmatchedQuote(5));
+        assert_eq!(format!("{}", input),
+                   "malformed input: unmatched opening quote at character 5"
+                       .into_string());
+        assert_eq!(input.cause().unwrap().description(),
+                   "unmatched opening quote");
+
+        let limit: RussetError = FromError::from_error(LineTooLong(3, 4));
+        assert_eq!(format!("{}", limit),
+                   format!("input exceeds limit: {}", LineTooLong(3, 4)));
+    }
+
+    #[test]
+    fn codes_are_stable() {
+        let codes: Vec<uint> = [
+            UnmatchedQuote(0), UnfinishedEscape(0), BadEscape,
+            QuoteNotAtFieldStart(0), InvalidUtf8(0), NulInPath(0),
+            LineTooLong(0, 0), TotalLengthExceeded(0, 0), LimitExceeded(0),
+            DeadlineExceeded(0)
+        ].iter().map(|e| {
+            let err: RussetError = FromError::from_error(e.clone());
+            err.code()
+        }).collect();
+        assert_eq!(codes,
+                   vec![ 101, 102, 103, 104, 105, 106, 401, 402, 403, 404 ]);
+
+        let trailing: RussetError =
+            FromError::from_error(TrailingGarbageAfterQuote('x', 0));
+        assert_eq!(trailing.code(), 107);
+
+        assert_eq!(Unrepresentable(UnrepresentableWord(0)).code(), 501);
+        assert_eq!(Table(SeparatorUnrepresentable).code(), 506);
+    }
+}
@@ -1,6 +1,7 @@
 //! The EscapeScheme trait and implementations.
 #![experimental]
 
+use std::char;
 use std::collections::hashmap::HashMap;
 
 
@@ -34,11 +35,79 @@ pub trait EscapeScheme {
     /// assert_eq!(c_escapes().escape('r'), Some('\r'));
     /// ```
     fn escape(&self, chr: char) -> Option<char>;
+
+    /// Attempts to map an escape sequence, `seq`, to its literal substitute.
+    ///
+    /// `seq` holds every character read since the escape leader.  The
+    /// Tokeniser calls this once per character, until the result is not
+    /// `Incomplete`.
+    ///
+    /// The default implementation handles single-character sequences using
+    /// `escape`, and is suitable for any scheme without multiple-character
+    /// sequences.
+    ///
+    /// # Return value
+    ///
+    /// An EscapeResult, which is `Escaped(x)` when `seq` is a valid escape
+    /// sequence with substitute `x`, `Incomplete` when `seq` is the start of
+    /// a valid escape sequence, and `Invalid` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use russet::escape_scheme::EscapeScheme;
+    /// use russet::escape_scheme::{ Escaped, Incomplete, UnicodeEscape };
+    /// use russet::escape_scheme::c_escapes;
+    ///
+    /// let ue = UnicodeEscape(box c_escapes());
+    /// assert_eq!(ue.escape_seq(&['u', '0', '0']), Incomplete);
+    /// assert_eq!(ue.escape_seq(&['u', '0', '0', '4', '1']), Escaped('A'));
+    /// assert_eq!(ue.escape_seq(&['n']), Escaped('\n'));
+    /// ```
+    fn escape_seq(&self, seq: &[char]) -> EscapeResult {
+        escape_single(self, seq)
+    }
+
+    /// Attempts to finish an incomplete escape sequence, `seq`, at the end
+    /// of input.
+    ///
+    /// This is used for sequences of variable length, such as octal
+    /// escapes, which are ended by the first character that cannot extend
+    /// them.  The default implementation never finishes a sequence.
+    ///
+    /// # Return value
+    ///
+    /// An Option, which is `Some(x)` when `seq` is a valid escape sequence
+    /// on its own with substitute `x`, and `None` otherwise.
+    #[allow(unused_variable)]
+    fn escape_end(&self, seq: &[char]) -> Option<char> {
+        None
+    }
+}
+
+
+/// The result of mapping an escape sequence to its literal substitute.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub enum EscapeResult {
+    /// The sequence is complete, and has the given substitute.
+    Escaped(char),
+
+    /// The sequence without its last character is complete, and has the
+    /// given substitute.  The last character is not part of the sequence,
+    /// and should be read as if it were not escaped.
+    EscapedBefore(char),
+
+    /// The sequence is the start of a valid escape sequence, but more
+    /// characters are needed to finish it.
+    Incomplete,
+
+    /// The sequence is not a valid escape sequence.
+    Invalid
 }
 
 
 /// An enumeration of simple escape schemes.
-#[deriving(Clone)]
+#[deriving(Clone, Eq, PartialEq, Show)]
 pub enum SimpleEscapeScheme<M> {
     /// Any character prefixed by an escape leader is treated as its literal
     /// value.  This is similar to the shell style of character escaping.
@@ -46,20 +115,155 @@ pub enum SimpleEscapeScheme<M> {
 
     /// Any character prefixed by an escape leader is looked up in the map,
     /// and the corresponding entry substituted for the escape sequence.
-    MapEscape(M)
+    ///
+    /// Characters missing from the map, including the leader itself, are
+    /// bad escapes; map the leader to itself to let it be escaped.
+    MapEscape(M),
+
+    /// Any character prefixed by an escape leader is looked up in the map,
+    /// as in `MapEscape`, but characters missing from the map are not
+    /// escaped at all: the leader, given here, stands for itself, and the
+    /// character is read as if it had not been escaped.  This is how a
+    /// POSIX shell reads `\` inside double quotes.
+    PartialEscape(char, M),
+
+    /// A `u` followed by exactly four hexadecimal digits is substituted by
+    /// the character with that code point, as in `\u0041`.  Any other
+    /// sequence is handled by the inner scheme.
+    ///
+    /// Code points in the UTF-16 surrogate range are invalid.
+    UnicodeEscape(Box<SimpleEscapeScheme<M>>),
+
+    /// An `x` followed by exactly two hexadecimal digits is substituted by
+    /// the character with that code point, as in `\x41`.  Any other
+    /// sequence is handled by the inner scheme.
+    ///
+    /// Only ASCII code points (up to `\x7F`) are valid, as the meaning of
+    /// higher values depends on the encoding.
+    HexEscape(Box<SimpleEscapeScheme<M>>),
+
+    /// An octal digit followed by up to two more octal digits is substituted
+    /// by the character with that code point, as in `\101`.  The sequence
+    /// ends at the third digit or the first character that is not an octal
+    /// digit, whichever is first.  Any other sequence is handled by the
+    /// inner scheme.
+    OctalEscape(Box<SimpleEscapeScheme<M>>)
 }
 
 impl<M> EscapeScheme for SimpleEscapeScheme<M> where M: Map<char, char> {
     fn escape(&self, chr: char) -> Option<char> {
         match *self {
             LiteralEscape => Some(chr),
-            MapEscape(ref map) => map.find(&chr).map(|c| c.clone())
+            MapEscape(ref map) | PartialEscape(_, ref map) =>
+                map.find(&chr).map(|c| c.clone()),
+            UnicodeEscape(_) if chr == 'u' => None,
+            UnicodeEscape(ref inner) => inner.escape(chr),
+            HexEscape(_) if chr == 'x' => None,
+            HexEscape(ref inner) => inner.escape(chr),
+            OctalEscape(_) if is_octal(chr) => escape_octal(&[chr]),
+            OctalEscape(ref inner) => inner.escape(chr)
+        }
+    }
+
+    fn escape_seq(&self, seq: &[char]) -> EscapeResult {
+        match *self {
+            UnicodeEscape(_) if seq.head() == Some(&'u') =>
+                escape_hex(seq.tail(), 4, 0x10FFFF),
+            UnicodeEscape(ref inner) => inner.escape_seq(seq),
+            HexEscape(_) if seq.head() == Some(&'x') =>
+                escape_hex(seq.tail(), 2, 0x7F),
+            HexEscape(ref inner) => inner.escape_seq(seq),
+            OctalEscape(_) if seq.head().map_or(false, |c| is_octal(*c)) =>
+                match escape_octal(seq) {
+                    Some(c) if seq.len() == 3 => Escaped(c),
+                    Some(_)                   => Incomplete,
+                    None => escape_octal(seq.init())
+                                .map(|c| EscapedBefore(c))
+                                .unwrap_or(Invalid)
+                },
+            OctalEscape(ref inner) => inner.escape_seq(seq),
+            PartialEscape(leader, _) if seq.len() == 1 =>
+                match self.escape(seq[0]) {
+                    Some(c) => Escaped(c),
+                    None    => EscapedBefore(leader)
+                },
+            _ => escape_single(self, seq)
+        }
+    }
+
+    fn escape_end(&self, seq: &[char]) -> Option<char> {
+        match *self {
+            UnicodeEscape(_) if seq.head() == Some(&'u') => None,
+            UnicodeEscape(ref inner) => inner.escape_end(seq),
+            HexEscape(_) if seq.head() == Some(&'x') => None,
+            HexEscape(ref inner) => inner.escape_end(seq),
+            OctalEscape(_) if seq.head().map_or(false, |c| is_octal(*c)) =>
+                escape_octal(seq),
+            OctalEscape(ref inner) => inner.escape_end(seq),
+            _ => None
+        }
+    }
+}
+
+
+/// Maps a single-character escape sequence using `scheme`'s `escape`.
+fn escape_single<S: EscapeScheme>(scheme: &S, seq: &[char]) -> EscapeResult {
+    match ( seq.len(), seq.head().and_then(|c| scheme.escape(*c)) ) {
+        ( 1, Some(c) ) => Escaped(c),
+        _              => Invalid
+    }
+}
+
+
+/// Maps a sequence of `digits` hexadecimal digits to the character with
+/// that code point, which may be at most `max`.
+fn escape_hex(seq: &[char], digits: uint, max: u32) -> EscapeResult {
+    let mut value = 0u32;
+    for c in seq.iter() {
+        match c.to_digit(16) {
+            Some(d) => { value = value * 16 + d as u32; },
+            None    => { return Invalid; }
         }
     }
+
+    if seq.len() < digits {
+        Incomplete
+    } else if value > max {
+        Invalid
+    } else {
+        char::from_u32(value).map(|c| Escaped(c)).unwrap_or(Invalid)
+    }
+}
+
+
+/// Checks whether `chr` is an octal digit.
+fn is_octal(chr: char) -> bool {
+    chr.is_digit_radix(8)
+}
+
+
+/// Maps a sequence of octal digits to the character with that code point.
+///
+/// If `seq` is empty or contains any non-octal characters, the result is
+/// `None`.
+fn escape_octal(seq: &[char]) -> Option<char> {
+    if seq.is_empty() { return None; }
+
+    let mut value = 0u32;
+    for c in seq.iter() {
+        match c.to_digit(8) {
+            Some(d) => { value = value * 8 + d as u32; },
+            None    => { return None; }
+        }
+    }
+    char::from_u32(value)
 }
 
 
 /// A constructor for a C-style escape sequence.
+///
+/// This maps `\n`, `\r`, `\"`, `\'`, `\\` and `\t` to their C
+/// equivalents, and `\xHH` to the ASCII character with code point `HH`.
 pub fn c_escapes() -> SimpleEscapeScheme<HashMap<char, char>> {
     let map: HashMap<char, char> =
         vec![ ( 'n',  '\n' ),
@@ -68,5 +272,150 @@ pub fn c_escapes() -> SimpleEscapeScheme<HashMap<char, char>> {
               ( '\'', '\'' ),
               ( '\\', '\\' ),
               ( 't',  '\t' ) ].move_iter().collect();
-    MapEscape(map)
+    HexEscape(box MapEscape(map))
+}
+
+
+/// A constructor for a POSIX-style escape sequence.
+///
+/// This is the C-style escape sequence of `c_escapes`, with the addition of
+/// octal escapes such as `\101`.
+pub fn posix_escapes() -> SimpleEscapeScheme<HashMap<char, char>> {
+    OctalEscape(box c_escapes())
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::hashmap::HashMap;
+
+    use builders::c_style_tokeniser;
+    use builders::types::{ StockEscapeMap, StockQuoteMap };
+    use line::LineTokeniser;
+    use super::{ EscapeScheme, c_escapes, posix_escapes, UnicodeEscape };
+    use super::{ Escaped, EscapedBefore, PartialEscape };
+    use tokeniser::{ Tokeniser, Error, BadEscape, UnfinishedEscape };
+
+    fn unicode_line(line: &str) -> Result<Vec<String>, Error> {
+        let quote_map: StockQuoteMap = HashMap::new();
+        let escape_map: StockEscapeMap =
+            vec![ ( '\\', UnicodeEscape(box c_escapes()) ) ]
+                .move_iter().collect();
+        Tokeniser::new(quote_map, escape_map).add_line(line).into_strings()
+    }
+
+    #[test]
+    fn unicode_escape() {
+        assert_eq!(unicode_line("\\u0041\\u00e9 \\u263A"),
+                   Ok(vec![ "A\u00e9".into_string(), "\u263a".into_string() ]));
+    }
+
+    #[test]
+    fn unicode_escape_falls_back_to_inner() {
+        assert_eq!(unicode_line("a\\nb"), Ok(vec![ "a\nb".into_string() ]));
+    }
+
+    #[test]
+    fn unicode_escape_bad_hex() {
+        assert_eq!(unicode_line("\\u00g1"), Err(BadEscape));
+    }
+
+    #[test]
+    fn unicode_escape_surrogate() {
+        assert_eq!(unicode_line("\\uD800"), Err(BadEscape));
+        assert_eq!(unicode_line("\\udfff"), Err(BadEscape));
+    }
+
+    #[test]
+    fn unicode_escape_unfinished() {
+        assert_eq!(unicode_line("ab \\u00"), Err(UnfinishedEscape(3)));
+    }
+
+    #[test]
+    fn hex_escape() {
+        assert_eq!(c_style_tokeniser.line("\\x00 \\x7F \\x7f"),
+                   Ok(vec![ "\x00".into_string(),
+                            "\x7f".into_string(),
+                            "\x7f".into_string() ]));
+    }
+
+    #[test]
+    fn hex_escape_sequence() {
+        assert_eq!(c_style_tokeniser.line("\"\\x41\\x42\" \\x41c"),
+                   Ok(vec![ "AB".into_string(), "Ac".into_string() ]));
+    }
+
+    #[test]
+    fn hex_escape_non_ascii() {
+        assert_eq!(c_style_tokeniser.line("\\xFF"), Err(BadEscape));
+        assert_eq!(c_style_tokeniser.line("\\x80"), Err(BadEscape));
+    }
+
+    #[test]
+    fn hex_escape_bad_digit() {
+        assert_eq!(c_style_tokeniser.line("\\x4g"), Err(BadEscape));
+    }
+
+    fn posix_line(line: &str) -> Result<Vec<String>, Error> {
+        let quote_map: StockQuoteMap = HashMap::new();
+        let escape_map: StockEscapeMap =
+            vec![ ( '\\', posix_escapes() ) ].move_iter().collect();
+        Tokeniser::new(quote_map, escape_map).add_line(line).into_strings()
+    }
+
+    #[test]
+    fn octal_escape() {
+        assert_eq!(posix_line("\\101\\102 \\0"),
+                   Ok(vec![ "AB".into_string(), "\x00".into_string() ]));
+    }
+
+    #[test]
+    fn octal_escape_stops_at_non_octal() {
+        assert_eq!(posix_line("\\18 \\7x"),
+                   Ok(vec![ "\x018".into_string(), "\x07x".into_string() ]));
+        assert_eq!(posix_line("\\1\\n"),
+                   Ok(vec![ "\x01\n".into_string() ]));
+    }
+
+    #[test]
+    fn octal_escape_ends_word() {
+        assert_eq!(posix_line("\\12 a"),
+                   Ok(vec![ "\n".into_string(), "a".into_string() ]));
+    }
+
+    #[test]
+    fn octal_escape_at_end_of_input() {
+        assert_eq!(posix_line("a\\7"), Ok(vec![ "a\x07".into_string() ]));
+    }
+
+    #[test]
+    fn octal_escape_falls_back_to_inner() {
+        assert_eq!(posix_line("\\t\\x41"), Ok(vec![ "\tA".into_string() ]));
+    }
+
+    /// Every three-digit octal escape should map to its code point.
+    #[quickcheck]
+    fn octal_escape_roundtrip(value: u8) -> bool {
+        let line = format!("\\{:03o}", value);
+        posix_line(line.as_slice())
+            == Ok(vec![ String::from_char(1, value as char) ])
+    }
+
+    /// The stock schemes map the leader to itself, so `\\` is a backslash.
+    #[test]
+    fn stock_schemes_map_leader_to_itself() {
+        assert_eq!(c_escapes().escape('\\'), Some('\\'));
+        assert_eq!(posix_escapes().escape('\\'), Some('\\'));
+    }
+
+    #[test]
+    fn partial_escape_keeps_leader_for_unmapped() {
+        let map: HashMap<char, char> =
+            vec![ ( '\"', '\"' ), ( '\\', '\\' ) ].move_iter().collect();
+        let pe = PartialEscape('\\', map);
+        assert_eq!(pe.escape_seq(&[ '\"' ]), Escaped('\"'));
+        assert_eq!(pe.escape_seq(&[ '\\' ]), Escaped('\\'));
+        assert_eq!(pe.escape_seq(&[ 'n' ]), EscapedBefore('\\'));
+        assert_eq!(pe.escape('n'), None);
+    }
 }
@@ -4,14 +4,14 @@
 //! POSIX shell-style separation of a line of _words_ into a vector of
 //! strings.
 //!
-//! It comes with three example tokenisers, employing simple whitespace
-//! splitting, POSIX shell-style and C-style tactics, and allows custom
+//! It comes with example tokenisers, employing simple whitespace
+//! splitting, POSIX shell-style, C-style and CSV tactics, and allows custom
 //! tokenisers to be created by specifying the permitted quotation pairs,
 //! escape sequences, and escape sequence leading character.
 //!
 //! Russet is quite basic; it doesn't implement shell-style variable and
-//! command expansion, multiple-character escape sequences (such as C unicode
-//! sequences), and the array of available ‘stock’ tokenisers is limited.
+//! command expansion, and the array of available ‘stock’ tokenisers is
+//! limited.
 //! However, it can likely be extended to include these and more.
 #![experimental]
 
@@ -19,29 +19,111 @@
 #[phase(plugin)]
 extern crate quickcheck_macros;
 extern crate quickcheck;
+#[cfg(test)]
+extern crate test;
+extern crate time;
 
 
 pub use builders::{
+    QuotePairParseError,
+    c_quote,
     c_style_tokeniser,
+    colon_split_tokeniser,
+    csv_tokeniser,
+    null_split_tokeniser,
+    python_shlex_tokeniser,
+    shell_join,
+    shell_quote,
     shell_style_tokeniser,
+    shell_style_tokeniser_with_comments,
+    tab_split_tokeniser,
     whitespace_split_tokeniser
 };
+pub use chunked::ChunkedTokeniser;
+pub use error::RussetError;
 pub use escape_scheme::{
     EscapeScheme,
     SimpleEscapeScheme,
     LiteralEscape,
-    MapEscape
+    MapEscape,
+    UnicodeEscape,
+    HexEscape,
+    OctalEscape,
+    PartialEscape
 };
 pub use line::LineTokeniser;
+pub use policy::{ Policy, PolicyRule, PolicyViolation };
+pub use quote::{
+    Quoter,
+    TableError,
+    TableOpts,
+    UnrepresentableWord,
+    WrapError,
+    shell_quoter
+};
+pub use sink::WordSink;
+pub use token::{
+    Bare,
+    DoubleQuoted,
+    Escaped,
+    Mixed,
+    SingleQuoted,
+    Token,
+    TokenKind
+};
 pub use tokeniser::{
+    AfterQuote,
+    AnyAfterQuote,
+    AnyBeforeQuote,
+    BeforeQuote,
+    Bounded,
+    BufferPolicy,
+    Bytes,
+    CaseInsensitive,
+    CaseSensitive,
+    CaseSensitivity,
+    Chars,
+    Custom,
+    DoubledEscape,
     Error,
+    Failed,
+    FoldWhitespace,
     IgnoreEscapes,
+    InEscape,
+    InQuote,
+    InWord,
+    KeepDelimiters,
+    LengthUnit,
+    LexicalOrder,
+    LiteralQuoteInWord,
+    NaturalOrder,
     ParseEscapes,
     QuoteMode,
-    Tokeniser
+    Ready,
+    SeparatorAfterQuote,
+    SeparatorBeforeQuote,
+    SeparatorChars,
+    SeparatorFn,
+    Separators,
+    Span,
+    SpannedToken,
+    State,
+    Tokeniser,
+    TokeniserIter,
+    Tokens,
+    Unbounded,
+    Whitespace,
+    WordOrder,
+    Words
 };
 
 pub mod builders;
+pub mod chunked;
+pub mod error;
 pub mod escape_scheme;
 pub mod line;
+pub mod policy;
+pub mod quote;
+pub mod sink;
+pub mod token;
 pub mod tokeniser;
@@ -11,10 +11,97 @@ use tokeniser::Error;
 /// functions is slightly weird.
 pub trait LineTokeniser {
     fn line(self, ln: &str) -> Result<Vec<String>, Error>;
+
+    /// Tokenises each line of `input` separately.
+    ///
+    /// Lines are separated by `\n`, and any `\r` before it is removed.  A
+    /// blank line gives an empty vector, and a `\n` at the very end of
+    /// `input` does not start another line.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the words of each line if every line was
+    /// valid, and otherwise the zero-based index of the first invalid line
+    /// along with its Error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ LineTokeniser, shell_style_tokeniser };
+    /// use russet::tokeniser::UnmatchedQuote;
+    ///
+    /// assert_eq!(shell_style_tokeniser.tokenise_lines("a b\n\nc\n"),
+    ///            Ok(vec![ vec![ "a".into_string(), "b".into_string() ],
+    ///                     vec![],
+    ///                     vec![ "c".into_string() ] ]));
+    /// assert_eq!(shell_style_tokeniser.tokenise_lines("a\nb 'c\nd"),
+    ///            Err(( 1, UnmatchedQuote(2) )));
+    /// ```
+    fn tokenise_lines(self, input: &str)
+      -> Result<Vec<Vec<String>>, ( uint, Error )>;
 }
 
 impl LineTokeniser for fn() -> StockTokeniser {
     fn line(self, ln: &str) -> Result<Vec<String>, Error> {
         self().add_line(ln).into_strings()
     }
+
+    fn tokenise_lines(self, input: &str)
+      -> Result<Vec<Vec<String>>, ( uint, Error )> {
+        if input.is_empty() {
+            return Ok(vec![]);
+        }
+        let body = if input.ends_with("\n") {
+            input.slice_to(input.len() - 1)
+        } else {
+            input
+        };
+
+        let mut lines = vec![];
+        for ( i, ln ) in body.split('\n').enumerate() {
+            match self.line(ln) {
+                Ok(words) => lines.push(words),
+                Err(e)    => return Err(( i, e ))
+            }
+        }
+        Ok(lines)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use builders::{ c_style_tokeniser, shell_style_tokeniser };
+    use tokeniser::{ BadEscape, UnmatchedQuote };
+    use super::LineTokeniser;
+
+    /// Converts rows of string slices into rows of Strings.
+    fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|w| w.into_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn tokenise_lines_blank_lines() {
+        assert_eq!(shell_style_tokeniser.tokenise_lines("\na b\n  \n\nc"),
+                   Ok(rows([ &[], &[ "a", "b" ], &[], &[], &[ "c" ] ])));
+        assert_eq!(shell_style_tokeniser.tokenise_lines(""), Ok(vec![]));
+        assert_eq!(shell_style_tokeniser.tokenise_lines("\n"),
+                   Ok(rows([ &[] ])));
+    }
+
+    #[test]
+    fn tokenise_lines_crlf() {
+        assert_eq!(shell_style_tokeniser.tokenise_lines("a 'b c'\r\nd\r\n"),
+                   Ok(rows([ &[ "a", "b c" ], &[ "d" ] ])));
+    }
+
+    #[test]
+    fn tokenise_lines_errors() {
+        assert_eq!(shell_style_tokeniser.tokenise_lines("a\n\nb 'c\nd'"),
+                   Err(( 2, UnmatchedQuote(2) )));
+        assert_eq!(c_style_tokeniser.tokenise_lines("a\nb\n\\q\n\"c"),
+                   Err(( 2, BadEscape )));
+    }
 }
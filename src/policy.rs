@@ -0,0 +1,110 @@
+//! Policies restricting what untrusted lines may contain.
+#![experimental]
+
+use std::default::Default;
+use std::error;
+use std::fmt;
+
+use tokeniser::{ Error, Span };
+
+
+/// A policy for `Tokeniser::check_policy`.
+///
+/// The default Policy allows everything.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub struct Policy {
+    /// Strings that may only appear quoted or escaped, such as `;`, `|` or
+    /// `$(`.  A string is only forbidden where every one of its characters
+    /// is bare, so forbidding `$(` still allows `$HOME` and `$'('`.  Empty
+    /// strings are ignored.
+    pub forbidden_bare: Vec<String>,
+
+    /// Whether escape sequences may not stand for control characters.
+    pub forbid_control_escapes: bool,
+
+    /// The maximum number of words, if any.
+    pub max_words: Option<uint>,
+
+    /// The maximum length of a word, in characters, if any.
+    pub max_word_len: Option<uint>
+}
+
+impl Default for Policy {
+    fn default() -> Policy {
+        Policy {
+            forbidden_bare: vec![],
+            forbid_control_escapes: false,
+            max_words: None,
+            max_word_len: None
+        }
+    }
+}
+
+
+/// A rule of a Policy that a line broke.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub enum PolicyRule {
+    /// The given string appeared unquoted and unescaped.
+    ForbiddenBare(String),
+
+    /// An escape sequence stood for the given control character.
+    ControlEscape(char),
+
+    /// There were more words than the given limit.
+    TooManyWords(uint),
+
+    /// A word was longer than the given limit.
+    WordTooLong(uint),
+
+    /// The line could not be tokenised.
+    Malformed(Error)
+}
+
+
+/// An error raised when a line breaks a Policy.
+#[deriving(Clone, Eq, PartialEq)]
+pub struct PolicyViolation {
+    /// The rule that was broken.
+    pub rule: PolicyRule,
+
+    /// The span of the line, in characters, that broke the rule.  For a
+    /// malformed line, this is the whole line.
+    pub span: Span
+}
+
+impl error::Error for PolicyViolation {
+    fn description(&self) -> &str {
+        match self.rule {
+            ForbiddenBare(..) => "forbidden string outside quotes",
+            ControlEscape(..) => "escape sequence for a control character",
+            TooManyWords(..)  => "too many words",
+            WordTooLong(..)   => "word too long",
+            Malformed(..)     => "malformed line"
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match self.rule {
+            Malformed(ref e) => Some(e as &error::Error),
+            _                => None
+        }
+    }
+}
+
+impl fmt::Show for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use std::error::Error;
+
+        try!(write!(f, "{} at characters {} to {}",
+                    self.description(), self.span.start, self.span.end));
+        match self.rule {
+            ForbiddenBare(ref s) =>
+                write!(f, " ({})", s.as_slice().escape_default()),
+            ControlEscape(c) =>
+                write!(f, " ({})", c.escape_default().collect::<String>()),
+            TooManyWords(limit) | WordTooLong(limit) =>
+                write!(f, " (limit {})", limit),
+            Malformed(ref e) => write!(f, " ({})", e)
+        }
+    }
+}
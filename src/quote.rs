@@ -0,0 +1,938 @@
+//! Quoting of words, so that they tokenise back into themselves.
+#![experimental]
+
+use std::char::{ is_control, is_whitespace };
+use std::default::Default;
+use std::error;
+use std::fmt;
+
+
+/// An error raised when a word cannot be quoted.
+///
+/// The word contains a character at the given character offset that no
+/// representation available to the Quoter can produce.
+#[deriving(Clone, Eq, PartialEq)]
+pub struct UnrepresentableWord(pub uint);
+
+impl error::Error for UnrepresentableWord {
+    fn description(&self) -> &str {
+        "word cannot be represented"
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        None
+    }
+}
+
+impl fmt::Show for UnrepresentableWord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let UnrepresentableWord(pos) = *self;
+        write!(f, "word cannot be represented (at character {})", pos)
+    }
+}
+
+
+/// An error raised when words cannot be wrapped into lines.
+#[deriving(Clone, Eq, PartialEq)]
+pub enum WrapError {
+    /// The word at the given index is wider than a line, even when quoted.
+    WordTooWide(uint),
+
+    /// The word at the given index cannot be quoted on a single line.
+    WordUnrepresentable(uint)
+}
+
+impl error::Error for WrapError {
+    fn description(&self) -> &str {
+        match *self {
+            WordTooWide(..)         => "word too wide for line",
+            WordUnrepresentable(..) => "word cannot be represented on a line"
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        None
+    }
+}
+
+impl fmt::Show for WrapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use std::error::Error;
+
+        match *self {
+            WordTooWide(i) | WordUnrepresentable(i) =>
+                write!(f, "{} (word {})", self.description(), i)
+        }
+    }
+}
+
+
+/// An error raised when words cannot be laid out as a table.
+#[deriving(Clone, Eq, PartialEq)]
+pub enum TableError {
+    /// The cell at the given row and column cannot be quoted to fit the
+    /// maximum column width, even when truncated to one character.
+    CellTooWide(uint, uint),
+
+    /// The cell at the given row and column cannot be quoted on a single
+    /// line.
+    CellUnrepresentable(uint, uint),
+
+    /// The column separator is empty, or cannot be quoted on a single
+    /// line.
+    SeparatorUnrepresentable
+}
+
+impl error::Error for TableError {
+    fn description(&self) -> &str {
+        match *self {
+            CellTooWide(..)         => "cell too wide for column",
+            CellUnrepresentable(..) => "cell cannot be represented on a line",
+            SeparatorUnrepresentable =>
+                "column separator cannot be represented on a line"
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        None
+    }
+}
+
+impl fmt::Show for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use std::error::Error;
+
+        match *self {
+            CellTooWide(r, c) | CellUnrepresentable(r, c) =>
+                write!(f, "{} (row {}, column {})", self.description(), r, c),
+            SeparatorUnrepresentable => write!(f, "{}", self.description())
+        }
+    }
+}
+
+
+/// Options for laying out words as a table with `Quoter::format_table`.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub struct TableOpts {
+    /// The maximum width of a column, in characters, if any.  Cells whose
+    /// quoted form is wider are truncated until it fits.
+    pub max_width: Option<uint>,
+
+    /// The string placed between columns, after any padding.  This must be
+    /// non-empty, and must not contain a line break.  A separator that is
+    /// not all whitespace is quoted, so that it reads back as one word.
+    pub separator: String
+}
+
+impl Default for TableOpts {
+    /// Creates TableOpts with no maximum width, separating columns with a
+    /// single space.
+    fn default() -> TableOpts {
+        TableOpts { max_width: None, separator: " ".into_string() }
+    }
+}
+
+
+/// The representations a character can take inside a quoted word.
+/// These are used as indices into the cost tables of `Quoter::quote`.
+static UNQUOTED: uint = 0;
+static IN_LITERAL_QUOTE: uint = 1;
+static IN_ESCAPING_QUOTE: uint = 2;
+
+
+/// A description of the quoting facilities of a tokeniser, used to render
+/// words in a form that the tokeniser reads back into the same words.
+///
+/// A Quoter segments each word and picks the shortest combination of bare
+/// characters, quotations and escapes for it, mixing quote styles within
+/// a word where that is the only way to represent it.
+///
+/// Control characters, such as newlines, are only ever written inside the
+/// literal quote.  A shell reads an escaped newline as a line continuation,
+/// and a bare one would end the line.
+#[deriving(Clone)]
+pub struct Quoter {
+    /// Characters that may appear outside quotes without being escaped.
+    bare: fn(char) -> bool,
+
+    /// A quote character, in which everything but itself is literal.
+    literal_quote: Option<char>,
+
+    /// A quote character, in which the escape leader is parsed.
+    escaping_quote: Option<char>,
+
+    /// Characters that must be escaped inside the escaping quote, besides
+    /// the quote itself and the escape leader.
+    escaped_in_quote: fn(char) -> bool,
+
+    /// A leader that makes the following character literal.
+    escape_leader: Option<char>
+}
+
+impl Quoter {
+    /// Creates a new Quoter with no quotes and no escape leader.
+    ///
+    /// # Arguments
+    ///
+    /// * `bare` - A predicate for the characters that can appear outside
+    ///   quotes unescaped.  Whitespace, control characters, quote
+    ///   characters and the escape leader are never left bare, even if
+    ///   this allows them.
+    ///
+    /// # Return value
+    ///
+    /// A new Quoter.
+    pub fn new(bare: fn(char) -> bool) -> Quoter {
+        Quoter {
+            bare: bare,
+            literal_quote: None,
+            escaping_quote: None,
+            escaped_in_quote: never,
+            escape_leader: None
+        }
+    }
+
+    /// Sets the quote character in which every other character is literal.
+    ///
+    /// This corresponds to a quote pair with IgnoreEscapes mode.
+    pub fn with_literal_quote(mut self, quote: char) -> Quoter {
+        self.literal_quote = Some(quote);
+        self
+    }
+
+    /// Sets the quote character in which the escape leader is parsed.
+    ///
+    /// This corresponds to a quote pair with ParseEscapes mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `quote` - The quote character.
+    /// * `escaped` - A predicate for any characters, other than `quote` and
+    ///   the escape leader, that must be escaped inside the quote.
+    pub fn with_escaping_quote(mut self, quote: char, escaped: fn(char) -> bool)
+      -> Quoter {
+        self.escaping_quote = Some(quote);
+        self.escaped_in_quote = escaped;
+        self
+    }
+
+    /// Sets the escape leader, which makes the character after it literal.
+    ///
+    /// This corresponds to an escape leader mapped to LiteralEscape.
+    pub fn with_escape_leader(mut self, leader: char) -> Quoter {
+        self.escape_leader = Some(leader);
+        self
+    }
+
+    /// Quotes a word.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the shortest rendering of `word` if it can be
+    /// represented, and an UnrepresentableWord error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::quote::shell_quoter;
+    ///
+    /// let quoter = shell_quoter();
+    /// assert_eq!(quoter.quote("abc"), Ok("abc".into_string()));
+    /// assert_eq!(quoter.quote("a b"), Ok("a\\ b".into_string()));
+    /// assert_eq!(quoter.quote("a \"b\" c"),
+    ///            Ok("'a \"b\" c'".into_string()));
+    /// ```
+    pub fn quote(&self, word: &str) -> Result<String, UnrepresentableWord> {
+        if word.is_empty() {
+            return self.quote_empty();
+        }
+
+        let chars: Vec<char> = word.chars().collect();
+
+        // costs[m] is the cost of the cheapest rendering of the characters
+        // so far, where the last one is in representation m.  Costs are
+        // compared by length, then by number of quote switches, so that
+        // ties go to the simpler rendering; remaining ties stay in the same
+        // representation.  back[i][m] is the representation of the
+        // character before character i on that rendering.
+        let mut costs = [ Some(( 0u, 0u )), None, None ];
+        let mut back: Vec<[uint, ..3]> = Vec::with_capacity(chars.len());
+
+        for ( i, &c ) in chars.iter().enumerate() {
+            let mut next = [ None, None, None ];
+            let mut from = [ UNQUOTED, UNQUOTED, UNQUOTED ];
+
+            for m in range(0u, 3) {
+                let len = match self.char_cost(m, c) {
+                    Some(x) => x,
+                    None    => continue
+                };
+                for p in range(0u, 3) {
+                    let total = match costs[p] {
+                        Some(x) => add_switch(x, p, m, len),
+                        None    => continue
+                    };
+                    let better = next[m].map_or(true, |best| {
+                        total < best || ( total == best && p == m )
+                    });
+                    if better {
+                        next[m] = Some(total);
+                        from[m] = p;
+                    }
+                }
+            }
+
+            if next.iter().all(|x| x.is_none()) {
+                return Err(UnrepresentableWord(i));
+            }
+            back.push(from);
+            costs = next;
+        }
+
+        // Every rendering must end outside quotes.
+        let mut last = UNQUOTED;
+        let mut best = None;
+        for m in range(0u, 3) {
+            match costs[m].map(|x| add_switch(x, m, UNQUOTED, 0)) {
+                Some(total) if best.map_or(true, |b| total < b) => {
+                    best = Some(total);
+                    last = m;
+                },
+                _ => ()
+            }
+        }
+
+        let mut modes = Vec::from_elem(chars.len(), UNQUOTED);
+        for i in range(0, chars.len()).rev() {
+            *modes.get_mut(i) = last;
+            last = back.get(i)[last];
+        }
+
+        Ok(self.render(chars.as_slice(), modes.as_slice()))
+    }
+
+    /// Quotes words and wraps them into lines of at most a given width.
+    ///
+    /// Lines are only broken between words, so no quotation or escape
+    /// sequence spans a line break.  Words are separated by a space within
+    /// a line.  Each line but the last ends with a space and the escape
+    /// leader, which count towards its width, as a shell line continuation.
+    /// The lines read back into the original words with the tokeniser this
+    /// Quoter describes, if it reads line continuations.  A Quoter with no
+    /// escape leader breaks lines with a bare newline.
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - The words to quote.
+    /// * `width` - The maximum width of a line, in characters.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the wrapped lines if every word fits on a
+    /// line, and a WrapError naming the first word that does not
+    /// otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::quote::shell_quoter;
+    ///
+    /// let words = [ "echo", "hello world", "and", "goodbye" ];
+    /// assert_eq!(shell_quoter().wrap_line(words.as_slice(), 16),
+    ///            Ok("echo \\\nhello\\ world \\\nand goodbye".into_string()));
+    /// ```
+    pub fn wrap_line(&self, words: &[&str], width: uint)
+      -> Result<String, WrapError> {
+        let mut out = String::new();
+        let mut line_width = 0u;
+
+        // Room left on a line for the continuation after its last word.
+        let continuation = self.escape_leader.map_or(0u, |_| 2);
+
+        for ( i, word ) in words.iter().enumerate() {
+            let quoted = match self.quote(*word) {
+                Ok(q) if !q.as_slice().contains_char('\n') => q,
+                _ => return Err(WordUnrepresentable(i))
+            };
+
+            // Only the last word can end the last line, which needs no
+            // continuation.
+            let reserve = if i + 1 < words.len() { continuation } else { 0 };
+            let quoted_width = quoted.as_slice().char_len();
+            if width < quoted_width + reserve {
+                return Err(WordTooWide(i));
+            }
+
+            if i == 0 {
+                // First word: no separator needed.
+            } else if width < line_width + 1 + quoted_width + reserve {
+                match self.escape_leader {
+                    Some(leader) => {
+                        out.push_char(' ');
+                        out.push_char(leader);
+                    },
+                    None => ()
+                }
+                out.push_char('\n');
+                line_width = 0;
+            } else {
+                out.push_char(' ');
+                line_width += 1;
+            }
+
+            out.push_str(quoted.as_slice());
+            line_width += quoted_width;
+        }
+
+        Ok(out)
+    }
+
+    /// Quotes rows of words and lays them out as a table, with each column
+    /// padded with spaces to the width of its widest quoted cell.
+    ///
+    /// Each row becomes one line.  With a blank separator, the line reads
+    /// back into the row's words (truncated, if `opts` sets a maximum
+    /// width) with the tokeniser this Quoter describes.  Widths are
+    /// measured in characters, as in `wrap_line`.  The last cell of each
+    /// row is not padded, so no line ends in whitespace.
+    ///
+    /// A separator that is not all whitespace, such as `|`, is quoted with
+    /// this Quoter, and given a space on either side that has none, so that
+    /// it cannot run into a cell.  It then reads back as one word, the
+    /// separator less its surrounding whitespace, between each pair of the
+    /// row's words, which are every other word of the line.
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - The rows of words to lay out.  Rows need not be of equal
+    ///   length.
+    /// * `opts` - The maximum column width and the column separator.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the lines of the table separated by newlines if
+    /// every cell can be laid out, and a TableError otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::default::Default;
+    /// use russet::quote::shell_quoter;
+    ///
+    /// let rows = vec![
+    ///     vec![ "add".into_string(), "my file".into_string() ],
+    ///     vec![ "remove-all".into_string(), "x".into_string() ]
+    /// ];
+    /// assert_eq!(shell_quoter().format_table(rows.as_slice(),
+    ///                                        &Default::default()),
+    ///            Ok("add        my\\ file\nremove-all x".into_string()));
+    /// ```
+    pub fn format_table(&self, rows: &[Vec<String>], opts: &TableOpts)
+      -> Result<String, TableError> {
+        let separator = try!(self.quote_separator(opts.separator.as_slice()));
+        let sep = separator.as_slice();
+
+        let mut quoted_rows = Vec::with_capacity(rows.len());
+        let mut widths: Vec<uint> = vec![];
+
+        for ( r, row ) in rows.iter().enumerate() {
+            let mut quoted_row = Vec::with_capacity(row.len());
+            for ( c, cell ) in row.iter().enumerate() {
+                let quoted = try!(self.quote_cell(cell.as_slice(),
+                                                  opts.max_width, r, c));
+                let width = quoted.as_slice().char_len();
+                if widths.len() <= c {
+                    widths.push(width);
+                } else if *widths.get(c) < width {
+                    *widths.get_mut(c) = width;
+                }
+                quoted_row.push(quoted);
+            }
+            quoted_rows.push(quoted_row);
+        }
+
+        let mut out = String::new();
+        for ( r, row ) in quoted_rows.iter().enumerate() {
+            if 0 < r {
+                out.push_char('\n');
+            }
+            for ( c, cell ) in row.iter().enumerate() {
+                if 0 < c {
+                    out.push_str(sep);
+                }
+                out.push_str(cell.as_slice());
+                if c + 1 < row.len() {
+                    let pad = *widths.get(c) - cell.as_slice().char_len();
+                    out.grow(pad, ' ');
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Quotes a table's column separator onto one line, so that it cannot
+    /// be read as part of a cell.
+    fn quote_separator(&self, sep: &str) -> Result<String, TableError> {
+        if sep.is_empty() || sep.contains_char('\n') {
+            return Err(SeparatorUnrepresentable);
+        }
+
+        let word = sep.trim();
+        if word.is_empty() {
+            return Ok(sep.into_string());
+        }
+        let quoted = match self.quote(word) {
+            Ok(q) if !q.as_slice().contains_char('\n') => q,
+            _ => return Err(SeparatorUnrepresentable)
+        };
+
+        let before = sep.slice_to(sep.len() - sep.trim_left().len());
+        let after = sep.slice_from(sep.trim_right().len());
+        let mut out = String::new();
+        out.push_str(if before.is_empty() { " " } else { before });
+        out.push_str(quoted.as_slice());
+        out.push_str(if after.is_empty() { " " } else { after });
+        Ok(out)
+    }
+
+    /// Quotes the cell at row `r` and column `c` of a table onto one line,
+    /// truncating it to fit `max_width` if given.
+    fn quote_cell(&self, cell: &str, max_width: Option<uint>, r: uint, c: uint)
+      -> Result<String, TableError> {
+        let fits = |q: &String| {
+            !q.as_slice().contains_char('\n')
+            && max_width.map_or(true, |w| q.as_slice().char_len() <= w)
+        };
+
+        let quoted = match self.quote(cell) {
+            Ok(q) => q,
+            Err(..) => return Err(CellUnrepresentable(r, c))
+        };
+        if fits(&quoted) {
+            return Ok(quoted);
+        }
+        if max_width.is_none() || cell.contains_char('\n') {
+            return Err(CellUnrepresentable(r, c));
+        }
+
+        // Try ever shorter prefixes, keeping at least one character so that
+        // the cell does not vanish.
+        let ends: Vec<uint> = cell.char_indices().map(|( i, _ )| i).collect();
+        for &end in ends.slice_from(1).iter().rev() {
+            match self.quote(cell.slice_to(end)) {
+                Ok(ref q) if fits(q) => return Ok(q.clone()),
+                _                    => ()
+            }
+        }
+        Err(CellTooWide(r, c))
+    }
+
+    /// Quotes the empty word, which needs a pair of quotes.
+    fn quote_empty(&self) -> Result<String, UnrepresentableWord> {
+        match self.literal_quote.or(self.escaping_quote) {
+            Some(q) => Ok(String::from_char(2, q)),
+            None    => Err(UnrepresentableWord(0))
+        }
+    }
+
+    /// Calculates the length of `c` in representation `mode`, if `c` can be
+    /// represented in it.
+    fn char_cost(&self, mode: uint, c: char) -> Option<uint> {
+        let escape_cost = self.escape_leader.map(|_| 2u);
+
+        if mode == IN_LITERAL_QUOTE {
+            self.literal_quote.and_then(|q| if c == q { None } else { Some(1) })
+        } else if is_control(c) {
+            None
+        } else if mode == IN_ESCAPING_QUOTE {
+            self.escaping_quote.and_then(|q| {
+                if self.must_escape_in_quote(q, c) {
+                    escape_cost
+                } else {
+                    Some(1)
+                }
+            })
+        } else if self.can_be_bare(c) {
+            Some(1)
+        } else {
+            escape_cost
+        }
+    }
+
+    /// Checks whether `c` can appear outside quotes without an escape.
+    fn can_be_bare(&self, c: char) -> bool {
+        (self.bare)(c)
+        && !is_whitespace(c)
+        && self.literal_quote != Some(c)
+        && self.escaping_quote != Some(c)
+        && self.escape_leader != Some(c)
+    }
+
+    /// Checks whether `c` must be escaped inside the escaping quote `q`.
+    fn must_escape_in_quote(&self, q: char, c: char) -> bool {
+        c == q || self.escape_leader == Some(c) || (self.escaped_in_quote)(c)
+    }
+
+    /// Renders `chars`, where each character is in the corresponding
+    /// representation in `modes`.
+    fn render(&self, chars: &[char], modes: &[uint]) -> String {
+        let mut out = String::new();
+        let mut current = UNQUOTED;
+
+        for ( &c, &m ) in chars.iter().zip(modes.iter()) {
+            if m != current {
+                self.quote_char(current).map(|q| out.push_char(q));
+                self.quote_char(m).map(|q| out.push_char(q));
+                current = m;
+            }
+
+            let escape = match m {
+                UNQUOTED          => !self.can_be_bare(c),
+                IN_ESCAPING_QUOTE =>
+                    self.escaping_quote.map_or(false, |q| {
+                        self.must_escape_in_quote(q, c)
+                    }),
+                _                 => false
+            };
+            if escape {
+                self.escape_leader.map(|l| out.push_char(l));
+            }
+            out.push_char(c);
+        }
+
+        self.quote_char(current).map(|q| out.push_char(q));
+        out
+    }
+
+    /// Retrieves the quote character for the representation `mode`.
+    fn quote_char(&self, mode: uint) -> Option<char> {
+        match mode {
+            IN_LITERAL_QUOTE  => self.literal_quote,
+            IN_ESCAPING_QUOTE => self.escaping_quote,
+            _                 => None
+        }
+    }
+}
+
+
+/// Adds to `cost` the cost of moving from representation `from` to
+/// representation `to`, then adding a character of length `len`.
+fn add_switch(cost: ( uint, uint ), from: uint, to: uint, len: uint)
+  -> ( uint, uint ) {
+    let ( total, switches ) = cost;
+    let quotes = if from == to {
+        0
+    } else if from == UNQUOTED || to == UNQUOTED {
+        1
+    } else {
+        2
+    };
+    ( total + quotes + len, switches + if from == to { 0 } else { 1 } )
+}
+
+
+/// A predicate that holds for no characters.
+#[allow(unused_variable)]
+fn never(c: char) -> bool {
+    false
+}
+
+
+/// Checks whether `chr` can appear unquoted in a POSIX shell word.
+pub fn is_shell_safe(chr: char) -> bool {
+    (chr.is_ascii() && chr.is_alphanumeric()) || "-_./:=@%+,".contains_char(chr)
+}
+
+
+/// Checks whether `chr` would be expanded inside POSIX shell double quotes.
+fn is_shell_expansion(chr: char) -> bool {
+    chr == '$' || chr == '`'
+}
+
+
+/// Creates a Quoter for the shell-style stock tokeniser.
+///
+/// The words it produces are also valid POSIX shell words: characters the
+/// shell would expand inside double quotes are always escaped, and control
+/// characters are always single-quoted.
+///
+/// # Return value
+///
+/// A Quoter that uses single quotes, double quotes and backslash escapes.
+pub fn shell_quoter() -> Quoter {
+    Quoter::new(is_shell_safe).with_literal_quote('\'')
+                              .with_escaping_quote('\"', is_shell_expansion)
+                              .with_escape_leader('\\')
+}
+
+
+#[cfg(test)]
+mod test {
+    use builders::shell_style_tokeniser;
+    use line::LineTokeniser;
+    use std::default::Default;
+
+    use super::{ Quoter, UnrepresentableWord, shell_quoter };
+    use super::{ WordTooWide, WordUnrepresentable };
+    use super::{ CellTooWide, CellUnrepresentable, SeparatorUnrepresentable };
+    use super::TableOpts;
+    use super::{ is_shell_safe, never };
+
+    /// The characters used to build words for the round-trip property.
+    static ALPHABET: [char, ..6] = [ ' ', '\'', '\"', '\\', 'a', '\n' ];
+
+    #[test]
+    fn quote_bare() {
+        assert_eq!(shell_quoter().quote("abc-1.txt"),
+                   Ok("abc-1.txt".into_string()));
+    }
+
+    #[test]
+    fn quote_prefers_cheapest() {
+        assert_eq!(shell_quoter().quote("a b"), Ok("a\\ b".into_string()));
+        assert_eq!(shell_quoter().quote("a b c d"),
+                   Ok("'a b c d'".into_string()));
+        assert_eq!(shell_quoter().quote("it's a b"),
+                   Ok("\"it's a b\"".into_string()));
+    }
+
+    #[test]
+    fn quote_escapes_expansions() {
+        assert_eq!(shell_quoter().quote("'$x y'"),
+                   Ok("\"'\\$x y'\"".into_string()));
+    }
+
+    #[test]
+    fn quote_control_in_literal_quote() {
+        assert_eq!(shell_quoter().quote("\n"), Ok("'\n'".into_string()));
+        assert_eq!(shell_quoter().quote("a b\n"),
+                   Ok("'a b\n'".into_string()));
+
+        let quoter = Quoter::new(is_shell_safe).with_escape_leader('\\');
+        assert_eq!(quoter.quote("a\n"), Err(UnrepresentableWord(1)));
+    }
+
+    #[test]
+    fn quote_mixed_without_escapes() {
+        let quoter = Quoter::new(is_shell_safe).with_literal_quote('\'')
+                                               .with_escaping_quote('\"',
+                                                                    never);
+        assert_eq!(quoter.quote("a'b\"c"),
+                   Ok("\"a'b\"'\"'c".into_string()));
+    }
+
+    #[test]
+    fn quote_unrepresentable() {
+        let quoter = Quoter::new(is_shell_safe).with_literal_quote('\'');
+        assert_eq!(quoter.quote("a b'c"), Err(UnrepresentableWord(3)));
+    }
+
+    #[test]
+    fn quote_empty() {
+        assert_eq!(shell_quoter().quote(""), Ok("''".into_string()));
+        assert_eq!(Quoter::new(is_shell_safe).quote(""),
+                   Err(UnrepresentableWord(0)));
+    }
+
+    #[quickcheck]
+    fn shell_quoter_round_trip(words: Vec<Vec<uint>>) -> bool {
+        let words: Vec<String> =
+            words.iter()
+                 .map(|w| w.iter().map(|&i| ALPHABET[i % 6]).collect())
+                 .collect();
+        let quoted: Vec<String> =
+            words.iter()
+                 .map(|w| shell_quoter().quote(w.as_slice()).unwrap())
+                 .collect();
+
+        shell_style_tokeniser.line(quoted.connect(" ").as_slice())
+            == Ok(words)
+    }
+
+    /// A long command, for the wrapping tests.
+    static LONG_COMMAND: [&'static str, ..12] = [
+        "rsync", "--archive", "--verbose", "--exclude", "*.o",
+        "/home/me/My Documents/", "backup:/srv/backups/me/",
+        "--rsh", "ssh -p 2222", "--delete", "--dry-run", "it's done"
+    ];
+
+    /// Wraps LONG_COMMAND at 40 characters.
+    fn wrapped_command() -> String {
+        shell_quoter().wrap_line(LONG_COMMAND.as_slice(), 40).unwrap()
+    }
+
+    #[test]
+    fn wrap_line_round_trip() {
+        let wrapped = wrapped_command();
+
+        for line in wrapped.as_slice().lines() {
+            assert!(line.char_len() <= 40);
+        }
+
+        // Reading the continuations gives back the words.
+        let words: Vec<String> =
+            LONG_COMMAND.iter().map(|w| w.into_string()).collect();
+        assert_eq!(shell_style_tokeniser().with_line_continuation(true)
+                                          .add_string(wrapped.as_slice())
+                                          .into_strings(),
+                   Ok(words));
+    }
+
+    #[test]
+    fn wrap_line_continues_lines() {
+        let wrapped = wrapped_command();
+        let lines: Vec<&str> = wrapped.as_slice().lines().collect();
+        assert!(lines.len() > 1);
+
+        for line in lines.init().iter() {
+            assert!(line.ends_with(" \\"));
+        }
+        assert!(!lines.last().unwrap().ends_with("\\"));
+    }
+
+    #[test]
+    fn wrap_line_quotes_stay_on_one_line() {
+        let wrapped = wrapped_command();
+
+        // Each line, less its continuation, reads back on its own, so no
+        // quote spans two lines.
+        for line in wrapped.as_slice().lines() {
+            let line = line.trim_right_chars('\\');
+            assert!(shell_style_tokeniser.line(line).is_ok());
+        }
+    }
+
+    #[test]
+    fn wrap_line_word_too_wide() {
+        let giant = String::from_char(50, 'x');
+        let words = [ "echo", "hello", giant.as_slice() ];
+        assert_eq!(shell_quoter().wrap_line(words.as_slice(), 40),
+                   Err(WordTooWide(2)));
+    }
+
+    #[test]
+    fn wrap_line_counts_continuation() {
+        // Only the last line has no continuation to make room for.
+        let words = [ "echo", "xxxxxxxxxx" ];
+        assert_eq!(shell_quoter().wrap_line(words.as_slice(), 10),
+                   Ok("echo \\\nxxxxxxxxxx".into_string()));
+
+        let words = [ "xxxxxxxxx", "echo" ];
+        assert_eq!(shell_quoter().wrap_line(words.as_slice(), 10),
+                   Err(WordTooWide(0)));
+    }
+
+    #[test]
+    fn wrap_line_newline_in_word() {
+        let words = [ "echo", "two\nlines" ];
+        assert_eq!(shell_quoter().wrap_line(words.as_slice(), 40),
+                   Err(WordUnrepresentable(1)));
+    }
+
+    /// Builds a table of words from rows of string slices.
+    fn table(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|w| w.into_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn format_table_aligns_quoted_widths() {
+        let rows = table([ &[ "ls", "my file", "-l" ],
+                           &[ "rm", "a", "it's" ],
+                           &[ "echo" ] ]);
+        let opts = TableOpts { max_width: None, separator: "  ".into_string() };
+        assert_eq!(shell_quoter().format_table(rows.as_slice(), &opts),
+                   Ok("ls    my\\ file  -l\n\
+                       rm    a         it\\'s\n\
+                       echo".into_string()));
+    }
+
+    #[test]
+    fn format_table_truncates() {
+        let rows = table([ &[ "a b c d e", "xyz" ] ]);
+        let opts = TableOpts { max_width: Some(6), ..Default::default() };
+        let out = shell_quoter().format_table(rows.as_slice(), &opts);
+        assert_eq!(out, Ok("a\\ b\\  xyz".into_string()));
+        assert_eq!(shell_style_tokeniser.line(out.unwrap().as_slice()),
+                   Ok(vec![ "a b ".into_string(), "xyz".into_string() ]));
+    }
+
+    #[test]
+    fn format_table_errors() {
+        let opts = TableOpts { max_width: Some(1), ..Default::default() };
+        assert_eq!(shell_quoter().format_table(table([ &[ "ab", "'c" ] ])
+                                                   .as_slice(),
+                                               &opts),
+                   Err(CellTooWide(0, 1)));
+        assert_eq!(shell_quoter().format_table(table([ &[ "a" ],
+                                                       &[ "b\nc" ] ])
+                                                   .as_slice(),
+                                               &Default::default()),
+                   Err(CellUnrepresentable(1, 0)));
+
+        for sep in [ "", "a\nb" ].iter() {
+            let opts = TableOpts { separator: sep.into_string(),
+                                   ..Default::default() };
+            assert_eq!(shell_quoter().format_table(table([ &[ "a" ] ])
+                                                       .as_slice(),
+                                                   &opts),
+                       Err(SeparatorUnrepresentable));
+        }
+    }
+
+    #[test]
+    fn format_table_quotes_separator() {
+        let rows = table([ &[ "a b", "c" ], &[ "d", "e" ] ]);
+        for sep in [ " | ", "|" ].iter() {
+            let opts = TableOpts { separator: sep.into_string(),
+                                   ..Default::default() };
+            let out = shell_quoter().format_table(rows.as_slice(), &opts);
+            assert_eq!(out, Ok("a\\ b \\| c\nd    \\| e".into_string()));
+
+            let lines: Vec<Vec<String>> =
+                out.unwrap().as_slice().split('\n')
+                   .map(|l| shell_style_tokeniser.line(l).unwrap())
+                   .collect();
+            assert_eq!(lines, table([ &[ "a b", "|", "c" ],
+                                      &[ "d", "|", "e" ] ]));
+        }
+    }
+
+    #[quickcheck]
+    fn format_table_round_trip(rows: Vec<Vec<Vec<uint>>>,
+                               max_width: Option<uint>) -> bool {
+        // The newline, last in ALPHABET, is left out, as the table is read
+        // back a line at a time.
+        let rows: Vec<Vec<String>> =
+            rows.iter()
+                .map(|row| {
+                    row.iter()
+                       .map(|w| w.iter().map(|&i| ALPHABET[i % 5]).collect())
+                       .collect::<Vec<String>>()
+                })
+                .collect();
+
+        // No rows at all would still give one, empty, line.
+        if rows.is_empty() {
+            return true;
+        }
+
+        // Every character in ALPHABET can be quoted in at most four.
+        let opts = TableOpts { max_width: max_width.map(|w| w % 10 + 4),
+                               ..Default::default() };
+        let out = shell_quoter().format_table(rows.as_slice(), &opts).unwrap();
+        let lines: Vec<&str> = out.as_slice().split('\n').collect();
+
+        lines.len() == rows.len()
+        && lines.iter().zip(rows.iter()).all(|( line, row )| {
+            let words = shell_style_tokeniser.line(*line).unwrap();
+            words.len() == row.len()
+            && words.iter().zip(row.iter()).all(|( w, cell )| {
+                cell.as_slice().starts_with(w.as_slice())
+                && ( max_width.is_some() || w == cell )
+            })
+        })
+    }
+}
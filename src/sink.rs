@@ -0,0 +1,182 @@
+//! The _WordSink_ trait, for storing words as they are read.
+#![experimental]
+
+use tokeniser::Error;
+
+
+/// A destination for the words read by `Tokeniser::tokenise_into_sink`.
+///
+/// A WordSink decides how words are stored: in an arena, a pool of
+/// interned strings, or a custom string type.  Each word is given to the
+/// sink as a call to `begin_word`, any number of calls to `push_char` and
+/// `push_str`, and a call to `end_word`.  The Tokeniser writes each
+/// character into the sink as it is read, without building the word
+/// itself, so a sink with preallocated storage reads words without
+/// allocating.
+///
+/// The default sink, `Vec<String>`, collects the words as `into_strings`
+/// does.
+pub trait WordSink {
+    /// Starts a new word.
+    fn begin_word(&mut self);
+
+    /// Adds a character to the current word.
+    fn push_char(&mut self, c: char);
+
+    /// Adds a string to the current word.
+    fn push_str(&mut self, s: &str);
+
+    /// Ends the current word.
+    fn end_word(&mut self);
+
+    /// Reports that tokenising failed with `e`.
+    ///
+    /// Words given to the sink before the failure are not taken back; a
+    /// sink that must not keep them should discard them here.  The word
+    /// being read at the failure, if any, was begun but will not be ended.
+    fn fail(&mut self, e: &Error);
+}
+
+impl WordSink for Vec<String> {
+    fn begin_word(&mut self) {
+        self.push(String::new());
+    }
+
+    fn push_char(&mut self, c: char) {
+        match self.mut_last() {
+            Some(word) => word.push_char(c),
+            None       => fail!("push_char called before begin_word")
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        match self.mut_last() {
+            Some(word) => word.push_str(s),
+            None       => fail!("push_str called before begin_word")
+        }
+    }
+
+    fn end_word(&mut self) {}
+
+    fn fail(&mut self, _: &Error) {}
+}
+
+
+#[cfg(test)]
+mod test {
+    use builders::{ shell_style_tokeniser, whitespace_split_tokeniser };
+    use tokeniser::{ Error, UnmatchedQuote };
+    use super::WordSink;
+
+    /// A sink storing every word in one preallocated buffer.
+    struct ArenaSink {
+        text: String,
+        ends: Vec<uint>,
+        failed: Option<Error>
+    }
+
+    impl ArenaSink {
+        fn with_capacity(bytes: uint, words: uint) -> ArenaSink {
+            ArenaSink {
+                text: String::with_capacity(bytes),
+                ends: Vec::with_capacity(words),
+                failed: None
+            }
+        }
+
+        fn words(&self) -> Vec<&str> {
+            let mut start = 0;
+            self.ends.iter().map(|&end| {
+                let word = self.text.as_slice().slice(start, end);
+                start = end;
+                word
+            }).collect()
+        }
+    }
+
+    impl WordSink for ArenaSink {
+        fn begin_word(&mut self) {}
+
+        fn push_char(&mut self, c: char) {
+            self.text.push_char(c);
+        }
+
+        fn push_str(&mut self, s: &str) {
+            self.text.push_str(s);
+        }
+
+        fn end_word(&mut self) {
+            self.ends.push(self.text.len());
+        }
+
+        fn fail(&mut self, e: &Error) {
+            self.failed = Some(e.clone());
+        }
+    }
+
+    #[test]
+    fn arena_sink_does_not_allocate_per_word() {
+        let mut sink = ArenaSink::with_capacity(64, 8);
+        let ( text_cap, ends_cap ) = ( sink.text.capacity(),
+                                       sink.ends.capacity() );
+
+        let tok = shell_style_tokeniser();
+        assert_eq!(tok.tokenise_into_sink("cp 'my file' \"a b\" c\\ d",
+                                          &mut sink),
+                   Ok(()));
+        assert_eq!(sink.words(), vec![ "cp", "my file", "a b", "c d" ]);
+
+        // Every word went into the buffers allocated up front.
+        assert_eq!(sink.text.capacity(), text_cap);
+        assert_eq!(sink.ends.capacity(), ends_cap);
+    }
+
+    #[test]
+    fn arena_sink_many_words() {
+        let line = "ab 'c d' e\\ f g ".repeat(250);
+        let mut sink = ArenaSink::with_capacity(line.len(), 1000);
+        let ( text_cap, ends_cap ) = ( sink.text.capacity(),
+                                       sink.ends.capacity() );
+
+        let tok = shell_style_tokeniser();
+        assert_eq!(tok.tokenise_into_sink(line.as_slice(), &mut sink),
+                   Ok(()));
+        assert_eq!(sink.ends.len(), 1000);
+        assert_eq!(sink.text.capacity(), text_cap);
+        assert_eq!(sink.ends.capacity(), ends_cap);
+    }
+
+    #[test]
+    fn sink_told_of_failure() {
+        let mut sink = ArenaSink::with_capacity(16, 4);
+        let tok = shell_style_tokeniser();
+        assert_eq!(tok.tokenise_into_sink("a 'b", &mut sink),
+                   Err(UnmatchedQuote(2)));
+        assert_eq!(sink.failed, Some(UnmatchedQuote(2)));
+        assert_eq!(sink.words(), vec![ "a" ]);
+    }
+
+    #[test]
+    fn vec_sink_appends() {
+        let mut sink = vec![ "x".into_string() ];
+        let tok = whitespace_split_tokeniser();
+        assert_eq!(tok.tokenise_into_sink("a b", &mut sink), Ok(()));
+        assert_eq!(sink, vec![ "x".into_string(),
+                               "a".into_string(),
+                               "b".into_string() ]);
+    }
+
+    #[quickcheck]
+    fn vec_sink_matches_into_strings(line: String) -> bool {
+        let tok = shell_style_tokeniser();
+        let mut sink = vec![];
+        let sunk = tok.tokenise_into_sink(line.as_slice(), &mut sink);
+        let words = tok.clone().add_line(line.as_slice()).into_strings();
+
+        match ( sunk, words ) {
+            ( Ok(()), Ok(words) ) => sink == words,
+            ( Err(a), Err(b) )    => a == b,
+            _                     => false
+        }
+    }
+}
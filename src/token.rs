@@ -0,0 +1,113 @@
+//! The _Token_ class, for words along with how they were written.
+#![experimental]
+
+
+/// A word, along with how it was written in the input.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub struct Token {
+    /// The word, with quotes and escapes processed.
+    pub value: String,
+
+    /// How the word was written.
+    pub kind: TokenKind,
+
+    /// Whether an escape sequence was read in the word, in or out of
+    /// quotes.
+    pub had_escape: bool
+}
+
+impl Token {
+    /// Checks whether any part of the Token was quoted.
+    ///
+    /// A partly quoted word, such as `foo"bar"`, counts as quoted.
+    pub fn was_quoted(&self) -> bool {
+        self.kind.is_quoted()
+    }
+}
+
+
+/// How a word was written in the input.
+///
+/// Quotes are classified by their quote mode, not by their characters:
+/// quotes in which escapes are ignored are single quotes, and any others are
+/// double quotes.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub enum TokenKind {
+    /// The word is unquoted, and contains no escape sequences.
+    Bare,
+
+    /// The word is entirely inside quotes in which escapes are ignored.
+    SingleQuoted,
+
+    /// The word is entirely inside quotes in which escapes are read.
+    DoubleQuoted,
+
+    /// The word is unquoted, and contains at least one escape sequence.
+    Escaped,
+
+    /// The word is made of parts of different kinds, such as `foo"bar"`.
+    Mixed
+}
+
+impl TokenKind {
+    /// Checks whether any part of a word of this kind was quoted.
+    pub fn is_quoted(&self) -> bool {
+        match *self {
+            Bare | Escaped => false,
+            _              => true
+        }
+    }
+
+    /// Finds the kind of a word made of a part of this kind followed by a
+    /// part of kind `next`.
+    ///
+    /// Escape sequences in an unquoted word do not make it `Mixed`; the
+    /// word is `Escaped`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ Bare, DoubleQuoted, Escaped, Mixed };
+    ///
+    /// assert_eq!(Bare.combine(Escaped), Escaped);
+    /// assert_eq!(Bare.combine(DoubleQuoted), Mixed);
+    /// ```
+    pub fn combine(self, next: TokenKind) -> TokenKind {
+        match ( self, next ) {
+            ( Bare, Escaped ) | ( Escaped, Bare ) => Escaped,
+            ( a, b ) if a == b                    => a,
+            _                                     => Mixed
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{ Bare, SingleQuoted, DoubleQuoted, Escaped, Mixed };
+
+    #[test]
+    fn combine_same() {
+        for &kind in [ Bare, SingleQuoted, DoubleQuoted, Escaped, Mixed ]
+                     .iter() {
+            assert_eq!(kind.combine(kind), kind);
+        }
+    }
+
+    #[test]
+    fn is_quoted() {
+        assert!(!Bare.is_quoted());
+        assert!(!Escaped.is_quoted());
+        assert!(SingleQuoted.is_quoted());
+        assert!(DoubleQuoted.is_quoted());
+        assert!(Mixed.is_quoted());
+    }
+
+    #[test]
+    fn combine_different() {
+        assert_eq!(Escaped.combine(Bare), Escaped);
+        assert_eq!(SingleQuoted.combine(DoubleQuoted), Mixed);
+        assert_eq!(Escaped.combine(SingleQuoted), Mixed);
+        assert_eq!(Mixed.combine(Bare), Mixed);
+    }
+}
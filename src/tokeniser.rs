@@ -2,8 +2,33 @@
 #![experimental]
 
 use std::char::is_whitespace;
+use std::cmp;
+use std::collections::hashmap::HashMap;
+use std::default::Default;
+use std::error;
+use std::fmt;
+use std::io::{ Buffer, BufferedReader, EndOfFile, IoError, IoResult };
+use std::iter;
+use std::mem;
+use std::str;
+use std::sync::Arc;
+use std::vec::MoveItems;
+use time::precise_time_ns;
 
-use escape_scheme::EscapeScheme;
+use escape_scheme::{
+    EscapeScheme,
+    Escaped,
+    EscapedBefore,
+    Incomplete,
+    Invalid
+};
+use policy::{ Policy, PolicyViolation };
+use policy::{ ControlEscape, ForbiddenBare, Malformed };
+use policy::{ TooManyWords, WordTooLong };
+use quote::shell_quoter;
+use sink::WordSink;
+use token;
+use token::{ Token, TokenKind, Bare, SingleQuoted, DoubleQuoted };
 
 
 /// A tokeniser object.
@@ -21,6 +46,13 @@ pub struct Tokeniser<Q, E, S> {
     /// The current vector of parsed words.
     vec: Vec<String>,
 
+    /// The number of completed words held in `vec`.
+    held: uint,
+
+    /// Whether the current word has been begun in the WordSink being
+    /// written to.
+    begun: bool,
+
     /// The current tokeniser error, if any.
     /// An error ‘poisons’ the tokeniser, causing it to ignore any further
     /// input.
@@ -30,22 +62,128 @@ pub struct Tokeniser<Q, E, S> {
     in_word: bool,
 
     /// The current closing quote character and quote mode, if any.
-    quote: Option<( char, QuoteMode )>,
+    quote: Option<( char, QuoteMode<S> )>,
+
+    /// The character offset of the current opening quote, if any.
+    quote_at: uint,
+
+    /// The quote just closed, if a closing character straight after it
+    /// would reopen it.
+    reopen: Option<( char, QuoteMode<S> )>,
+
+    /// Whether the last character read was whitespace folded into a space.
+    folded: bool,
+
+    /// Whether the rest of the current line is being skipped.
+    skipping: bool,
 
     /// The current escape scheme in use, if any.
     escape: Option<S>,
 
+    /// The character offset of the current escape leader, if any.
+    escape_at: uint,
+
+    /// Whether the current escape leader, if any, started a word.
+    escape_began_word: bool,
+
+    /// Whether an escape leader followed by a newline is discarded along
+    /// with the newline.
+    line_continuation: bool,
+
+    /// The characters read since the current escape leader.
+    escape_seq: Vec<char>,
+
     /// Maps from quote openers to quote closers.
     quote_map: Q,
 
     /// Map from escape leader characters to their schemes.
     escape_map: E,
+
+    /// The number of characters fed into the tokeniser so far.
+    char_count: uint,
+
+    /// The number of bytes of UTF-8 fed into the tokeniser so far.
+    byte_count: uint,
+
+    /// The byte offset at which the current word started.
+    word_start: uint,
+
+    /// The character offset at which the current word started.
+    word_start_char: uint,
+
+    /// The kind of the current word so far, if any of it has been read.
+    kind: Option<TokenKind>,
+
+    /// Whether an escape sequence has been read in the current word.
+    had_escape: bool,
+
+    /// What is known about each completed word, besides its text.
+    info: Vec<WordInfo>,
+
+    /// The number of words completed so far.
+    word_count: uint,
+
+    /// The maximum number of words to split the input into, if any.
+    max_words: Option<uint>,
+
+    /// The length of the current line so far, in `line_unit`s.
+    line_len: uint,
+
+    /// The maximum permitted line length, if any.
+    max_line_len: Option<uint>,
+
+    /// The unit in which line lengths are measured.
+    line_unit: LengthUnit,
+
+    /// What may come before an opening quote.
+    before_quote: BeforeQuote,
+
+    /// What may come after a closing quote.
+    after_quote: AfterQuote,
+
+    /// The characters that separate words.
+    separators: Separators,
+
+    /// Whether every quotation keeps its quote characters.
+    keep_delimiters: bool,
+
+    /// The transformation applied to each character before it is read, if
+    /// any.
+    transform: Option<fn(char) -> char>,
+
+    /// The characters that start comments at the start of a word.
+    comment_chars: Vec<char>,
+
+    /// Whether comment characters also start comments in the middle of a
+    /// word.
+    comments_anywhere: bool,
+
+    /// The characters that end statements when unquoted and unescaped.
+    statement_terminators: Vec<char>,
+
+    /// The number of completed words held before each statement end.
+    statement_ends: Vec<uint>,
+
+    /// Whether consecutive separators produce empty words.
+    preserve_empty_fields: bool,
+
+    /// Whether each quoted part of a word is a word of its own.
+    split_adjacent_quotes: bool,
+
+    /// Whether the last character read closed a quote.
+    just_closed: bool,
+
+    /// Whether a line has been fed with `push_line` since the Tokeniser was
+    /// created or reset, so that the next line starts with a newline.
+    line_fed: bool
 }
 
 
 /// A quote mode.
-#[deriving(Clone)]
-pub enum QuoteMode {
+///
+/// The parameter `S` is the type of escape scheme used by `Custom`.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub enum QuoteMode<S> {
     /// All characters except the closing character have their literal value.
     /// This is equivalent to single-quoting in POSIX shell.
     IgnoreEscapes,
@@ -53,7 +191,221 @@ pub enum QuoteMode {
     /// All characters except the closing character and escape sequences
     /// have their literal value.  This is roughly equivalent to
     /// double-quoting in POSIX shell.
-    ParseEscapes
+    ParseEscapes,
+
+    /// As ParseEscapes, but each run of unescaped whitespace, including
+    /// newlines, is folded into a single space.  This is similar to YAML's
+    /// folded scalars.
+    FoldWhitespace,
+
+    /// All characters except the closing character and escape sequences
+    /// have their literal value, but escape sequences are started by the
+    /// given leader and interpreted by the given scheme, instead of by the
+    /// Tokeniser's escape map.
+    Custom(char, S),
+
+    /// All characters except the closing character have their literal
+    /// value, and the closing character written twice stands for itself.
+    /// This is the quoting of CSV fields.
+    DoubledEscape,
+
+    /// As the given quote mode, but the opening and closing quote
+    /// characters are kept in the word.
+    KeepDelimiters(Box<QuoteMode<S>>)
+}
+
+impl<S> QuoteMode<S> {
+    /// Checks whether the Tokeniser's escape map is used in this quote mode.
+    ///
+    /// This is false for `Custom`, which has its own escape scheme.
+    pub fn parses_escapes(&self) -> bool {
+        match *self {
+            IgnoreEscapes         => false,
+            ParseEscapes          => true,
+            FoldWhitespace        => true,
+            Custom(..)            => false,
+            DoubledEscape         => false,
+            KeepDelimiters(ref m) => m.parses_escapes()
+        }
+    }
+
+    /// Checks whether the quote characters are kept in words in this quote
+    /// mode.
+    pub fn keeps_delimiters(&self) -> bool {
+        match *self {
+            KeepDelimiters(_) => true,
+            _                 => false
+        }
+    }
+
+    /// Retrieves the quote mode that decides how characters inside the
+    /// quotes are read, looking through any `KeepDelimiters`.
+    pub fn base(&self) -> &QuoteMode<S> {
+        match *self {
+            KeepDelimiters(ref m) => m.base(),
+            ref m                 => m
+        }
+    }
+
+    /// Finds the kind of token read in this quote mode.
+    fn token_kind(&self) -> TokenKind {
+        match *self.base() {
+            IgnoreEscapes | DoubledEscape => SingleQuoted,
+            _                             => DoubleQuoted
+        }
+    }
+}
+
+
+/// A unit of length for line length limits.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub enum LengthUnit {
+    /// Lengths are measured in Unicode characters.
+    Chars,
+
+    /// Lengths are measured in bytes of UTF-8.
+    Bytes
+}
+
+
+/// A policy for how much of a word a streaming adaptor may buffer.
+///
+/// There is no default: a stream containing an unterminated quote would
+/// otherwise grow the buffer without bound.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub enum BufferPolicy {
+    /// Words may be at most the given number of bytes long.  A longer word
+    /// is reported as `LimitExceeded`, and the rest of its line is skipped.
+    Bounded(uint),
+
+    /// Words may be of any length.
+    Unbounded
+}
+
+
+/// The characters that separate words.
+#[deriving(Clone)]
+pub enum Separators {
+    /// Words are separated by Unicode whitespace.
+    Whitespace,
+
+    /// Words are separated by any of the given characters.
+    SeparatorChars(Vec<char>),
+
+    /// Words are separated by any character satisfying the given predicate.
+    SeparatorFn(fn(char) -> bool)
+}
+
+
+/// A policy for what may come before an opening quote.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub enum BeforeQuote {
+    /// A quote may open anywhere, including in the middle of a word.
+    /// This is the POSIX shell behaviour, where `a"b"` is the word `ab`.
+    AnyBeforeQuote,
+
+    /// A quote may only open at the start of a word, after a separator or
+    /// at the start of input.
+    SeparatorBeforeQuote,
+
+    /// A quote may only open at the start of a word; elsewhere, including
+    /// straight after a closing quote, the opening character is an
+    /// ordinary character.  This reads `it's` as one word.
+    LiteralQuoteInWord
+}
+
+
+/// A policy for what may come after a closing quote.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub enum AfterQuote {
+    /// Anything may follow a closing quote, continuing the word.  This is
+    /// the POSIX shell behaviour, where `"a"b` is the word `ab`.
+    AnyAfterQuote,
+
+    /// Only a separator, a statement terminator, an opening quote or the
+    /// end of input may follow a closing quote.
+    SeparatorAfterQuote
+}
+
+
+/// Whether word comparisons distinguish upper and lower case.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub enum CaseSensitivity {
+    /// Words match only if they are identical.
+    CaseSensitive,
+
+    /// Words match if they are identical after lowercasing.
+    CaseInsensitive
+}
+
+
+/// How words are ordered when comparing lines.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub enum WordOrder {
+    /// Words are ordered character by character.
+    LexicalOrder,
+
+    /// Words are ordered character by character, except that runs of
+    /// ASCII digits are ordered by their numeric value, so that `file2`
+    /// comes before `file10`.
+    NaturalOrder
+}
+
+
+/// The state of a Tokeniser between characters.
+///
+/// The parameter `S` is the type of escape scheme used by the Tokeniser.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub enum State<S> {
+    /// The Tokeniser is between words.
+    Ready,
+
+    /// The Tokeniser is in the middle of an unquoted word.
+    InWord,
+
+    /// The Tokeniser is inside a quotation, waiting for the given closing
+    /// character.  Escapes are handled according to the given mode.
+    InQuote(char, QuoteMode<S>),
+
+    /// The Tokeniser is in the middle of an escape sequence.
+    InEscape,
+
+    /// The Tokeniser has failed, and is ignoring further input.
+    Failed
+}
+
+
+/// A span of input, as byte or character offsets.
+///
+/// The span starts at offset `start` and ends just before offset `end`.
+#[deriving(Clone, Eq, PartialEq, Show)]
+pub struct Span {
+    /// The offset of the start of the span.
+    pub start: uint,
+
+    /// The offset just past the end of the span.
+    pub end: uint
+}
+
+
+/// A word paired with the Span of input it was read from.
+pub type SpannedToken = ( String, Span );
+
+
+/// What a Tokeniser knows about a completed word, besides its text.
+#[deriving(Clone)]
+struct WordInfo {
+    /// The byte span of the word.
+    span: Span,
+
+    /// The character span of the word.
+    char_span: Span,
+
+    /// How the word was written.
+    kind: TokenKind,
+
+    /// Whether an escape sequence was read in the word.
+    had_escape: bool
 }
 
 
@@ -61,21 +413,137 @@ pub enum QuoteMode {
 ///
 /// A Tokeniser's `into_strings` method can fail with one of the following
 /// errors if called while the Tokeniser is in an unfinished state.
-#[deriving(Clone, Eq, PartialEq, Show)]
+#[deriving(Clone, Eq, PartialEq)]
 pub enum Error {
     /// A quotation was opened, but not closed.
-    UnmatchedQuote,
+    ///
+    /// The field is the character offset of the opening quote.
+    UnmatchedQuote(uint),
 
     /// An escape sequence was started, but not finished.
-    UnfinishedEscape,
+    ///
+    /// The field is the character offset of the escape leader.
+    UnfinishedEscape(uint),
 
     /// An unknown escape sequence was encountered.
-    BadEscape
+    BadEscape,
+
+    /// A line exceeded the configured maximum line length.
+    ///
+    /// The first field is the limit, and the second the character offset
+    /// (from the start of all input) at which the limit was crossed.
+    LineTooLong(uint, uint),
+
+    /// A quote was opened in the middle of a word, when the Tokeniser
+    /// requires quotes to start words.
+    ///
+    /// The field is the character offset of the opening quote.
+    QuoteNotAtFieldStart(uint),
+
+    /// A closing quote was followed by something other than a separator or
+    /// an opening quote, when the Tokeniser requires quotes to end words.
+    ///
+    /// The fields are the offending character and its character offset.
+    TrailingGarbageAfterQuote(char, uint),
+
+    /// Input bytes were not valid UTF-8.
+    ///
+    /// The field is the byte offset of the first invalid byte.
+    InvalidUtf8(uint),
+
+    /// The words were longer in total than a given limit.
+    ///
+    /// The first field is the limit, and the second the total length, both
+    /// in bytes.
+    TotalLengthExceeded(uint, uint),
+
+    /// A word was longer than a streaming adaptor's bounded buffer.
+    ///
+    /// The field is the limit, in bytes.
+    LimitExceeded(uint),
+
+    /// A word held a NUL character, and so could not be made into a Path.
+    ///
+    /// The field is the index of the word.
+    NulInPath(uint),
+
+    /// Tokenising did not finish by a deadline.
+    ///
+    /// The field is the number of characters read before the deadline
+    /// passed.
+    DeadlineExceeded(uint)
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            UnmatchedQuote(..)       => "unmatched opening quote",
+            UnfinishedEscape(..)     =>
+                "incomplete escape sequence at end of input",
+            BadEscape                => "unknown escape sequence",
+            LineTooLong(..)          => "line too long",
+            QuoteNotAtFieldStart(..) => "quote opened in the middle of a word",
+            TrailingGarbageAfterQuote(..) =>
+                "text straight after closing quote",
+            InvalidUtf8(..)          => "invalid UTF-8",
+            TotalLengthExceeded(..)  => "words too long in total",
+            LimitExceeded(..)        => "word too long for buffer",
+            NulInPath(..)            => "NUL character in path",
+            DeadlineExceeded(..)     => "deadline exceeded"
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        // Tokeniser errors are never caused by other errors.
+        None
+    }
 }
 
+impl fmt::Show for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use std::error::Error;
+
+        match *self {
+            UnmatchedQuote(at) | UnfinishedEscape(at)
+                | QuoteNotAtFieldStart(at) =>
+                write!(f, "{} at character {}", self.description(), at),
+            LineTooLong(limit, at) =>
+                write!(f, "{} (limit {}, exceeded at character {})",
+                       self.description(), limit, at),
+            TrailingGarbageAfterQuote(chr, at) =>
+                write!(f, "{} at character {} ({})", self.description(), at,
+                       chr.escape_default().collect::<String>()),
+            InvalidUtf8(at) =>
+                write!(f, "{} at byte {}", self.description(), at),
+            TotalLengthExceeded(limit, total) =>
+                write!(f, "{} (limit {}, total {})",
+                       self.description(), limit, total),
+            LimitExceeded(limit) =>
+                write!(f, "{} (limit {})", self.description(), limit),
+            NulInPath(word) =>
+                write!(f, "{} in word {}", self.description(), word),
+            DeadlineExceeded(read) =>
+                write!(f, "{} after {} characters", self.description(), read),
+            _ => write!(f, "{}", self.description())
+        }
+    }
+}
+
+
+/// The time, in nanoseconds, that `tokenise_with_deadline` aims to leave
+/// between checks of the clock.
+static DEADLINE_CHECK_NS: u64 = 50000;
+
+/// The most characters `tokenise_with_deadline` reads between checks of
+/// the clock.
+static MAX_DEADLINE_STRIDE: uint = 4096;
+
+/// The number of bytes `add_read` reads from its Reader at a time.
+static READ_CHUNK_LEN: uint = 4096;
+
 
 impl<Q, E, S> Tokeniser<Q, E, S>
-    where Q: Map<char, ( char, QuoteMode )>,
+    where Q: Map<char, ( char, QuoteMode<S> )>,
           E: Map<char, S>,
           S: EscapeScheme,
           Q: Clone,
@@ -103,11 +571,13 @@ impl<Q, E, S> Tokeniser<Q, E, S>
     /// use russet::{ Tokeniser, ParseEscapes, QuoteMode };
     /// use russet::{ MapEscape, SimpleEscapeScheme };
     ///
-    /// let quote_map: HashMap<char, ( char, QuoteMode )> =
+    /// type Scheme = SimpleEscapeScheme<HashMap<char, char>>;
+    ///
+    /// let quote_map: HashMap<char, ( char, QuoteMode<Scheme> )> =
     ///     vec![ ( '\"', ( '\"', ParseEscapes ) ) ].move_iter().collect();
     /// let escape_pairs: HashMap<char, char> =
     ///     vec![ ( 'n', '\n' ) ].move_iter().collect();
-    /// let escape_map: HashMap<char, SimpleEscapeScheme<HashMap<char, char>>> =
+    /// let escape_map: HashMap<char, Scheme> =
     ///     vec![ ( '\\', MapEscape(escape_pairs) )].move_iter().collect();
     /// let tok = Tokeniser::new(quote_map, escape_map);
     /// assert_eq!(tok.into_strings(), Ok(vec![]));
@@ -115,174 +585,6016 @@ impl<Q, E, S> Tokeniser<Q, E, S>
     pub fn new(quote_map: Q, escape_map: E) -> Tokeniser<Q, E, S> {
         Tokeniser {
             vec: vec![ String::new() ],
+            held: 0,
+            begun: false,
             error: None,
             in_word: false,
             quote: None,
+            quote_at: 0,
+            reopen: None,
+            folded: false,
+            skipping: false,
             escape: None,
+            escape_at: 0,
+            escape_began_word: false,
+            line_continuation: false,
+            escape_seq: vec![],
             quote_map: quote_map,
-            escape_map: escape_map
+            escape_map: escape_map,
+            char_count: 0,
+            byte_count: 0,
+            word_start: 0,
+            word_start_char: 0,
+            kind: None,
+            had_escape: false,
+            info: vec![],
+            word_count: 0,
+            max_words: None,
+            line_len: 0,
+            max_line_len: None,
+            line_unit: Chars,
+            before_quote: AnyBeforeQuote,
+            after_quote: AnyAfterQuote,
+            separators: Whitespace,
+            keep_delimiters: false,
+            transform: None,
+            comment_chars: vec![],
+            comments_anywhere: false,
+            statement_terminators: vec![],
+            statement_ends: vec![],
+            preserve_empty_fields: false,
+            split_adjacent_quotes: false,
+            just_closed: false,
+            line_fed: false
         }
     }
 
-    /// Feeds a single character `chr` to a Tokeniser.
+    /// Creates a new, blank Tokeniser with the given separators.
+    ///
+    /// This is equivalent to `Tokeniser::new(quote_map, escape_map)`
+    /// followed by `with_separators(separators)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `quote_map`  - A map, mapping characters that serve as opening quotes
+    ///                  to their closing quotes and quote modes.
+    /// * `escape_map` - A map, mapping escape leader characters to their escape
+    ///                  schemes.  An empty map disables escapes.
+    /// * `separators` - The characters that separate words, outside quotes
+    ///                  and escape sequences.
     ///
     /// # Return value
     ///
-    /// A new Tokeniser, representing the state of the Tokeniser after
-    /// consuming `chr`.
+    /// A new Tokeniser, with an empty state.
+    pub fn new_with_separator(quote_map: Q, escape_map: E,
+                              separators: Separators) -> Tokeniser<Q, E, S> {
+        Tokeniser::new(quote_map, escape_map).with_separators(separators)
+    }
+
+    /// Sets the maximum line length of a Tokeniser.
+    ///
+    /// Lines are delimited by `\n`, which does not count towards the line
+    /// length.  As soon as a line grows beyond `limit`, the Tokeniser fails
+    /// with `LineTooLong` and ignores any further input.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The maximum line length, or `None` for no limit.
+    /// * `unit`  - Whether `limit` is measured in characters or bytes.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, with the given line length limit.
     ///
     /// # Example
     ///
     /// ```rust
-    /// use russet::whitespace_split_tokeniser;
+    /// use russet::{ whitespace_split_tokeniser, Chars };
+    /// use russet::tokeniser::LineTooLong;
     ///
     /// let tok = whitespace_split_tokeniser();
-    /// let tok2 = tok.add_char('a').add_char('b').add_char('c');
-    /// assert_eq!(tok2.into_strings(), Ok(vec![ "abc".into_string() ]));
+    /// let tok = tok.with_max_line_len(Some(4), Chars);
+    /// assert_eq!(tok.add_line("abcde").into_strings(),
+    ///            Err(LineTooLong(4, 4)));
     /// ```
-    pub fn add_char(self, chr: char) -> Tokeniser<Q, E, S> {
-        let mut new = self.clone();
-
-        match (chr, self) {
-            // ERROR
-            //   Found an error
-            //   -> Ignore input
-            ( _, Tokeniser { error: Some(_), .. } ) => (),
-
-            // ESCAPE SEQUENCES
-            //   Currently escaping
-            //   -> Escape via escape scheme.
-            ( c, Tokeniser { escape: Some(s), .. } ) => match s.escape(c) {
-                Some(cc) => new.emit(cc),
-                None     => { new.error = Some(BadEscape); }
-            },
-
-            // ESCAPE LEADER
-            //   Escape leader, not in quotes
-            //   -> Begin escape (and word if not in one already)
-            ( c, Tokeniser { escape: None,
-                             quote: None,
-                             escape_map: ref e, .. } ) if e.contains_key(&c) =>
-                new.start_escaping(c),
-            //   Escape leader, in escape-permitting quotes
-            //   -> Begin escape (and word if not in one already)
-            ( c, Tokeniser { escape: None,
-                             quote: Some(( _, ParseEscapes )),
-                             escape_map: ref e, .. } ) if e.contains_key(&c) =>
-                new.start_escaping(c),
-
-            // QUOTE OPENING
-            //   Quote opening character, not currently in quoted word
-            //   -> Start quoting
-            ( c, Tokeniser { escape: None, quote: None, quote_map: ref q, .. } )
-                if q.contains_key(&c) => {
-                new.quote = Some(q.find(&c).unwrap().clone());
-                new.in_word = true;
-            },
-
-            // QUOTE CLOSING
-            //   Quote closing character, in quoted word, quotes ok
-            //   -> Stop quoting
-            ( c, Tokeniser { escape: None, quote: Some(( cc, _ )), .. } )
-                if c == cc => {
-                new.quote = None;
-                new.in_word = true;
-            },
-
-            // UNESCAPED WHITESPACE
-            //   Unescaped whitespace, while not in a word
-            //   -> Ignore
-            ( a, Tokeniser { escape: None, in_word: false, .. } )
-                if is_whitespace(a) => (),
-            //   Unescaped whitespace, while in a non-quoted word
-            //   -> End word
-            ( a, Tokeniser { escape: None, in_word: true, quote: None, .. } )
-                if is_whitespace(a) => {
-                new.in_word = false;
-                new.vec.push(String::new());
-            },
-
-            // DEFAULT
-            //   Anything else
-            //   -> Echo
-            ( a, _ ) => new.emit(a)
-        }
-
-        new
+    pub fn with_max_line_len(mut self, limit: Option<uint>, unit: LengthUnit)
+      -> Tokeniser<Q, E, S> {
+        self.max_line_len = limit;
+        self.line_unit = unit;
+        self
     }
 
-    /// Feeds an Iterator of chars, `it`, into the Tokeniser.
+    /// Sets the maximum number of words a Tokeniser splits its input into.
+    ///
+    /// Once all but the last of the words have been read, the separators
+    /// after them are skipped, and everything from there on is taken
+    /// verbatim as the last word.  Quotes and escapes in this word are not
+    /// interpreted, so an unmatched quote in it is not an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The maximum number of words, or `None` for no limit.
+    ///             A limit of zero is treated as a limit of one.
     ///
     /// # Return value
     ///
-    /// A new Tokeniser, representing the state of the Tokeniser after
-    /// consuming the characters in `it`.
-    pub fn add_iter<I: Iterator<char>>(self, mut it: I) -> Tokeniser<Q, E, S> {
-        it.fold(self, |s, chr| s.add_char(chr))
+    /// A new Tokeniser, with the given word limit.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().with_max_words(Some(3));
+    /// let tok = tok.add_line("MSG 'my friend' it's  \"here\"");
+    /// assert_eq!(tok.into_strings(),
+    ///            Ok(vec![ "MSG".into_string(),
+    ///                     "my friend".into_string(),
+    ///                     "it's  \"here\"".into_string() ]));
+    /// ```
+    pub fn with_max_words(mut self, limit: Option<uint>)
+      -> Tokeniser<Q, E, S> {
+        self.max_words = limit;
+        self
     }
 
-    /// Feeds a string, `string`, into the Tokeniser.
+    /// Sets what may come before an opening quote in a Tokeniser.
     ///
     /// # Return value
     ///
-    /// A new Tokeniser, representing the state of the Tokeniser after
-    /// consuming `string`.
-    pub fn add_string(self, string: &str) -> Tokeniser<Q, E, S> {
-        self.add_iter(string.chars())
+    /// A new Tokeniser, with the given policy.  If the policy is
+    /// `SeparatorBeforeQuote`, opening a quote in the middle of a word fails
+    /// the Tokeniser with `QuoteNotAtFieldStart`; if it is
+    /// `LiteralQuoteInWord`, the opening character is part of the word.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, SeparatorBeforeQuote };
+    /// use russet::LiteralQuoteInWord;
+    /// use russet::tokeniser::QuoteNotAtFieldStart;
+    ///
+    /// let tok = shell_style_tokeniser();
+    /// let tok = tok.with_before_quote(SeparatorBeforeQuote);
+    /// assert_eq!(tok.add_line("ab c\"d\"").into_strings(),
+    ///            Err(QuoteNotAtFieldStart(4)));
+    ///
+    /// let tok = shell_style_tokeniser();
+    /// let tok = tok.with_before_quote(LiteralQuoteInWord);
+    /// assert_eq!(tok.add_line("\"O'Brien\" it's").into_strings(),
+    ///            Ok(vec![ "O'Brien".into_string(),
+    ///                     "it's".into_string() ]));
+    /// ```
+    pub fn with_before_quote(mut self, policy: BeforeQuote)
+      -> Tokeniser<Q, E, S> {
+        self.before_quote = policy;
+        self
     }
 
-    /// Feeds a line, `line`, into the Tokeniser.
-    /// This differs from `add_str` in that the line is whitespace-trimmed
-    /// before adding.
+    /// Sets what may come after a closing quote in a Tokeniser.
     ///
     /// # Return value
     ///
-    /// A new Tokeniser, representing the state of the Tokeniser after
-    /// consuming `line`.
-    pub fn add_line(self, line: &str) -> Tokeniser<Q, E, S> {
-        self.add_string(line.trim())
+    /// A new Tokeniser, with the given policy.  If the policy is
+    /// `SeparatorAfterQuote`, anything but a separator, statement
+    /// terminator or opening quote straight after a closing quote fails the
+    /// Tokeniser with `TrailingGarbageAfterQuote`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, SeparatorAfterQuote };
+    /// use russet::tokeniser::TrailingGarbageAfterQuote;
+    ///
+    /// let tok = shell_style_tokeniser();
+    /// let tok = tok.with_after_quote(SeparatorAfterQuote);
+    /// assert_eq!(tok.clone().add_line("cd \"path\" more").into_strings(),
+    ///            Ok(vec![ "cd".into_string(),
+    ///                     "path".into_string(),
+    ///                     "more".into_string() ]));
+    /// assert_eq!(tok.add_line("cd \"path\"more").into_strings(),
+    ///            Err(TrailingGarbageAfterQuote('m', 9)));
+    /// ```
+    pub fn with_after_quote(mut self, policy: AfterQuote)
+      -> Tokeniser<Q, E, S> {
+        self.after_quote = policy;
+        self
     }
 
-    /// Destroys the tokeniser, extracting the string vector.
+    /// Sets the characters that separate words in a Tokeniser.
+    ///
+    /// Separators inside quotes, or escaped, are part of the word as usual.
     ///
     /// # Return value
     ///
-    /// A Result, containing the tokenised string vector if the Tokeniser
-    /// was in a valid ending state, and an Error otherwise.
-    pub fn into_strings(mut self) -> Result<Vec<String>, Error> {
-        if self.error.is_some() {
-            Err(self.error.unwrap())
-        } else if self.in_word && self.quote.is_some() {
-            Err(UnmatchedQuote)
-        } else if self.escape.is_some() {
-            Err(UnfinishedEscape)
-        } else {
-            self.drop_empty_current_string();
-            Ok(self.vec)
-        }
+    /// A new Tokeniser, with the given separators.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, SeparatorChars };
+    ///
+    /// let tok = shell_style_tokeniser();
+    /// let tok = tok.with_separators(SeparatorChars(vec![ ':' ]));
+    /// assert_eq!(tok.add_line("/bin:'/my bin':/usr\\:bin").into_strings(),
+    ///            Ok(vec![ "/bin".into_string(),
+    ///                     "/my bin".into_string(),
+    ///                     "/usr:bin".into_string() ]));
+    /// ```
+    pub fn with_separators(mut self, separators: Separators)
+      -> Tokeniser<Q, E, S> {
+        self.separators = separators;
+        self
     }
 
-    /// Adds a character into a Tokeniser's current string.
-    /// This automatically sets the Tokeniser's state to be in a word,
-    /// and clears any escape sequence flag.
-    fn emit(&mut self, c: char) {
-        self.in_word = true;
-        self.escape = None;
-        self.vec.mut_last().mutate(|s| { s.push_char(c); s });
+    /// Sets a predicate deciding which characters separate words in a
+    /// Tokeniser.
+    ///
+    /// This is equivalent to `with_separators(SeparatorFn(separator))`.
+    /// The predicate is not consulted inside quotes or escape sequences.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, with the given separator predicate.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// fn is_space(c: char) -> bool { c == ' ' }
+    ///
+    /// let tok = shell_style_tokeniser().with_separator_fn(is_space);
+    /// assert_eq!(tok.add_line("a\tb c").into_strings(),
+    ///            Ok(vec![ "a\tb".into_string(), "c".into_string() ]));
+    /// ```
+    pub fn with_separator_fn(self, separator: fn(char) -> bool)
+      -> Tokeniser<Q, E, S> {
+        self.with_separators(SeparatorFn(separator))
+    }
+
+    /// Sets whether consecutive separators in a Tokeniser produce empty
+    /// words.
+    ///
+    /// When set, every unquoted separator ends a word, even an empty one,
+    /// and a separator at the end of input is followed by an empty word.
+    /// This suits formats with empty fields, such as CSV or `/etc/passwd`.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, which keeps empty words between separators if
+    /// `preserve` is true, and skips runs of separators otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, SeparatorChars };
+    ///
+    /// let tok = shell_style_tokeniser()
+    ///     .with_separators(SeparatorChars(vec![ ':' ]))
+    ///     .with_preserve_empty_fields(true);
+    /// assert_eq!(tok.add_line("a::b:").into_strings(),
+    ///            Ok(vec![ "a".into_string(),
+    ///                     "".into_string(),
+    ///                     "b".into_string(),
+    ///                     "".into_string() ]));
+    /// ```
+    pub fn with_preserve_empty_fields(mut self, preserve: bool)
+      -> Tokeniser<Q, E, S> {
+        self.preserve_empty_fields = preserve;
+        self
+    }
+
+    /// Sets whether each quoted part of a word is a word of its own.
+    ///
+    /// Normally, as in the shell, quoted and unquoted parts with nothing
+    /// between them make up one word.  With this set, a word ends when a
+    /// quote closes or opens within it, so `"a"'b'c` is three words, as is
+    /// `x"y"z`.  A doubled closing character in a `DoubledEscape` quote
+    /// still stands for itself.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, which splits words at quotes if `split` is true,
+    /// and joins the parts of words otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().with_split_adjacent_quotes(true);
+    /// assert_eq!(tok.add_line("x\"a\"'b'c d").into_strings(),
+    ///            Ok(vec![ "x".into_string(),
+    ///                     "a".into_string(),
+    ///                     "b".into_string(),
+    ///                     "c".into_string(),
+    ///                     "d".into_string() ]));
+    /// ```
+    pub fn with_split_adjacent_quotes(mut self, split: bool)
+      -> Tokeniser<Q, E, S> {
+        self.split_adjacent_quotes = split;
+        self
+    }
+
+    /// Sets whether an escape leader followed by a newline continues the
+    /// line.
+    ///
+    /// As in POSIX shell, a line continuation stands for nothing at all:
+    /// both the escape leader and the newline are discarded, rather than
+    /// giving a literal newline.  A continuation between two words does not
+    /// join them, but one inside a word does.  Continuations are read
+    /// wherever escapes are, so not inside quotes that ignore escapes.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, which reads line continuations if `continuation`
+    /// is true, and escaped newlines otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().with_line_continuation(true);
+    /// assert_eq!(tok.clone().add_string("a \\\nb\\\nc").into_strings(),
+    ///            Ok(vec![ "a".into_string(), "bc".into_string() ]));
+    /// assert_eq!(tok.add_line("a \\").add_line("b").into_strings(),
+    ///            Ok(vec![ "a".into_string(), "b".into_string() ]));
+    /// ```
+    pub fn with_line_continuation(mut self, continuation: bool)
+      -> Tokeniser<Q, E, S> {
+        self.line_continuation = continuation;
+        self
+    }
+
+    /// Sets whether a Tokeniser keeps quote characters in words.
+    ///
+    /// This applies `KeepDelimiters` to every quote mode in the quote map.
+    /// Escapes inside quotes are still handled as the quote mode says.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, which keeps quote characters if `keep` is true, and
+    /// keeps them only for `KeepDelimiters` quotes otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().with_keep_delimiters(true);
+    /// assert_eq!(tok.add_line("say \"hello world\"").into_strings(),
+    ///            Ok(vec![ "say".into_string(),
+    ///                     "\"hello world\"".into_string() ]));
+    /// ```
+    pub fn with_keep_delimiters(mut self, keep: bool) -> Tokeniser<Q, E, S> {
+        self.keep_delimiters = keep;
+        self
+    }
+
+    /// Sets a transformation applied to each character fed into a Tokeniser
+    /// before it is read.
+    ///
+    /// This can be used to normalise case, or to map characters onto
+    /// others before tokenising.  Offsets in errors and spans still refer
+    /// to the untransformed input.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, with the given transformation.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// fn lower(c: char) -> char { c.to_lowercase() }
+    ///
+    /// let tok = shell_style_tokeniser().with_input_transform(lower);
+    /// assert_eq!(tok.add_line("LS 'My Files'").into_strings(),
+    ///            Ok(vec![ "ls".into_string(), "my files".into_string() ]));
+    /// ```
+    pub fn with_input_transform(mut self, transform: fn(char) -> char)
+      -> Tokeniser<Q, E, S> {
+        self.transform = Some(transform);
+        self
+    }
+
+    /// Sets the characters that start comments in a Tokeniser.
+    ///
+    /// A comment character starts a comment only where a word could start,
+    /// and not inside quotes or escaped.  The rest of the line after it is
+    /// ignored.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, with the given comment characters.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().with_comment_chars(vec![ '#' ]);
+    /// assert_eq!(tok.add_line("echo a#b '#c' # d").into_strings(),
+    ///            Ok(vec![ "echo".into_string(),
+    ///                     "a#b".into_string(),
+    ///                     "#c".into_string() ]));
+    /// ```
+    pub fn with_comment_chars(mut self, chars: Vec<char>)
+      -> Tokeniser<Q, E, S> {
+        self.comment_chars = chars;
+        self
+    }
+
+    /// Sets whether comment characters start comments in the middle of
+    /// words in a Tokeniser.
+    ///
+    /// Normally, as in the shell, a comment character inside a word is part
+    /// of the word.  With this set, it ends the word and starts a comment,
+    /// as in many configuration files.  Comment characters in quotes or
+    /// escaped are still part of words.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, which starts comments anywhere outside quotes if
+    /// `anywhere` is true, and only at the start of words otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().with_comment_chars(vec![ '#' ])
+    ///                                  .with_comments_anywhere(true);
+    /// assert_eq!(tok.add_line("echo a#b '#c' \\#d").into_strings(),
+    ///            Ok(vec![ "echo".into_string(), "a".into_string() ]));
+    /// ```
+    pub fn with_comments_anywhere(mut self, anywhere: bool)
+      -> Tokeniser<Q, E, S> {
+        self.comments_anywhere = anywhere;
+        self
+    }
+
+    /// Sets the characters that end statements, for `into_statements`.
+    ///
+    /// A statement terminator ends both the current word and the current
+    /// statement, unless it is quoted or escaped.  A newline that ends a
+    /// comment still ends a statement.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, with the given statement terminators.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok =
+    ///     shell_style_tokeniser().with_statement_terminators(vec![ ';' ]);
+    /// assert_eq!(tok.add_line("cd /tmp; ls").into_statements(),
+    ///            Ok(vec![ vec![ "cd".into_string(), "/tmp".into_string() ],
+    ///                     vec![ "ls".into_string() ] ]));
+    /// ```
+    pub fn with_statement_terminators(mut self, chars: Vec<char>)
+      -> Tokeniser<Q, E, S> {
+        self.statement_terminators = chars;
+        self
+    }
+
+    /// Resets a Tokeniser, discarding everything fed into it but keeping its
+    /// configuration.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, equivalent to a fresh Tokeniser with the same quote
+    /// map, escape map and limits.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().add_line("abc 'def");
+    /// assert_eq!(tok.reset().add_line("ghi").into_strings(),
+    ///            Ok(vec![ "ghi".into_string() ]));
+    /// ```
+    pub fn reset(mut self) -> Tokeniser<Q, E, S> {
+        self.clear();
+        self
+    }
+
+    /// Resets a Tokeniser in place.
+    ///
+    /// This is the mutating equivalent of `reset`.
+    pub fn clear(&mut self) {
+        self.vec = vec![ String::new() ];
+        self.held = 0;
+        self.begun = false;
+        self.error = None;
+        self.in_word = false;
+        self.quote = None;
+        self.quote_at = 0;
+        self.reopen = None;
+        self.just_closed = false;
+        self.folded = false;
+        self.skipping = false;
+        self.escape = None;
+        self.escape_at = 0;
+        self.escape_began_word = false;
+        self.escape_seq.clear();
+        self.char_count = 0;
+        self.byte_count = 0;
+        self.word_start = 0;
+        self.word_start_char = 0;
+        self.kind = None;
+        self.had_escape = false;
+        self.info.clear();
+        self.word_count = 0;
+        self.line_len = 0;
+        self.statement_ends.clear();
+        self.line_fed = false;
+    }
+
+    /// Replaces a completed word in the Tokeniser.
+    ///
+    /// Only words that have been ended by a separator can be replaced; the
+    /// word currently being read cannot.
+    ///
+    /// # Arguments
+    ///
+    /// * `index`    - The index of the completed word to replace.
+    /// * `new_word` - The replacement word.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, with the word at `index` replaced by `new_word`.
+    /// If there is no completed word at `index`, the Tokeniser is returned
+    /// unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser().add_line("ls -l -a ");
+    /// assert_eq!(tok.replace_word(1, "--long").into_strings(),
+    ///            Ok(vec![ "ls".into_string(),
+    ///                     "--long".into_string(),
+    ///                     "-a".into_string() ]));
+    /// ```
+    pub fn replace_word(mut self, index: uint, new_word: &str)
+      -> Tokeniser<Q, E, S> {
+        if index + 1 < self.vec.len() {
+            *self.vec.get_mut(index) = new_word.into_string();
+        }
+        self
+    }
+
+    /// Discards the word currently being read, and skips the rest of the
+    /// current line.
+    ///
+    /// Any quotation or escape sequence in progress is abandoned.  Input is
+    /// ignored up to and including the next `\n`, after which the Tokeniser
+    /// reads words as normal.  Completed words are kept.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let mut tok = shell_style_tokeniser();
+    /// tok.push_str("a 'b c");
+    /// tok.skip_line();
+    /// tok.push_str("d' e\nf");
+    /// assert_eq!(tok.into_strings(),
+    ///            Ok(vec![ "a".into_string(), "f".into_string() ]));
+    /// ```
+    pub fn skip_line(&mut self) {
+        // Replace the word, rather than clearing it, to free its buffer.
+        *self.vec.mut_last().unwrap() = String::new();
+        self.begun = false;
+        self.kind = None;
+        self.had_escape = false;
+        self.in_word = false;
+        self.quote = None;
+        self.folded = false;
+        self.escape = None;
+        self.escape_seq.clear();
+        self.skipping = true;
+    }
+
+    /// Checks the word currently being read against a buffer policy.
+    ///
+    /// Streaming adaptors call this after each character.  If the word has
+    /// outgrown a `Bounded` policy, the rest of its line is skipped, as
+    /// with `skip_line`.
+    ///
+    /// # Return value
+    ///
+    /// A Result, which is an Error if the word was too long, and empty
+    /// otherwise.
+    pub fn enforce_buffer_policy(&mut self, policy: &BufferPolicy)
+      -> Result<(), Error> {
+        let len = self.vec.last().map(|w| w.len()).unwrap_or(0);
+        match *policy {
+            Bounded(limit) if len > limit => {
+                self.skip_line();
+                Err(LimitExceeded(limit))
+            },
+            _ => Ok(())
+        }
+    }
+
+    /// Retrieves the number of bytes allocated for the word currently being
+    /// read.
+    pub fn buffer_capacity(&self) -> uint {
+        self.vec.last().map(|w| w.capacity()).unwrap_or(0)
+    }
+
+    /// Removes the completed words from the Tokeniser, without finishing
+    /// the word currently being read.
+    ///
+    /// This allows words to be taken from a Tokeniser as input arrives,
+    /// with `into_strings` finishing the last of them at the end of input.
+    ///
+    /// # Return value
+    ///
+    /// The words completed since the Tokeniser was created, reset or last
+    /// drained.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let mut tok = shell_style_tokeniser();
+    /// tok.push_str("ls -l 'my fi");
+    /// assert_eq!(tok.drain_completed(),
+    ///            vec![ "ls".into_string(), "-l".into_string() ]);
+    /// tok.push_str("le'");
+    /// assert_eq!(tok.into_strings(), Ok(vec![ "my file".into_string() ]));
+    /// ```
+    pub fn drain_completed(&mut self) -> Vec<String> {
+        // The last string is always the word in progress.
+        let current = self.vec.pop().unwrap_or(String::new());
+        self.held = 0;
+        self.info.clear();
+        self.statement_ends.clear();
+        mem::replace(&mut self.vec, vec![ current ])
+    }
+
+    /// Feeds a single character `chr` to a Tokeniser.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, representing the state of the Tokeniser after
+    /// consuming `chr`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser();
+    /// let tok2 = tok.add_char('a').add_char('b').add_char('c');
+    /// assert_eq!(tok2.into_strings(), Ok(vec![ "abc".into_string() ]));
+    /// ```
+    pub fn add_char(mut self, chr: char) -> Tokeniser<Q, E, S> {
+        self.push_char(chr);
+        self
+    }
+
+    /// Feeds a single character `chr` to a Tokeniser, after transforming
+    /// it with `f`.
+    ///
+    /// `f` is applied before any transformation set with
+    /// `with_input_transform`.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, representing the state of the Tokeniser after
+    /// consuming the transformed `chr`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser();
+    /// let tok2 = tok.add_char_with_transform('a', |c| c.to_uppercase());
+    /// assert_eq!(tok2.into_strings(), Ok(vec![ "A".into_string() ]));
+    /// ```
+    pub fn add_char_with_transform(self, chr: char, f: |char| -> char)
+      -> Tokeniser<Q, E, S> {
+        self.add_char(f(chr))
+    }
+
+    /// Feeds an Iterator of chars, `it`, into the Tokeniser.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, representing the state of the Tokeniser after
+    /// consuming the characters in `it`.
+    pub fn add_iter<I: Iterator<char>>(mut self, it: I) -> Tokeniser<Q, E, S> {
+        self.feed_iter(it);
+        self
+    }
+
+    /// Feeds a string, `string`, into the Tokeniser.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, representing the state of the Tokeniser after
+    /// consuming `string`.
+    pub fn add_string(mut self, string: &str) -> Tokeniser<Q, E, S> {
+        self.push_str(string);
+        self
+    }
+
+    /// Feeds the contents of a Buffer, `reader`, into the Tokeniser.
+    ///
+    /// The Buffer is read one character at a time, so it is never read
+    /// entirely into memory.  Reading stops early if the Tokeniser fails,
+    /// for example by exceeding the maximum line length.  A word outgrowing
+    /// `policy` also fails the Tokeniser, with `LimitExceeded`: unlike
+    /// `tokens`, there is nowhere to report the word and carry on.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The Buffer to read.
+    /// * `policy` - How long the word being read may grow.
+    ///
+    /// # Return value
+    ///
+    /// An IoResult, containing a new Tokeniser representing the state of
+    /// the Tokeniser after consuming `reader` if reading succeeded, and the
+    /// IoError otherwise.  Invalid UTF-8 is reported as an IoError.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::io::MemReader;
+    /// use russet::{ shell_style_tokeniser, Unbounded };
+    ///
+    /// let reader = MemReader::new(b"abc 'd e'".to_vec());
+    /// let tok = shell_style_tokeniser().add_reader(reader, Unbounded)
+    ///                                  .unwrap();
+    /// assert_eq!(tok.into_strings(),
+    ///            Ok(vec![ "abc".into_string(), "d e".into_string() ]));
+    /// ```
+    pub fn add_reader<R: Buffer>(mut self, mut reader: R, policy: BufferPolicy)
+      -> IoResult<Tokeniser<Q, E, S>> {
+        try!(self.feed_reader(&mut reader, policy));
+        Ok(self)
+    }
+
+    /// Feeds the contents of a Reader, `reader`, into the Tokeniser.
+    ///
+    /// This is `add_reader` for Readers without their own buffer, such as
+    /// Files.  The Reader is read 4096 bytes at a time, and characters split
+    /// between reads are put back together.  If the Tokeniser fails, reading
+    /// stops early, and any bytes read past the offending character are
+    /// lost.  As with `add_reader`, a word outgrowing `policy` fails the
+    /// Tokeniser with `LimitExceeded`.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The Reader to read.
+    /// * `policy` - How long the word being read may grow.
+    ///
+    /// # Return value
+    ///
+    /// An IoResult, containing a new Tokeniser representing the state of
+    /// the Tokeniser after consuming `reader` if reading succeeded, and the
+    /// IoError otherwise.  Invalid UTF-8 is reported as an IoError; errors
+    /// in the input itself, such as unmatched quotes, are reported by
+    /// `into_strings` as usual.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::io::MemReader;
+    /// use russet::{ shell_style_tokeniser, Unbounded };
+    ///
+    /// let mut reader = MemReader::new(b"abc 'd e'".to_vec());
+    /// let tok = shell_style_tokeniser().add_read(&mut reader, Unbounded)
+    ///                                  .unwrap();
+    /// assert_eq!(tok.into_strings(),
+    ///            Ok(vec![ "abc".into_string(), "d e".into_string() ]));
+    /// ```
+    pub fn add_read<R: Reader>(mut self, reader: &mut R, policy: BufferPolicy)
+      -> IoResult<Tokeniser<Q, E, S>> {
+        try!(self.feed_read(reader, policy));
+        Ok(self)
+    }
+
+    /// Feeds a single character `chr` to a Tokeniser, in place.
+    ///
+    /// This is the mutating equivalent of `add_char`, and avoids moving the
+    /// Tokeniser on every character.  All other means of feeding the
+    /// Tokeniser are implemented in terms of this method.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let mut tok = whitespace_split_tokeniser();
+    /// for c in "abc".chars() {
+    ///     tok.push_char(c);
+    /// }
+    /// assert_eq!(tok.into_strings(), Ok(vec![ "abc".into_string() ]));
+    /// ```
+    pub fn push_char(&mut self, chr: char) {
+        let mut buffer = WordBuffer { words: mem::replace(&mut self.vec,
+                                                          vec![]) };
+        self.push_char_to(chr, &mut buffer);
+        self.vec = buffer.words;
+    }
+
+    /// Feeds a single character `chr` to a Tokeniser, writing any word
+    /// text to `sink`.
+    fn push_char_to<K: WordSink>(&mut self, chr: char, sink: &mut K) {
+        let byte_pos = self.byte_count;
+        let char_pos = self.char_count;
+        self.byte_count += chr.len_utf8_bytes();
+
+        // A quote opening or closing mid-word ends the word before this
+        // character, as if a separator were there.
+        if self.splits_before(chr) {
+            self.in_word = false;
+            self.end_word_to(sink);
+            self.complete_word(byte_pos, char_pos);
+        }
+
+        let was_in_word = self.in_word;
+        let words = self.held;
+
+        self.step(chr, sink);
+
+        if !was_in_word && ( self.in_word || words < self.held ) {
+            // An empty field starts, and ends, at its separator.
+            self.word_start = byte_pos;
+            self.word_start_char = char_pos;
+        }
+        if words < self.held {
+            self.complete_word(byte_pos, char_pos);
+        }
+    }
+
+    /// Records what is known about the word just completed, which ended at
+    /// the given byte and character offsets.
+    fn complete_word(&mut self, byte_end: uint, char_end: uint) {
+        let info = self.current_info(byte_end, char_end);
+        self.info.push(info);
+        self.kind = None;
+        self.had_escape = false;
+        self.word_count += 1;
+    }
+
+    /// Checks whether the word being read ends before `chr`, because the
+    /// Tokeniser splits adjacent quotes and `chr` would join a quoted part
+    /// of the word to another part.
+    fn splits_before(&self, chr: char) -> bool {
+        let chr = match self.transform {
+            Some(f) => f(chr),
+            None    => chr
+        };
+
+        if !self.split_adjacent_quotes || !self.in_word
+           || self.error.is_some() || self.escape.is_some()
+           || self.quote.is_some() || self.skipping
+           || self.at_word_limit() || self.is_separator(chr) {
+            return false;
+        }
+
+        if self.just_closed {
+            // A doubled closing character reopens the quote instead.
+            !self.reopen.as_ref().map_or(false, |&( cc, _ )| cc == chr)
+        } else {
+            self.quote_map.contains_key(&chr)
+                && self.before_quote != LiteralQuoteInWord
+        }
+    }
+
+    /// Runs the Tokeniser's state machine on a single character `chr`,
+    /// writing any word text to `sink`.
+    fn step<K: WordSink>(&mut self, chr: char, sink: &mut K) {
+        let pos = self.char_count;
+        self.count_char(chr);
+        let after_close = mem::replace(&mut self.just_closed, false);
+
+        let chr = match self.transform {
+            Some(f) => f(chr),
+            None    => chr
+        };
+
+        // ERROR
+        //   Found an error
+        //   -> Ignore input
+        if self.error.is_some() { return; }
+
+        // SKIPPED LINE
+        //   Skipping the rest of a line
+        //   -> Ignore input, up to and including the newline, unless the
+        //      newline ends a statement
+        if self.skipping {
+            self.skipping = chr != '\n';
+            if self.skipping || !self.statement_terminators.contains(&chr) {
+                return;
+            }
+        }
+
+        // ESCAPE SEQUENCES
+        //   Currently escaping
+        //   -> Escape via escape scheme.
+        //   Escape sequence ended before this character
+        //   -> Emit sequence, then read character as normal.
+        match self.escape.take() {
+            // LINE CONTINUATION
+            //   Newline straight after an escape leader, continuations on
+            //   -> Discard both, ending any word the leader started
+            Some(..) if self.line_continuation && chr == '\n'
+                        && self.escape_seq.is_empty() => {
+                if self.escape_began_word && self.quote.is_none() {
+                    self.in_word = false;
+                    self.kind = None;
+                    self.had_escape = false;
+                }
+                return;
+            },
+            Some(s) => {
+                self.escape_seq.push(chr);
+                match s.escape_seq(self.escape_seq.as_slice()) {
+                    Escaped(c)       => { self.emit_to(c, sink); return; },
+                    EscapedBefore(c) => self.emit_to(c, sink),
+                    Incomplete       => { self.escape = Some(s); return; },
+                    Invalid          => {
+                        self.error = Some(BadEscape);
+                        return;
+                    }
+                }
+            },
+            None => ()
+        }
+
+        // WORD LIMIT
+        //   Reading the last permitted word, separator before it
+        //   -> Ignore
+        //   Reading the last permitted word, otherwise
+        //   -> Echo verbatim
+        if self.at_word_limit() {
+            if self.in_word || !self.is_separator(chr) {
+                self.note_kind(Bare);
+                self.emit_to(chr, sink);
+            }
+            return;
+        }
+
+        // DOUBLED QUOTES
+        //   Closing character, straight after closing a quote in which it
+        //   stands for itself when doubled
+        //   -> Echo, and reopen the quote
+        let reopen = self.reopen.take();
+        if reopen.as_ref().map_or(false, |&( cc, _ )| cc == chr) {
+            self.emit_to(chr, sink);
+            self.quote = reopen;
+            return;
+        }
+
+        // TEXT AFTER QUOTES
+        //   Anything but a separator, terminator or opening quote, straight
+        //   after a closing quote, quotes must end words
+        //   -> Fail
+        if after_close && self.in_word
+           && self.after_quote == SeparatorAfterQuote
+           && !self.is_separator(chr)
+           && !self.statement_terminators.contains(&chr)
+           && !self.quote_map.contains_key(&chr)
+           && !( self.comments_anywhere
+                 && self.comment_chars.contains(&chr) ) {
+            self.error = Some(TrailingGarbageAfterQuote(chr, pos));
+            return;
+        }
+
+        let quote = self.quote.as_ref().map(|&( cc, ref mode )| {
+            ( cc, mode.base().clone() )
+        });
+
+        match ( chr, quote, self.in_word ) {
+            // COMMENTS
+            //   Comment character, where a word could start
+            //   -> Skip the rest of the line
+            ( c, None, false ) if self.comment_chars.contains(&c) =>
+                self.skipping = true,
+            //   Comment character, in an unquoted word, comments anywhere
+            //   -> End word, then skip the rest of the line
+            ( c, None, true )
+                if self.comments_anywhere
+                && self.comment_chars.contains(&c) => {
+                self.in_word = false;
+                self.end_word_to(sink);
+                self.skipping = true;
+            },
+
+            // ESCAPE LEADER
+            //   Custom escape leader, in quotes with a custom scheme
+            //   -> Begin escape with that scheme
+            ( c, Some(( _, Custom(leader, ref scheme) )), _ )
+                if c == leader => {
+                self.escape = Some(scheme.clone());
+                self.escape_at = pos;
+                self.escape_seq.clear();
+                self.had_escape = true;
+            },
+            //   Escape leader, not in quotes
+            //   -> Begin escape (and word if not in one already)
+            ( c, None, _ ) if self.escape_map.contains_key(&c) => {
+                self.note_kind(token::Escaped);
+                self.start_escaping(c, pos);
+            },
+            //   Escape leader, in escape-permitting quotes
+            //   -> Begin escape (and word if not in one already)
+            ( c, Some(( _, ref mode )), _ )
+                if mode.parses_escapes() && self.escape_map.contains_key(&c) =>
+                self.start_escaping(c, pos),
+
+            // QUOTE OPENING
+            //   Quote opening character, not currently in quoted word
+            //   -> Start quoting
+            //   Quote opening character, in a word, quotes must start words
+            //   -> Fail
+            ( c, None, true )
+                if self.quote_map.contains_key(&c)
+                && self.before_quote == SeparatorBeforeQuote => {
+                self.error = Some(QuoteNotAtFieldStart(pos));
+            },
+            //   Quote opening character, in a word, quotes must start words
+            //   but are otherwise literal
+            //   -> Echo
+            ( c, None, true )
+                if self.quote_map.contains_key(&c)
+                && self.before_quote == LiteralQuoteInWord => {
+                self.note_kind(Bare);
+                self.emit_to(c, sink);
+            },
+            //   Quote opening character, otherwise
+            //   -> Start quoting
+            ( c, None, _ ) if self.quote_map.contains_key(&c) => {
+                self.quote = self.quote_map.find(&c).map(|q| q.clone());
+                self.quote_at = pos;
+                let kind = self.quote.as_ref().map(|&( _, ref mode )| {
+                    mode.token_kind()
+                });
+                self.note_kind(kind.unwrap());
+                if self.keeps_delimiters() { self.emit_to(c, sink); }
+                self.folded = false;
+                self.in_word = true;
+            },
+
+            // QUOTE CLOSING
+            //   Quote closing character, in quoted word, quotes ok
+            //   -> Stop quoting
+            ( c, Some(( cc, ref mode )), _ ) if c == cc => {
+                if self.keeps_delimiters() { self.emit_to(c, sink); }
+                let closed = self.quote.take();
+                match *mode {
+                    DoubledEscape => self.reopen = closed,
+                    _             => ()
+                }
+                self.in_word = true;
+                self.just_closed = true;
+            },
+
+            // STATEMENT TERMINATORS
+            //   Unquoted statement terminator
+            //   -> End word, if in one, then end statement
+            ( c, None, in_word ) if self.statement_terminators.contains(&c) => {
+                if in_word {
+                    self.in_word = false;
+                    self.end_word_to(sink);
+                }
+                let completed = self.held;
+                self.statement_ends.push(completed);
+            },
+
+            // UNESCAPED SEPARATORS
+            //   Unescaped separator, keeping empty fields
+            //   -> End word, even if empty
+            ( a, None, _ )
+                if self.preserve_empty_fields && self.is_separator(a) => {
+                self.in_word = false;
+                self.end_word_to(sink);
+            },
+            //   Unescaped separator, while not in a word
+            //   -> Ignore
+            ( a, _, false ) if self.is_separator(a) => (),
+            //   Unescaped separator, while in a non-quoted word
+            //   -> End word
+            ( a, None, true ) if self.is_separator(a) => {
+                self.in_word = false;
+                self.end_word_to(sink);
+            },
+
+            // FOLDED WHITESPACE
+            //   Unescaped whitespace, in folding quotes, after whitespace
+            //   -> Ignore
+            //   Unescaped whitespace, in folding quotes, otherwise
+            //   -> Echo a space
+            ( a, Some(( _, FoldWhitespace )), _ ) if is_whitespace(a) => {
+                if !self.folded {
+                    self.emit_to(' ', sink);
+                    self.folded = true;
+                }
+            },
+
+            // DEFAULT
+            //   Anything else
+            //   -> Echo
+            ( a, None, _ ) => {
+                self.note_kind(Bare);
+                self.emit_to(a, sink);
+            },
+            ( a, _, _ ) => self.emit_to(a, sink)
+        }
+    }
+
+    /// Feeds a string, `string`, into the Tokeniser, in place.
+    ///
+    /// This is the mutating equivalent of `add_string`.
+    ///
+    /// # Return value
+    ///
+    /// The Tokeniser itself, so that calls can be chained.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let mut tok = shell_style_tokeniser();
+    /// tok.push_str("ab 'c").push_str(" d'");
+    /// assert_eq!(tok.into_strings(),
+    ///            Ok(vec![ "ab".into_string(), "c d".into_string() ]));
+    /// ```
+    pub fn push_str(&mut self, string: &str) -> &mut Tokeniser<Q, E, S> {
+        self.feed_iter(string.chars());
+        self
+    }
+
+    /// Feeds a line, `line`, into the Tokeniser, in place.
+    ///
+    /// This is the mutating equivalent of `add_line`.
+    ///
+    /// # Return value
+    ///
+    /// The Tokeniser itself, so that calls can be chained.
+    pub fn push_line(&mut self, line: &str) -> &mut Tokeniser<Q, E, S> {
+        if self.line_fed {
+            self.push_char('\n');
+        }
+        self.line_fed = true;
+        self.push_str(strip_line_ending(line))
+    }
+
+    /// Feeds a single character `chr` to a Tokeniser, in place.
+    ///
+    /// This is equivalent to `push_char`.
+    pub fn feed_char(&mut self, chr: char) {
+        self.push_char(chr)
+    }
+
+    /// Feeds an Iterator of chars, `it`, into the Tokeniser, in place.
+    ///
+    /// This is the mutating equivalent of `add_iter`.
+    pub fn feed_iter<I: Iterator<char>>(&mut self, mut it: I) {
+        for chr in it {
+            self.push_char(chr);
+        }
+    }
+
+    /// Feeds the contents of a Buffer, `reader`, into the Tokeniser, in
+    /// place.
+    ///
+    /// This is the mutating equivalent of `add_reader`.  If the Tokeniser
+    /// fails, `reader` is left positioned after the offending character.
+    pub fn feed_reader<R: Buffer>(&mut self, reader: &mut R,
+                                  policy: BufferPolicy) -> IoResult<()> {
+        while self.error.is_none() {
+            match reader.read_char() {
+                Ok(chr) => {
+                    self.push_char(chr);
+                    match self.enforce_buffer_policy(&policy) {
+                        Err(e) => self.error = Some(e),
+                        Ok(()) => ()
+                    }
+                },
+                Err(IoError { kind: EndOfFile, .. }) => break,
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(())
+    }
+
+    /// Feeds the contents of a Reader, `reader`, into the Tokeniser, in
+    /// place.
+    ///
+    /// This is the mutating equivalent of `add_read`.
+    pub fn feed_read<R: Reader>(&mut self, reader: &mut R,
+                                policy: BufferPolicy) -> IoResult<()> {
+        let mut buffered = BufferedReader::with_capacity(READ_CHUNK_LEN,
+                                                         reader.by_ref());
+        self.feed_reader(&mut buffered, policy)
+    }
+
+    /// Feeds a string, `string`, into the Tokeniser, in place.
+    ///
+    /// This is equivalent to `push_str`, but does not return the Tokeniser.
+    pub fn feed_str(&mut self, string: &str) {
+        self.push_str(string);
+    }
+
+    /// Feeds a line, `line`, into the Tokeniser, in place.
+    ///
+    /// This is equivalent to `push_line`, but does not return the
+    /// Tokeniser.
+    pub fn feed_line(&mut self, line: &str) {
+        self.push_line(line);
+    }
+
+    /// Feeds a line, `line`, into the Tokeniser.
+    ///
+    /// This differs from `add_string` in that a trailing `\n` or `\r\n` is
+    /// removed from the line, and that successive lines are separated by a
+    /// `\n`.  A quotation left open at the end of one line therefore
+    /// continues into the next with a newline, as in a shell.  Any other
+    /// whitespace in the line is kept.
+    ///
+    /// # Return value
+    ///
+    /// A new Tokeniser, representing the state of the Tokeniser after
+    /// consuming `line`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().add_line("a \"b ").add_line(" c\"\n");
+    /// assert_eq!(tok.into_strings(),
+    ///            Ok(vec![ "a".into_string(), "b \n c".into_string() ]));
+    /// ```
+    pub fn add_line(mut self, line: &str) -> Tokeniser<Q, E, S> {
+        self.push_line(line);
+        self
+    }
+
+    /// Feeds a line, `line`, into the Tokeniser, checking that the line
+    /// left the Tokeniser in a state from which it can continue.
+    ///
+    /// Unlike `add_line`, which defers errors to `into_strings`, this fails
+    /// as soon as the line causes an error or ends in the middle of an
+    /// escape sequence.  A quotation left open is not an error, as it may
+    /// be closed by a later line.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing a new Tokeniser representing the state of the
+    /// Tokeniser after consuming `line` if it is valid, and an Error
+    /// otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    /// use russet::tokeniser::UnfinishedEscape;
+    ///
+    /// let tok = shell_style_tokeniser().add_line_strict("echo 'a").unwrap();
+    /// let tok2 = tok.add_line_strict("b' c").unwrap();
+    /// assert_eq!(tok2.add_line_strict("d\\").err(),
+    ///            Some(UnfinishedEscape(14)));
+    /// ```
+    pub fn add_line_strict(mut self, line: &str)
+      -> Result<Tokeniser<Q, E, S>, Error> {
+        self.push_line(line);
+        self.finish_escape();
+
+        match ( self.error.clone(), &self.escape ) {
+            ( Some(e), _ ) => Err(e),
+            ( None, &Some(_) ) => Err(UnfinishedEscape(self.escape_at)),
+            ( None, &None ) => Ok(self)
+        }
+    }
+
+    /// Feeds a line, `line`, into the Tokeniser, checking whether the
+    /// current word carries on into the next line.
+    ///
+    /// This is the case when the line ends inside a quotation or an escape
+    /// sequence, including a line ending with a lone escape leader.  The
+    /// caller should then read another line, for example by showing a
+    /// continuation prompt.
+    ///
+    /// # Return value
+    ///
+    /// A tuple of a new Tokeniser, representing the state of the Tokeniser
+    /// after consuming `line`, and whether another line is needed.  A
+    /// Tokeniser that has failed never needs another line.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let ( tok, more ) =
+    ///     shell_style_tokeniser().add_line_with_continuation_check("a 'b");
+    /// assert!(more);
+    /// let ( tok, more ) = tok.add_line_with_continuation_check("c' d");
+    /// assert!(!more);
+    /// assert_eq!(tok.into_strings(),
+    ///            Ok(vec![ "a".into_string(),
+    ///                     "b\nc".into_string(),
+    ///                     "d".into_string() ]));
+    /// ```
+    pub fn add_line_with_continuation_check(self, line: &str)
+      -> ( Tokeniser<Q, E, S>, bool ) {
+        let tok = self.add_line(line);
+        let more = match tok.state() {
+            InQuote(..) | InEscape => true,
+            _                      => false
+        };
+        ( tok, more )
+    }
+
+    /// Checks whether a line contains a given word.
+    ///
+    /// See `find_word`.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing whether `line` contains `target` as a word if
+    /// no error was found before the first match, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, CaseSensitive };
+    ///
+    /// let tok = shell_style_tokeniser();
+    /// assert_eq!(tok.contains_word("sudo \"rm -rf\" x", "rm", CaseSensitive),
+    ///            Ok(false));
+    /// assert_eq!(tok.contains_word("sudo 'rm' -rf x", "rm", CaseSensitive),
+    ///            Ok(true));
+    /// ```
+    pub fn contains_word(&self, line: &str, target: &str,
+                         case: CaseSensitivity) -> Result<bool, Error> {
+        self.find_word(line, target, case).map(|m| m.is_some())
+    }
+
+    /// Finds the first occurrence of a given word in a line.
+    ///
+    /// The line is tokenised with a fresh Tokeniser that has this
+    /// Tokeniser's configuration, so quotes and escapes are honoured.
+    /// Tokenising stops at the first match, so errors later in the line
+    /// are not reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `line`   - The line to search.
+    /// * `target` - The word to find.
+    /// * `case`   - Whether the match is case sensitive.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the index and byte Span of the first word in
+    /// `line` that matches `target`, if any, when no error was found before
+    /// the first match; and an Error otherwise.
+    pub fn find_word(&self, line: &str, target: &str, case: CaseSensitivity)
+      -> Result<Option<( uint, Span )>, Error> {
+        let mut tok = self.clone().reset();
+        let mut index = 0u;
+
+        for chr in line.chars() {
+            tok.push_char(chr);
+            match tok.error {
+                Some(ref e) => return Err(e.clone()),
+                None        => ()
+            }
+
+            if tok.vec.len() > 1 {
+                tok.held -= 1;
+                let word = tok.vec.remove(0).unwrap();
+                let span = tok.info.remove(0).unwrap().span;
+                if words_match(word.as_slice(), target, case) {
+                    return Ok(Some(( index, span )));
+                }
+                index += 1;
+            }
+        }
+
+        tok.into_spans().map(|v| {
+            v.move_iter().next().and_then(|( word, span )| {
+                if words_match(word.as_slice(), target, case) {
+                    Some(( index, span ))
+                } else {
+                    None
+                }
+            })
+        })
+    }
+
+    /// Checks whether two lines tokenise into the same words, however they
+    /// are quoted.
+    ///
+    /// Each line is read, as by `add_line`, by a fresh copy of the Tokeniser.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing whether the lines have the same words if both
+    /// were valid, and the Error from the first invalid line otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser();
+    /// assert_eq!(tok.lines_equivalent("git commit -m \"fix\"",
+    ///                                 "git commit -m fix"),
+    ///            Ok(true));
+    /// ```
+    pub fn lines_equivalent(&self, a: &str, b: &str) -> Result<bool, Error> {
+        let words_a = try!(self.clone().reset().add_line(a).into_strings());
+        let words_b = try!(self.clone().reset().add_line(b).into_strings());
+        Ok(words_a == words_b)
+    }
+
+    /// Renders a line in a canonical form, suitable as a key for finding
+    /// lines with the same words.
+    ///
+    /// The line is read, as by `add_line`, by a fresh copy of the Tokeniser,
+    /// and its words are rendered as by `into_strings_to_shell_line`.  Two
+    /// lines have the same canonical form exactly when `lines_equivalent`
+    /// holds for them.  The canonical form of a list of words will not
+    /// change between versions of Russet, so canonical forms may be stored.
+    ///
+    /// The canonical form is written in shell syntax, so it is itself in
+    /// canonical form only for tokenisers that read shell quoting.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the canonical form if the line was valid, and
+    /// an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser();
+    /// assert_eq!(tok.canonical_form("echo  \"a b\" 'c'"),
+    ///            Ok("echo a\\ b c".into_string()));
+    /// ```
+    pub fn canonical_form(&self, line: &str) -> Result<String, Error> {
+        self.clone().reset().add_line(line).into_strings_to_shell_line()
+    }
+
+    /// Compares two lines by their words, however they are quoted.
+    ///
+    /// Each line is read, as by `add_line`, by a fresh copy of the
+    /// Tokeniser.  The lines are ordered by their first differing word, and
+    /// a line whose words begin another's comes first.
+    ///
+    /// # Arguments
+    ///
+    /// * `a`     - The first line.
+    /// * `b`     - The second line.
+    /// * `order` - How to order two words.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the ordering of `a` relative to `b` if both
+    /// were valid, and the Error from the first invalid line otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, LexicalOrder, NaturalOrder };
+    ///
+    /// let tok = shell_style_tokeniser();
+    /// assert_eq!(tok.compare_lines("eat 'apple pie'", "eat zebra",
+    ///                              LexicalOrder),
+    ///            Ok(Less));
+    /// assert_eq!(tok.compare_lines("play file10", "play file2",
+    ///                              NaturalOrder),
+    ///            Ok(Greater));
+    /// ```
+    pub fn compare_lines(&self, a: &str, b: &str, order: WordOrder)
+      -> Result<cmp::Ordering, Error> {
+        let words_a = try!(self.clone().reset().add_line(a).into_strings());
+        let words_b = try!(self.clone().reset().add_line(b).into_strings());
+        Ok(compare_words(words_a.as_slice(), words_b.as_slice(), order))
+    }
+
+    /// Sorts lines by their words, as compared by `compare_lines`.
+    ///
+    /// Each line is tokenised only once.  Lines that cannot be tokenised
+    /// are sorted after all of the others, in the order they were given.
+    /// The sort is stable, so lines with the same words also keep their
+    /// order.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - The lines to sort, in place.
+    /// * `order` - How to order two words.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing nothing if every line was valid, and the Error
+    /// from the first invalid line, as given, otherwise.  The lines are
+    /// sorted either way.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, NaturalOrder };
+    ///
+    /// let mut lines = vec![ "play file10".into_string(),
+    ///                       "'play' file2".into_string() ];
+    /// let tok = shell_style_tokeniser();
+    /// assert_eq!(tok.sort_lines(lines.as_mut_slice(), NaturalOrder), Ok(()));
+    /// assert_eq!(lines, vec![ "'play' file2".into_string(),
+    ///                         "play file10".into_string() ]);
+    /// ```
+    pub fn sort_lines(&self, lines: &mut [String], order: WordOrder)
+      -> Result<(), Error> {
+        let mut keyed: Vec<( Result<Vec<String>, Error>, String )> =
+            lines.mut_iter().map(|line| {
+                let words = self.clone().reset()
+                                .add_line(line.as_slice())
+                                .into_strings();
+                ( words, mem::replace(line, String::new()) )
+            }).collect();
+
+        let error = keyed.iter().filter_map(|&( ref words, _ )| {
+            match *words {
+                Err(ref e) => Some(e.clone()),
+                Ok(..)     => None
+            }
+        }).next();
+
+        keyed.sort_by(|&( ref a, _ ), &( ref b, _ )| {
+            match ( a, b ) {
+                ( &Ok(ref a), &Ok(ref b) ) =>
+                    compare_words(a.as_slice(), b.as_slice(), order),
+                ( &Ok(..),    &Err(..)   ) => cmp::Less,
+                ( &Err(..),   &Ok(..)    ) => cmp::Greater,
+                ( &Err(..),   &Err(..)   ) => cmp::Equal
+            }
+        });
+        for ( slot, ( _, line ) ) in lines.mut_iter().zip(keyed.move_iter()) {
+            *slot = line;
+        }
+
+        match error {
+            Some(e) => Err(e),
+            None    => Ok(())
+        }
+    }
+
+    /// Tokenises a line, checking that it follows a Policy.
+    ///
+    /// The check sees how each character was written, so a forbidden
+    /// string is allowed in quotes or escaped, but not bare.  The line
+    /// is read, as by `add_string`, by a fresh copy of the Tokeniser, so
+    /// spans are character offsets into `line`.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the words of `line` if it was valid and followed
+    /// `policy`, and otherwise a PolicyViolation for the first rule broken.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::default::Default;
+    /// use russet::shell_style_tokeniser;
+    /// use russet::policy::{ ForbiddenBare, Policy };
+    ///
+    /// let policy = Policy { forbidden_bare: vec![ ";".into_string(),
+    ///                                             "$(".into_string() ],
+    ///                       ..Default::default() };
+    /// let tok = shell_style_tokeniser();
+    /// assert_eq!(tok.check_policy("echo 'a;b'", &policy),
+    ///            Ok(vec![ "echo".into_string(), "a;b".into_string() ]));
+    /// assert_eq!(tok.check_policy("echo a;b", &policy).unwrap_err().rule,
+    ///            ForbiddenBare(";".into_string()));
+    /// ```
+    pub fn check_policy(&self, line: &str, policy: &Policy)
+      -> Result<Vec<String>, PolicyViolation> {
+        let mut tok = self.clone().reset();
+
+        // The bare characters read since the last one that was not bare.
+        let mut bare_run = String::new();
+
+        for ( i, chr ) in line.chars().enumerate() {
+            let read = match tok.transform {
+                Some(f) => f(chr),
+                None    => chr
+            };
+
+            let bare = tok.escape.is_none()
+                       && tok.quote.is_none()
+                       && !tok.skipping;
+            if bare {
+                bare_run.push_char(read);
+            } else {
+                bare_run.truncate(0);
+            }
+            let forbidden = policy.forbidden_bare.iter().find(|s| {
+                !s.is_empty() && bare_run.as_slice().ends_with(s.as_slice())
+            });
+            match forbidden {
+                Some(s) => return Err(PolicyViolation {
+                    rule: ForbiddenBare(s.clone()),
+                    span: Span { start: i + 1 - s.as_slice().char_len(),
+                                 end: i + 1 }
+                }),
+                None => ()
+            }
+
+            // Work out what the escape sequence in progress, if any, will
+            // stand for once this character is read.
+            let escaped = match tok.escape {
+                Some(ref s) if policy.forbid_control_escapes => {
+                    let mut seq = tok.escape_seq.clone();
+                    seq.push(read);
+                    match s.escape_seq(seq.as_slice()) {
+                        Escaped(c)       => Some(( c, i + 1 )),
+                        EscapedBefore(c) => Some(( c, i )),
+                        _                => None
+                    }
+                },
+                _ => None
+            };
+            match escaped {
+                Some(( c, end )) if c.is_control() => {
+                    return Err(PolicyViolation {
+                        rule: ControlEscape(c),
+                        span: Span { start: tok.escape_at, end: end }
+                    });
+                },
+                _ => ()
+            }
+
+            tok.push_char(chr);
+        }
+
+        // An escape sequence still in progress may be ended by the end of
+        // the line, as it will be when the words are taken.
+        let end = match tok.escape {
+            Some(ref s) if policy.forbid_control_escapes
+                           && tok.error.is_none() =>
+                s.escape_end(tok.escape_seq.as_slice()),
+            _ => None
+        };
+        match end {
+            Some(c) if c.is_control() => {
+                return Err(PolicyViolation {
+                    rule: ControlEscape(c),
+                    span: Span { start: tok.escape_at, end: line.char_len() }
+                });
+            },
+            _ => ()
+        }
+
+        let words = match tok.into_words_with_info() {
+            Ok(words) => words,
+            Err(e) => return Err(PolicyViolation {
+                rule: Malformed(e),
+                span: Span { start: 0, end: line.char_len() }
+            })
+        };
+
+        match policy.max_words {
+            Some(limit) if limit < words.len() => {
+                let &( _, ref info ) = words.get(limit);
+                return Err(PolicyViolation {
+                    rule: TooManyWords(limit),
+                    span: info.char_span.clone()
+                });
+            },
+            _ => ()
+        }
+
+        for &( ref word, ref info ) in words.iter() {
+            match policy.max_word_len {
+                Some(limit) if limit < word.as_slice().char_len() => {
+                    return Err(PolicyViolation {
+                        rule: WordTooLong(limit),
+                        span: info.char_span.clone()
+                    });
+                },
+                _ => ()
+            }
+        }
+
+        Ok(words.move_iter().map(|( w, _ )| w).collect())
+    }
+
+    /// Tokenises `line` on a copy of the Tokeniser, giving up if it is not
+    /// finished by a deadline.
+    ///
+    /// This guards against configurations whose separator functions,
+    /// transformations or escape schemes are slow.  The clock is checked
+    /// before reading the first character, and then about every 50
+    /// microseconds: every character for slow configurations, and every
+    /// few thousand for fast ones.
+    ///
+    /// The Tokeniser is reset before `line` is read, and is not changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `line`     - The line to tokenise.
+    /// * `deadline` - The deadline, as a `time::precise_time_ns()` value.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the words of `line` if it was valid and read
+    /// before the deadline, `DeadlineExceeded` if the deadline passed, and
+    /// another Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate russet;
+    /// extern crate time;
+    ///
+    /// use russet::shell_style_tokeniser;
+    /// use russet::tokeniser::DeadlineExceeded;
+    /// use time::precise_time_ns;
+    ///
+    /// fn main() {
+    ///     let tok = shell_style_tokeniser();
+    ///     let soon = precise_time_ns() + 1000000000;
+    ///     assert_eq!(tok.tokenise_with_deadline("a 'b c'", soon),
+    ///                Ok(vec![ "a".into_string(), "b c".into_string() ]));
+    ///     assert_eq!(tok.tokenise_with_deadline("a 'b c'", 0),
+    ///                Err(DeadlineExceeded(0)));
+    /// }
+    /// ```
+    pub fn tokenise_with_deadline(&self, line: &str, deadline: u64)
+      -> Result<Vec<String>, Error> {
+        self.tokenise_with_clock(line, deadline, precise_time_ns)
+    }
+
+    /// As `tokenise_with_deadline`, but reading the time from `clock`.
+    fn tokenise_with_clock(&self, line: &str, deadline: u64,
+                           clock: || -> u64)
+      -> Result<Vec<String>, Error> {
+        let mut tok = self.clone().reset();
+
+        // How many characters to read between clock checks, adjusted after
+        // each check to keep checks about DEADLINE_CHECK_NS apart.
+        let mut stride = 1u;
+        let mut until_check = 0u;
+        let mut last_check = 0u64;
+
+        for ( read, chr ) in line.chars().enumerate() {
+            if until_check == 0 {
+                let now = clock();
+                if deadline <= now { return Err(DeadlineExceeded(read)); }
+
+                stride = if now - last_check < DEADLINE_CHECK_NS {
+                    cmp::min(stride * 2, MAX_DEADLINE_STRIDE)
+                } else {
+                    cmp::max(stride / 2, 1)
+                };
+                last_check = now;
+                until_check = stride;
+            }
+            until_check -= 1;
+
+            tok.push_char(chr);
+        }
+
+        tok.into_strings()
+    }
+
+    /// Tokenises a line into a WordSink, writing each word into the sink as
+    /// it is read.
+    ///
+    /// The line is read as by `add_line`, on a reset copy of the Tokeniser.
+    /// The Tokeniser keeps no copy of the words, so a sink that stores them
+    /// in preallocated space, such as an arena, reads the line without an
+    /// allocation per word.  If an error is found in the middle of a word,
+    /// that word is begun in the sink but never ended.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line to tokenise.
+    /// * `sink` - The WordSink to give the words to.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing nothing if the line was valid, and the Error
+    /// given to the sink's `fail` method otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let mut words = vec![];
+    /// let tok = shell_style_tokeniser();
+    /// assert_eq!(tok.tokenise_into_sink("a 'b c'", &mut words), Ok(()));
+    /// assert_eq!(words, vec![ "a".into_string(), "b c".into_string() ]);
+    /// ```
+    pub fn tokenise_into_sink<K: WordSink>(&self, line: &str, sink: &mut K)
+      -> Result<(), Error> {
+        self.clone().reset().feed_sink(line, sink)
+    }
+
+    /// Retrieves the Error the Tokeniser has failed with, if any.
+    ///
+    /// Only errors that stop the Tokeniser reading input are reported
+    /// here; an unmatched quote, for example, is not an error until the
+    /// Tokeniser is consumed.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Retrieves the word currently being read by the Tokeniser, if any.
+    ///
+    /// Escape sequences already read are substituted in the word; an
+    /// unfinished escape sequence is not part of it.
+    ///
+    /// # Return value
+    ///
+    /// An Option, which is `Some(w)` when the Tokeniser is in the middle of
+    /// reading word `w`, and `None` otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().add_string("ls ~/My\\ Doc");
+    /// assert_eq!(tok.current_word(), Some("~/My Doc"));
+    /// ```
+    pub fn current_word(&self) -> Option<&str> {
+        if self.in_word {
+            self.vec.last().map(|w| w.as_slice())
+        } else {
+            None
+        }
+    }
+
+    /// Retrieves the words the Tokeniser has completed so far, without
+    /// destroying it.
+    ///
+    /// The word currently being read, if any, is not included; see
+    /// `current_word`.  Words taken by `drain_completed` are not included
+    /// either.
+    ///
+    /// # Return value
+    ///
+    /// A slice of the words completed since the Tokeniser was created,
+    /// reset or last drained.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let mut tok = shell_style_tokeniser();
+    /// tok.push_str("cp 'my fi");
+    /// assert_eq!(tok.words_so_far(), [ "cp".into_string() ].as_slice());
+    /// tok.push_str("le' /tmp");
+    /// assert_eq!(tok.words_so_far().len(), 2);
+    /// ```
+    pub fn words_so_far(&self) -> &[String] {
+        // The last string is always the word in progress.
+        self.vec.slice_to(self.vec.len() - 1)
+    }
+
+    /// Retrieves the character offset at which the word currently being
+    /// read started, if any.
+    ///
+    /// The offset counts from the first character fed to the Tokeniser
+    /// since it was created or reset, as do the Spans of `into_char_spans`.
+    /// Together with `chars_fed`, this gives the span of input read so far
+    /// for the current word.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().add_string("ls 'My Doc");
+    /// assert_eq!(tok.current_token_started_at(), Some(3));
+    /// assert_eq!(tok.chars_fed(), 10);
+    /// ```
+    pub fn current_token_started_at(&self) -> Option<uint> {
+        if self.in_word { Some(self.word_start_char) } else { None }
+    }
+
+    /// Retrieves the number of characters fed to the Tokeniser since it
+    /// was created or reset.
+    pub fn chars_fed(&self) -> uint {
+        self.char_count
+    }
+
+    /// Retrieves the state of the Tokeniser.
+    ///
+    /// This can be used to decide whether more input is needed to finish
+    /// the current word, for example to show a continuation prompt.
+    ///
+    /// # Return value
+    ///
+    /// The State of the Tokeniser.  Escapes take priority over quotes, and
+    /// failure takes priority over both.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, InQuote, IgnoreEscapes };
+    ///
+    /// let tok = shell_style_tokeniser().add_string("echo 'abc");
+    /// assert_eq!(tok.state(), InQuote('\'', IgnoreEscapes));
+    /// ```
+    pub fn state(&self) -> State<S> {
+        match ( &self.error, &self.escape, &self.quote ) {
+            ( &Some(_), _, _ )                => Failed,
+            ( _, &Some(_), _ )                => InEscape,
+            ( _, _, &Some(( c, ref mode )) )  => InQuote(c, mode.clone()),
+            _ if self.in_word                 => InWord,
+            _                                 => Ready
+        }
+    }
+
+    /// Destroys the tokeniser, extracting the string vector.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the tokenised string vector if the Tokeniser
+    /// was in a valid ending state, and an Error otherwise.
+    pub fn into_strings(self) -> Result<Vec<String>, Error> {
+        match self.into_strings_lossy() {
+            ( vec, None    ) => Ok(vec),
+            ( _,   Some(e) ) => Err(e)
+        }
+    }
+
+    /// Destroys the tokeniser, extracting as much of the string vector as
+    /// was successfully read.
+    ///
+    /// Unlike `into_strings`, this never discards the words read so far.
+    /// If the Tokeniser ended inside a quoted string, the partially-read
+    /// word is included.
+    ///
+    /// # Return value
+    ///
+    /// A tuple of the tokenised string vector and, if the Tokeniser was not
+    /// in a valid ending state, the Error `into_strings` would have returned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    /// use russet::tokeniser::UnmatchedQuote;
+    ///
+    /// let tok = shell_style_tokeniser().add_line("echo 'hello wor");
+    /// assert_eq!(tok.into_strings_lossy(),
+    ///            ( vec![ "echo".into_string(), "hello wor".into_string() ],
+    ///              Some(UnmatchedQuote(5)) ));
+    /// ```
+    pub fn into_strings_lossy(mut self) -> ( Vec<String>, Option<Error> ) {
+        self.finish_escape();
+        let error = self.current_error();
+        self.drop_empty_current_string();
+        ( self.vec, error )
+    }
+
+    /// Destroys the tokeniser, extracting the string vector, and calls a
+    /// function on the Error if there is one.
+    ///
+    /// This is as `into_strings`, but lets the caller log or count errors
+    /// without handling them.
+    ///
+    /// # Arguments
+    ///
+    /// * `on_error` - A function called with the Error, if any, before it is
+    ///   returned.
+    ///
+    /// # Return value
+    ///
+    /// The same Result as `into_strings`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    /// use russet::tokeniser::UnmatchedQuote;
+    ///
+    /// let mut errors = 0u;
+    /// let tok = shell_style_tokeniser().add_line("echo 'hello wor");
+    /// assert_eq!(tok.into_strings_inspect_errors(|_| errors += 1),
+    ///            Err(UnmatchedQuote(5)));
+    /// assert_eq!(errors, 1);
+    /// ```
+    pub fn into_strings_inspect_errors(self, on_error: |&Error|)
+      -> Result<Vec<String>, Error> {
+        let result = self.into_strings();
+        match result {
+            Err(ref e) => on_error(e),
+            Ok(..)     => ()
+        }
+        result
+    }
+
+    /// Destroys the tokeniser, extracting the words grouped into statements.
+    ///
+    /// Statements are ended by the characters given to
+    /// `with_statement_terminators`; without any, all of the words form one
+    /// statement.  Unlike splitting the input into lines first, a
+    /// terminator inside quotes does not end a statement.  Empty statements
+    /// are left out.  Words taken by `drain_completed` are not part of any
+    /// statement.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the words of each statement if the Tokeniser
+    /// was in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser()
+    ///     .with_statement_terminators(vec![ '\n', ';' ])
+    ///     .add_string("a b\nc \"d\ne\"");
+    /// assert_eq!(tok.into_statements(),
+    ///            Ok(vec![ vec![ "a".into_string(), "b".into_string() ],
+    ///                     vec![ "c".into_string(), "d\ne".into_string() ] ]));
+    /// ```
+    pub fn into_statements(mut self) -> Result<Vec<Vec<String>>, Error> {
+        let ends = mem::replace(&mut self.statement_ends, vec![]);
+        let words = try!(self.into_strings());
+
+        let mut statements = vec![];
+        let mut current = vec![];
+        let mut bounds = ends.iter().peekable();
+        for ( i, word ) in words.move_iter().enumerate() {
+            while bounds.peek().map_or(false, |&&end| end <= i) {
+                bounds.next();
+                if !current.is_empty() {
+                    statements.push(mem::replace(&mut current, vec![]));
+                }
+            }
+            current.push(word);
+        }
+        if !current.is_empty() {
+            statements.push(current);
+        }
+        Ok(statements)
+    }
+
+    /// Destroys the tokeniser, extracting the string vector along with the
+    /// length of each string in bytes.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing a vector of (string, byte length) pairs if the
+    /// Tokeniser was in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser().add_line("abc déf");
+    /// assert_eq!(tok.into_strings_with_byte_lengths(),
+    ///            Ok(vec![ ( "abc".into_string(), 3 ),
+    ///                     ( "déf".into_string(), 4 ) ]));
+    /// ```
+    pub fn into_strings_with_byte_lengths(self)
+      -> Result<Vec<( String, uint )>, Error> {
+        self.into_strings_annotated(|s| s.len())
+    }
+
+    /// Destroys the tokeniser, extracting the string vector along with the
+    /// length of each string in Unicode characters.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing a vector of (string, character count) pairs if
+    /// the Tokeniser was in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser().add_line("abc déf");
+    /// assert_eq!(tok.into_strings_with_char_lengths(),
+    ///            Ok(vec![ ( "abc".into_string(), 3 ),
+    ///                     ( "déf".into_string(), 3 ) ]));
+    /// ```
+    pub fn into_strings_with_char_lengths(self)
+      -> Result<Vec<( String, uint )>, Error> {
+        self.into_strings_annotated(|s| s.char_len())
+    }
+
+    /// Destroys the tokeniser, extracting the string vector along with a
+    /// value computed from each string.
+    ///
+    /// # Arguments
+    ///
+    /// * `annotate` - A function computing the value for each string.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing a vector of (string, value) pairs if the
+    /// Tokeniser was in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser().add_line("abc -d");
+    /// assert_eq!(tok.into_strings_annotated(|s| s.starts_with("-")),
+    ///            Ok(vec![ ( "abc".into_string(), false ),
+    ///                     ( "-d".into_string(), true ) ]));
+    /// ```
+    pub fn into_strings_annotated<T>(self, annotate: |&str| -> T)
+      -> Result<Vec<( String, T )>, Error> {
+        self.into_strings().map(|v| v.move_iter()
+                                     .map(|s| {
+                                         let x = annotate(s.as_slice());
+                                         ( s, x )
+                                     })
+                                     .collect())
+    }
+
+    /// Destroys the tokeniser, extracting the string vector sorted by a
+    /// key taken from each string.
+    ///
+    /// The key is computed once for each string, and strings with equal
+    /// keys stay in the order they were read.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - A closure giving the key to sort each string by.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the sorted string vector if the Tokeniser was
+    /// in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser().add_line("ccc a bb dd");
+    /// assert_eq!(tok.into_strings_sort_key(|s| s.len()),
+    ///            Ok(vec![ "a".into_string(),
+    ///                     "bb".into_string(),
+    ///                     "dd".into_string(),
+    ///                     "ccc".into_string() ]));
+    /// ```
+    pub fn into_strings_sort_key<K: Ord>(self, key: |&str| -> K)
+      -> Result<Vec<String>, Error> {
+        self.into_strings_annotated(key).map(|mut keyed| {
+            keyed.sort_by(|&( _, ref a ), &( _, ref b )| a.cmp(b));
+            keyed.move_iter().map(|( s, _ )| s).collect()
+        })
+    }
+
+    /// Destroys the tokeniser, extracting the string vector split into
+    /// runs of consecutive strings with equal keys.
+    ///
+    /// The strings are not sorted, so strings with equal keys that are not
+    /// next to each other are in different groups.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - A closure giving the key to group each string by.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the groups, in order, if the Tokeniser was in
+    /// a valid ending state, and an Error otherwise.  There are no empty
+    /// groups.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser().add_line("-a -b x y -c");
+    /// let groups = tok.into_strings_group_consecutive_by(|s| {
+    ///     s.starts_with("-")
+    /// });
+    /// assert_eq!(groups,
+    ///            Ok(vec![ vec![ "-a".into_string(), "-b".into_string() ],
+    ///                     vec![ "x".into_string(), "y".into_string() ],
+    ///                     vec![ "-c".into_string() ] ]));
+    /// ```
+    pub fn into_strings_group_consecutive_by<K: Eq>(self, key: |&str| -> K)
+      -> Result<Vec<Vec<String>>, Error> {
+        self.into_strings_annotated(key).map(|keyed| {
+            let mut groups: Vec<( K, Vec<String> )> = vec![];
+            for ( s, k ) in keyed.move_iter() {
+                // The string starts a new group unless it joins the last.
+                let unplaced = match groups.mut_last() {
+                    Some(&( ref last, ref mut group )) if *last == k => {
+                        group.push(s);
+                        None
+                    },
+                    _ => Some(s)
+                };
+                match unplaced {
+                    Some(s) => groups.push(( k, vec![ s ] )),
+                    None    => ()
+                }
+            }
+            groups.move_iter().map(|( _, g )| g).collect()
+        })
+    }
+
+    /// Destroys the tokeniser, extracting every run of `window` consecutive
+    /// words, such as for finding n-grams.
+    ///
+    /// The runs overlap, each starting one word after the last.  If
+    /// `window` is 0, or there are fewer than `window` words, there are no
+    /// runs.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The number of words in each run.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the runs of words if the Tokeniser was in a
+    /// valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser().add_line("a b c");
+    /// assert_eq!(tok.into_strings_windowed(2),
+    ///            Ok(vec![ vec![ "a".into_string(), "b".into_string() ],
+    ///                     vec![ "b".into_string(), "c".into_string() ] ]));
+    /// ```
+    pub fn into_strings_windowed(self, window: uint)
+      -> Result<Vec<Vec<String>>, Error> {
+        self.into_strings().map(|v| {
+            if window == 0 {
+                vec![]
+            } else {
+                v.as_slice().windows(window).map(|w| w.to_vec()).collect()
+            }
+        })
+    }
+
+    /// Destroys the tokeniser, extracting the string vector split in two
+    /// by a predicate.
+    ///
+    /// Both vectors keep the words in the order they were read.
+    ///
+    /// # Arguments
+    ///
+    /// * `pred` - A closure checking whether a word belongs to the first
+    ///   vector.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the words for which `pred` is true and the
+    /// words for which it is false if the Tokeniser was in a valid ending
+    /// state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().add_line("ls -l 'my dir' -a");
+    /// assert_eq!(tok.into_strings_partition(|w| w.starts_with("-")),
+    ///            Ok(( vec![ "-l".into_string(), "-a".into_string() ],
+    ///                 vec![ "ls".into_string(), "my dir".into_string() ] )));
+    /// ```
+    pub fn into_strings_partition(self, pred: |&str| -> bool)
+      -> Result<( Vec<String>, Vec<String> ), Error> {
+        self.into_strings().map(|v| v.partition(|w| pred(w.as_slice())))
+    }
+
+    /// Destroys the tokeniser, extracting the string vector along with the
+    /// span of input that produced each string.
+    ///
+    /// Spans are byte offsets into everything fed to the Tokeniser since it
+    /// was created or reset, and cover any quotes and escape sequences in
+    /// the word.  Input removed before feeding, such as the line ending
+    /// removed by `add_line`, is not counted.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing each string and its Span if the Tokeniser was
+    /// in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, Span };
+    ///
+    /// let tok = shell_style_tokeniser().add_line("ls 'my file'");
+    /// assert_eq!(tok.into_spans(),
+    ///            Ok(vec![ ( "ls".into_string(), Span { start: 0, end: 2 } ),
+    ///                     ( "my file".into_string(),
+    ///                       Span { start: 3, end: 12 } ) ]));
+    /// ```
+    pub fn into_spans(self) -> Result<Vec<SpannedToken>, Error> {
+        self.into_words_with_info().map(|v| {
+            v.move_iter().map(|( w, info )| ( w, info.span )).collect()
+        })
+    }
+
+    /// Destroys the tokeniser, extracting the string vector along with the
+    /// span of input that produced each string, in characters.
+    ///
+    /// This is as `into_spans`, but the Spans are character offsets rather
+    /// than byte offsets.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing each string and its character Span if the
+    /// Tokeniser was in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, Span };
+    ///
+    /// let tok = shell_style_tokeniser().add_line("é 'my file'");
+    /// assert_eq!(tok.into_char_spans(),
+    ///            Ok(vec![ ( "é".into_string(), Span { start: 0, end: 1 } ),
+    ///                     ( "my file".into_string(),
+    ///                       Span { start: 2, end: 11 } ) ]));
+    /// ```
+    pub fn into_char_spans(self) -> Result<Vec<SpannedToken>, Error> {
+        self.into_words_with_info().map(|v| {
+            v.move_iter().map(|( w, info )| ( w, info.char_span )).collect()
+        })
+    }
+
+    /// Destroys the tokeniser, extracting the words as Tokens.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing a vector of Tokens, recording how each word was
+    /// quoted, if the Tokeniser was in a valid ending state, and an Error
+    /// otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, Token };
+    /// use russet::{ Bare, SingleQuoted, DoubleQuoted, Mixed };
+    ///
+    /// let tok = shell_style_tokeniser().add_line("echo '$a' \"$b\" c\"$d\"");
+    /// let kinds = tok.into_tokens().map(|ts| {
+    ///     ts.move_iter().map(|t| t.kind).collect::<Vec<_>>()
+    /// });
+    /// assert_eq!(kinds, Ok(vec![ Bare, SingleQuoted, DoubleQuoted, Mixed ]));
+    /// ```
+    pub fn into_tokens(self) -> Result<Vec<Token>, Error> {
+        self.into_words_with_info().map(|v| {
+            v.move_iter().map(|( w, info )| {
+                Token { value: w, kind: info.kind, had_escape: info.had_escape }
+            }).collect()
+        })
+    }
+
+    /// Destroys the tokeniser, extracting the string vector if its total
+    /// length is within a limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_bytes` - The maximum total length of the words, in bytes.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the tokenised string vector if the Tokeniser
+    /// was in a valid ending state and the words total at most `max_bytes`
+    /// bytes, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    /// use russet::tokeniser::TotalLengthExceeded;
+    ///
+    /// let tok = whitespace_split_tokeniser().add_line("abc  def");
+    /// assert_eq!(tok.into_strings_max_total_length(5),
+    ///            Err(TotalLengthExceeded(5, 6)));
+    /// ```
+    pub fn into_strings_max_total_length(self, max_bytes: uint)
+      -> Result<Vec<String>, Error> {
+        self.into_strings().and_then(|v| {
+            let total = v.iter().fold(0u, |n, w| n + w.len());
+            if total > max_bytes {
+                Err(TotalLengthExceeded(max_bytes, total))
+            } else {
+                Ok(v)
+            }
+        })
+    }
+
+    /// Destroys the tokeniser, extracting the string vector with repeated
+    /// words shared.
+    ///
+    /// Each distinct word is allocated once, and every occurrence of it
+    /// refers to the same Arc.  This saves memory on input that repeats a
+    /// few keywords many times.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the tokenised string vector if the Tokeniser
+    /// was in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser().add_line("set a set b");
+    /// let words = tok.into_strings_interned().unwrap();
+    /// assert_eq!(words.get(2).as_slice(), "set");
+    /// assert_eq!(&**words.get(0) as *const String,
+    ///            &**words.get(2) as *const String);
+    /// ```
+    pub fn into_strings_interned(self) -> Result<Vec<Arc<String>>, Error> {
+        let words = try!(self.into_strings());
+        let mut interned: HashMap<String, Arc<String>> = HashMap::new();
+
+        Ok(words.move_iter().map(|w| {
+            interned.find_or_insert_with(w, |w| Arc::new(w.clone())).clone()
+        }).collect())
+    }
+
+    /// Destroys the tokeniser, extracting the string vector with each word
+    /// in its own Arc, so that words can be shared between tasks.
+    ///
+    /// Each word is moved into its Arc without being copied.  Unlike
+    /// `into_strings_interned`, repeated words are not shared.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the tokenised string vector if the Tokeniser
+    /// was in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser().add_line("set a");
+    /// let words = tok.into_strings_to_arc().unwrap();
+    /// let word = words.get(1).clone();
+    /// spawn(proc() { assert_eq!(word.as_slice(), "a"); });
+    /// ```
+    pub fn into_strings_to_arc(self) -> Result<Vec<Arc<String>>, Error> {
+        self.into_strings().map(|v| {
+            v.move_iter().map(|w| Arc::new(w)).collect()
+        })
+    }
+
+    /// Destroys the tokeniser, extracting each word as a Path.
+    ///
+    /// This suits path lists, such as `PATH`, read with
+    /// `colon_split_tokeniser`.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing a vector of Paths if the Tokeniser was in a
+    /// valid ending state, and an Error otherwise.  A word holding a NUL
+    /// character cannot be a Path, and gives a NulInPath error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::colon_split_tokeniser;
+    ///
+    /// let tok = colon_split_tokeniser().add_line("/bin:'/opt/my app/bin'");
+    /// assert_eq!(tok.into_strings_to_path_components(),
+    ///            Ok(vec![ Path::new("/bin"), Path::new("/opt/my app/bin") ]));
+    /// ```
+    pub fn into_strings_to_path_components(self) -> Result<Vec<Path>, Error> {
+        let words = try!(self.into_strings());
+        let mut paths = Vec::with_capacity(words.len());
+        for ( i, w ) in words.move_iter().enumerate() {
+            match Path::new_opt(w) {
+                Some(path) => paths.push(path),
+                None       => return Err(NulInPath(i))
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Destroys the tokeniser, extracting the string vector with non-ASCII
+    /// whitespace replaced by spaces.
+    ///
+    /// Unquoted, unescaped whitespace already separates words, so this only
+    /// affects whitespace that was quoted or escaped, such as the
+    /// non-breaking spaces in text pasted from a word processor.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the tokenised string vector with each
+    /// non-ASCII whitespace character replaced by a space if the Tokeniser
+    /// was in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().add_line("'a\u00A0b' c\\\u2003d");
+    /// assert_eq!(tok.into_strings_with_whitespace_normalised(),
+    ///            Ok(vec![ "a b".into_string(), "c d".into_string() ]));
+    /// ```
+    pub fn into_strings_with_whitespace_normalised(self)
+      -> Result<Vec<String>, Error> {
+        self.into_strings().map(|v| {
+            v.iter().map(|w| normalise_whitespace(w.as_slice())).collect()
+        })
+    }
+
+    /// Destroys the tokeniser, extracting the string vector encoded as
+    /// UTF-16.
+    ///
+    /// The code units are plain `u16` values; byte order only matters once
+    /// they are written out as bytes.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the UTF-16 encoding of each string if the
+    /// Tokeniser was in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::whitespace_split_tokeniser;
+    ///
+    /// let tok = whitespace_split_tokeniser().add_line("a \U0001F600");
+    /// assert_eq!(tok.into_strings_utf16(),
+    ///            Ok(vec![ vec![ 0x61 ], vec![ 0xD83D, 0xDE00 ] ]));
+    /// ```
+    pub fn into_strings_utf16(self) -> Result<Vec<Vec<u16>>, Error> {
+        self.into_strings().map(|v| {
+            v.iter().map(|w| w.as_slice().utf16_units().collect()).collect()
+        })
+    }
+
+    /// Destroys the tokeniser, rendering the string vector as a JSON array.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing a JSON array of JSON strings if the Tokeniser
+    /// was in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().add_line("echo 'say \"hi\"'");
+    /// assert_eq!(tok.into_strings_to_json_array(),
+    ///            Ok("[\"echo\", \"say \\\"hi\\\"\"]".into_string()));
+    /// ```
+    pub fn into_strings_to_json_array(self) -> Result<String, Error> {
+        self.into_strings().map(|v| {
+            let words: Vec<String> =
+                v.iter().map(|w| json_quote(w.as_slice())).collect();
+            format!("[{}]", words.connect(", "))
+        })
+    }
+
+    /// Destroys the tokeniser, rendering the string vector as a line of
+    /// POSIX shell words.
+    ///
+    /// Each word is quoted with `shell_quoter`, which picks the shortest mix
+    /// of single quotes, double quotes and backslash escapes.  The result
+    /// reads back into the original words with the shell-style tokeniser.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the shell line if the Tokeniser was in a valid
+    /// ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::c_style_tokeniser;
+    ///
+    /// let tok = c_style_tokeniser().add_line("echo \"it's here\" -n");
+    /// assert_eq!(tok.into_strings_to_shell_line(),
+    ///            Ok("echo it\\'s\\ here -n".into_string()));
+    /// ```
+    pub fn into_strings_to_shell_line(self) -> Result<String, Error> {
+        self.into_strings().map(|v| {
+            // The shell quoter can escape any character, so cannot fail.
+            let quoter = shell_quoter();
+            let words: Vec<String> =
+                v.iter().map(|w| quoter.quote(w.as_slice()).unwrap()).collect();
+            words.connect(" ")
+        })
+    }
+
+    /// Destroys the tokeniser, rendering the string vector as NUL-delimited
+    /// bytes.
+    ///
+    /// Each word is followed by a NUL byte, including the last, which is
+    /// the format read by `xargs -0` and written by `find -print0`.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the delimited bytes if the Tokeniser was in a
+    /// valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().add_line("ls 'my file'");
+    /// assert_eq!(tok.into_strings_to_null_delimited(),
+    ///            Ok(b"ls\0my file\0".to_vec()));
+    /// ```
+    pub fn into_strings_to_null_delimited(self) -> Result<Vec<u8>, Error> {
+        self.into_strings().map(|v| {
+            let mut bytes = Vec::new();
+            for w in v.iter() {
+                bytes.push_all(w.as_bytes());
+                bytes.push(0u8);
+            }
+            bytes
+        })
+    }
+
+    /// Destroys the tokeniser, folding its string vector into a single
+    /// value.
+    ///
+    /// # Arguments
+    ///
+    /// * `init` - The initial value.
+    /// * `f`    - A function combining the value so far with the next word.
+    ///
+    /// # Return value
+    ///
+    /// A Result, containing the result of folding `f` over the words if the
+    /// Tokeniser was in a valid ending state, and an Error otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    ///
+    /// let tok = shell_style_tokeniser().add_line("ab 'c d' e");
+    /// assert_eq!(tok.fold_words(0u, |n, w| n + w.len()), Ok(6));
+    /// ```
+    pub fn fold_words<A>(self, init: A, f: |A, String| -> A)
+      -> Result<A, Error> {
+        match self.into_strings() {
+            Ok(v)  => Ok(v.move_iter().fold(init, f)),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Destroys the tokeniser, producing an iterator over its words.
+    ///
+    /// # Return value
+    ///
+    /// A TokeniserIter, yielding each completed word in turn.  If the
+    /// Tokeniser was not in a valid ending state, the last item yielded is
+    /// the Error `into_strings` would have returned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::shell_style_tokeniser;
+    /// use russet::tokeniser::UnmatchedQuote;
+    ///
+    /// let mut it = shell_style_tokeniser().add_line("ab 'cd").into_iter();
+    /// assert_eq!(it.next(), Some(Ok("ab".into_string())));
+    /// assert_eq!(it.next(), Some(Err(UnmatchedQuote(3))));
+    /// assert_eq!(it.next(), None);
+    /// ```
+    pub fn into_iter(mut self) -> TokeniserIter {
+        self.finish_escape();
+        let error = self.current_error();
+
+        if error.is_some() {
+            // The word being read when the Tokeniser failed is incomplete.
+            self.vec.pop();
+        } else {
+            self.drop_empty_current_string();
+        }
+
+        TokeniserIter { words: self.vec.move_iter(), error: error }
+    }
+
+    /// Destroys the tokeniser, producing an iterator over its words that
+    /// fails if the Tokeniser was not in a valid ending state.
+    ///
+    /// This is intended for input that is already known to be valid.
+    ///
+    /// # Return value
+    ///
+    /// A Words iterator, yielding each completed word in turn.  Iterating
+    /// past the last word fails the task if the Tokeniser is in error.
+    ///
+    /// Unlike `tokens`, this takes no BufferPolicy: every word has already
+    /// been read by the time the iterator is made, so there is nothing left
+    /// to bound.  Bound the input as it is fed instead, for example with
+    /// `add_reader`.
+    pub fn words(self) -> Words {
+        Words { inner: self.into_iter() }
+    }
+
+    /// Lazily tokenises an Iterator of chars, `it`.
+    ///
+    /// Words are read from `it` only as they are needed, so the Tokeniser
+    /// holds no more than the word currently being read.
+    ///
+    /// # Arguments
+    ///
+    /// * `it`     - The Iterator of chars to tokenise.
+    /// * `policy` - How long the word being read may grow.
+    ///
+    /// # Return value
+    ///
+    /// A Tokens iterator, yielding each word as soon as it is complete.  A
+    /// word outgrowing `policy` is yielded as a `LimitExceeded` Error, after
+    /// which reading resumes on the next line.  If the Tokeniser fails, the
+    /// last item yielded is the Error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use russet::{ shell_style_tokeniser, Unbounded };
+    ///
+    /// let tok = shell_style_tokeniser();
+    /// let mut tokens = tok.tokens("ab 'c d' e".chars(), Unbounded);
+    /// assert_eq!(tokens.next(), Some(Ok("ab".into_string())));
+    /// assert_eq!(tokens.next(), Some(Ok("c d".into_string())));
+    /// assert_eq!(tokens.next(), Some(Ok("e".into_string())));
+    /// assert_eq!(tokens.next(), None);
+    /// ```
+    pub fn tokens<I: Iterator<char>>(self, it: I, policy: BufferPolicy)
+      -> Tokens<I, Q, E, S> {
+        Tokens { tokeniser: self, iter: it, policy: policy, finished: false }
+    }
+
+    /// Destroys the tokeniser, extracting the words along with what is known
+    /// about each.
+    fn into_words_with_info(mut self)
+      -> Result<Vec<( String, WordInfo )>, Error> {
+        self.finish_escape();
+        match self.current_error() {
+            Some(e) => return Err(e),
+            None    => ()
+        }
+
+        self.drop_empty_current_string();
+        if self.info.len() < self.vec.len() {
+            let info = self.current_info(self.byte_count, self.char_count);
+            self.info.push(info);
+        }
+
+        Ok(self.vec.move_iter().zip(self.info.move_iter()).collect())
+    }
+
+    /// Collects what is known about the current word, if it were to end at
+    /// the given byte and character offsets.
+    fn current_info(&self, byte_end: uint, char_end: uint) -> WordInfo {
+        WordInfo {
+            span: Span { start: self.word_start, end: byte_end },
+            char_span: Span { start: self.word_start_char, end: char_end },
+            kind: self.kind.unwrap_or(Bare),
+            had_escape: self.had_escape
+        }
+    }
+
+    /// Finishes any escape sequence that can be ended by the end of input.
+    fn finish_escape(&mut self) {
+        let mut buffer = WordBuffer { words: mem::replace(&mut self.vec,
+                                                          vec![]) };
+        self.finish_escape_to(&mut buffer);
+        self.vec = buffer.words;
+    }
+
+    /// Finishes any escape sequence that can be ended by the end of input,
+    /// writing the escaped character to `sink`.
+    fn finish_escape_to<K: WordSink>(&mut self, sink: &mut K) {
+        if self.error.is_some() { return; }
+
+        let end = self.escape.as_ref().and_then(|s| {
+            s.escape_end(self.escape_seq.as_slice())
+        });
+        match end {
+            Some(c) => self.emit_to(c, sink),
+            None    => ()
+        }
+    }
+
+    /// Retrieves the Error the Tokeniser would fail with if it were
+    /// consumed now, if any.
+    fn current_error(&self) -> Option<Error> {
+        if self.error.is_some() {
+            self.error.clone()
+        } else if self.in_word && self.quote.is_some() {
+            Some(UnmatchedQuote(self.quote_at))
+        } else if self.escape.is_some() {
+            Some(UnfinishedEscape(self.escape_at))
+        } else {
+            None
+        }
+    }
+
+    /// Updates the character and line length counts for `c`, failing the
+    /// Tokeniser if the line length limit has been exceeded.
+    fn count_char(&mut self, c: char) {
+        if self.error.is_some() { return; }
+
+        if c == '\n' {
+            self.line_len = 0;
+        } else {
+            self.line_len += match self.line_unit {
+                Chars => 1,
+                Bytes => c.len_utf8_bytes()
+            };
+
+            match self.max_line_len {
+                Some(limit) if self.line_len > limit => {
+                    self.error = Some(LineTooLong(limit, self.char_count));
+                },
+                _ => ()
+            }
+        }
+
+        self.char_count += 1;
+    }
+
+    /// Adds a character into a Tokeniser's current string, in `sink`.
+    /// This automatically sets the Tokeniser's state to be in a word,
+    /// and clears any escape sequence flag.
+    fn emit_to<K: WordSink>(&mut self, c: char, sink: &mut K) {
+        self.in_word = true;
+        self.folded = false;
+        self.escape = None;
+        if !self.begun {
+            sink.begin_word();
+            self.begun = true;
+        }
+        sink.push_char(c);
+    }
+
+    /// Ends the current word in `sink`, beginning it first if nothing has
+    /// been written to it.
+    fn end_word_to<K: WordSink>(&mut self, sink: &mut K) {
+        if !self.begun { sink.begin_word(); }
+        sink.end_word();
+        self.begun = false;
+        self.held += 1;
+    }
+
+    /// Switches on escape mode, for the leader `c` at offset `pos`.
+    /// This automatically sets the Tokeniser to be in a word, if it isn't
+    /// already.
+    fn start_escaping(&mut self, c: char, pos: uint) {
+        self.escape = self.escape_map.find(&c).map(|a| a.clone());
+        self.escape_at = pos;
+        self.escape_began_word = !self.in_word;
+        self.escape_seq.clear();
+        self.had_escape = true;
+        self.in_word = true;
+    }
+
+    /// Checks whether the Tokeniser is reading the last word permitted by
+    /// its word limit, if any.
+    ///
+    /// This can only become true as a word ends, so the last word never
+    /// starts inside a quote or escape sequence.
+    fn at_word_limit(&self) -> bool {
+        match self.max_words {
+            Some(n) => self.word_count + 1 >= n,
+            None    => false
+        }
+    }
+
+    /// Notes that the current word has a part of the given kind.
+    fn note_kind(&mut self, kind: TokenKind) {
+        self.kind = Some(match self.kind {
+            Some(k) => k.combine(kind),
+            None    => kind
+        });
+    }
+
+    /// Checks whether the current quotation keeps its quote characters.
+    fn keeps_delimiters(&self) -> bool {
+        match self.quote {
+            Some(( _, ref mode )) =>
+                self.keep_delimiters || mode.keeps_delimiters(),
+            None => false
+        }
+    }
+
+    /// Checks whether `c` separates words.
+    fn is_separator(&self, c: char) -> bool {
+        match self.separators {
+            Whitespace             => is_whitespace(c),
+            SeparatorChars(ref cs) => cs.contains(&c),
+            SeparatorFn(f)         => f(c)
+        }
+    }
+
+    /// Checks whether the current word is a word even if it is empty.
+    ///
+    /// An empty word is kept if it is written as empty quotes, such as
+    /// `""`, or, when keeping empty fields, if it follows a separator.
+    fn keeps_empty_word(&self) -> bool {
+        let quoted = self.in_word && self.kind.map_or(false, |k| k.is_quoted());
+        quoted || ( self.preserve_empty_fields && 0 < self.word_count )
+    }
+
+    /// Drops the current working string, if it is empty and not a word.
+    fn drop_empty_current_string(&mut self) {
+        if self.keeps_empty_word() { return; }
+        if self.vec.last().map(|s| s.is_empty()).unwrap_or(false) {
+            self.vec.pop();
+        }
+    }
+
+    /// Feeds `line` into the Tokeniser, as by `add_line`, writing each
+    /// word to `sink` rather than holding it.
+    fn feed_sink<K: WordSink>(&mut self, line: &str, sink: &mut K)
+      -> Result<(), Error> {
+        for chr in strip_line_ending(line).chars() {
+            self.push_char_to(chr, sink);
+
+            // The words are in the sink, so what is known about them need
+            // not be kept.  Truncating keeps the buffers for the next word.
+            self.info.truncate(0);
+            self.statement_ends.truncate(0);
+            if self.error.is_some() { break; }
+        }
+        self.finish_escape_to(sink);
+
+        match self.current_error() {
+            Some(e) => {
+                sink.fail(&e);
+                Err(e)
+            },
+            None => {
+                if self.begun || self.keeps_empty_word() {
+                    self.end_word_to(sink);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+
+/// A Tokeniser's own store of words, as a WordSink.
+///
+/// The last word is always the one being read, so ending a word starts the
+/// next.
+struct WordBuffer {
+    /// The words, ending with the word being read.
+    words: Vec<String>
+}
+
+impl WordSink for WordBuffer {
+    fn begin_word(&mut self) {}
+
+    fn push_char(&mut self, c: char) {
+        self.words.mut_last().mutate(|s| { s.push_char(c); s });
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.words.mut_last().mutate(|w| { w.push_str(s); w });
+    }
+
+    fn end_word(&mut self) {
+        self.words.push(String::new());
+    }
+
+    fn fail(&mut self, _: &Error) {}
+}
+
+
+/// Checks whether `word` matches `target` with the given case sensitivity.
+fn words_match(word: &str, target: &str, case: CaseSensitivity) -> bool {
+    match case {
+        CaseSensitive   => word == target,
+        CaseInsensitive =>
+            iter::order::equals(word.chars().map(|c| c.to_lowercase()),
+                                target.chars().map(|c| c.to_lowercase()))
+    }
+}
+
+
+/// Compares two lists of words, ordering each pair of words as given.
+fn compare_words(a: &[String], b: &[String], order: WordOrder)
+  -> cmp::Ordering {
+    match order {
+        LexicalOrder => iter::order::cmp(a.iter(), b.iter()),
+        NaturalOrder => {
+            for ( x, y ) in a.iter().zip(b.iter()) {
+                match natural_cmp(x.as_slice(), y.as_slice()) {
+                    cmp::Equal => (),
+                    other      => return other
+                }
+            }
+            a.len().cmp(&b.len())
+        }
+    }
+}
+
+/// Compares two words, ordering runs of ASCII digits by their value.
+///
+/// Runs with the same value, such as `7` and `007`, are ordered by their
+/// length, so that no two different words compare equal.
+fn natural_cmp(a: &str, b: &str) -> cmp::Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        let ( x, y ) = match ( a.peek(), b.peek() ) {
+            ( None,     None     ) => return cmp::Equal,
+            ( None,     Some(..) ) => return cmp::Less,
+            ( Some(..), None     ) => return cmp::Greater,
+            ( Some(&x), Some(&y) ) => ( x, y )
+        };
+
+        let order = if x.is_digit_radix(10) && y.is_digit_radix(10) {
+            let ( run_a, run_b ) = ( digit_run(&mut a), digit_run(&mut b) );
+            let value_a = run_a.as_slice().trim_left_chars('0');
+            let value_b = run_b.as_slice().trim_left_chars('0');
+            match value_a.len().cmp(&value_b.len()) {
+                cmp::Equal => match value_a.cmp(&value_b) {
+                    cmp::Equal => run_a.len().cmp(&run_b.len()),
+                    other      => other
+                },
+                other => other
+            }
+        } else {
+            a.next();
+            b.next();
+            x.cmp(&y)
+        };
+        if order != cmp::Equal { return order; }
+    }
+}
+
+/// Takes the run of ASCII digits at the front of `chars`.
+fn digit_run<I: Iterator<char>>(chars: &mut iter::Peekable<char, I>)
+  -> String {
+    let mut run = String::new();
+    loop {
+        match chars.peek() {
+            Some(&c) if c.is_digit_radix(10) => run.push_char(c),
+            _                                => break
+        }
+        chars.next();
+    }
+    run
+}
+
+/// Replaces each non-ASCII whitespace character in `word` with a space.
+fn normalise_whitespace(word: &str) -> String {
+    word.chars().map(|c| {
+        if c > '\x7F' && is_whitespace(c) { ' ' } else { c }
+    }).collect()
+}
+
+
+/// Removes a trailing `\n`, `\r\n` or `\r` from `line`.
+fn strip_line_ending(line: &str) -> &str {
+    let line = if line.ends_with("\n") {
+        line.slice_to(line.len() - 1)
+    } else {
+        line
+    };
+    if line.ends_with("\r") { line.slice_to(line.len() - 1) } else { line }
+}
+
+/// Renders `word` as a JSON string literal.
+fn json_quote(word: &str) -> String {
+    let mut quoted = String::from_str("\"");
+    for c in word.chars() {
+        match c {
+            '"'    => quoted.push_str("\\\""),
+            '\\'   => quoted.push_str("\\\\"),
+            '\n'   => quoted.push_str("\\n"),
+            '\r'   => quoted.push_str("\\r"),
+            '\t'   => quoted.push_str("\\t"),
+            '\x08' => quoted.push_str("\\b"),
+            '\x0c' => quoted.push_str("\\f"),
+            c if (c as u32) < 0x20 =>
+                quoted.push_str(format!("\\u{:04x}", c as u32).as_slice()),
+            c => quoted.push_char(c)
+        }
+    }
+    quoted.push_char('"');
+    quoted
+}
+
+
+impl<Q, E, S> Default for Tokeniser<Q, E, S>
+    where Q: Map<char, ( char, QuoteMode<S> )>,
+          E: Map<char, S>,
+          S: EscapeScheme,
+          Q: Clone,
+          E: Clone,
+          S: Clone,
+          Q: Collection,
+          Q: Default,
+          E: Default {
+    /// Creates a new, blank Tokeniser with the default quote and escape
+    /// maps.
+    ///
+    /// For the stock map types, these are empty, so the Tokeniser splits
+    /// on whitespace alone.
+    fn default() -> Tokeniser<Q, E, S> {
+        Tokeniser::new(Default::default(), Default::default())
+    }
+}
+
+
+impl<Q, E, S> FromIterator<char> for Tokeniser<Q, E, S>
+    where Q: Map<char, ( char, QuoteMode<S> )>,
+          E: Map<char, S>,
+          S: EscapeScheme,
+          Q: Clone,
+          E: Clone,
+          S: Clone,
+          Q: Collection,
+          Q: Default,
+          E: Default {
+    /// Creates a Tokeniser with the default quote and escape maps, and
+    /// feeds `iterator` into it.
+    fn from_iter<I: Iterator<char>>(iterator: I) -> Tokeniser<Q, E, S> {
+        let tok: Tokeniser<Q, E, S> = Default::default();
+        tok.add_iter(iterator)
+    }
+}
+
+
+impl<Q, E, S> Extendable<char> for Tokeniser<Q, E, S>
+    where Q: Map<char, ( char, QuoteMode<S> )>,
+          E: Map<char, S>,
+          S: EscapeScheme,
+          Q: Clone,
+          E: Clone,
+          S: Clone,
+          Q: Collection {
+    /// Feeds the characters of `iterator` into the Tokeniser, in place.
+    fn extend<I: Iterator<char>>(&mut self, iterator: I) {
+        self.feed_iter(iterator)
+    }
+}
+
+
+impl<'a, Q, E, S> Extendable<&'a str> for Tokeniser<Q, E, S>
+    where Q: Map<char, ( char, QuoteMode<S> )>,
+          E: Map<char, S>,
+          S: EscapeScheme,
+          Q: Clone,
+          E: Clone,
+          S: Clone,
+          Q: Collection {
+    /// Feeds each string of `iterator` into the Tokeniser as a line, in
+    /// place.
+    fn extend<I: Iterator<&'a str>>(&mut self, mut iterator: I) {
+        for line in iterator {
+            self.push_line(line);
+        }
+    }
+}
+
+
+impl<Q, E, S> fmt::FormatWriter for Tokeniser<Q, E, S>
+    where Q: Map<char, ( char, QuoteMode<S> )>,
+          E: Map<char, S>,
+          S: EscapeScheme,
+          Q: Clone,
+          E: Clone,
+          S: Clone,
+          Q: Collection {
+    /// Feeds formatted output into the Tokeniser, allowing the use of
+    /// `write!`.
+    ///
+    /// Tokeniser errors are still deferred to `into_strings`; writing only
+    /// fails if `bytes` is not valid UTF-8.
+    fn write(&mut self, bytes: &[u8]) -> fmt::Result {
+        match str::from_utf8(bytes) {
+            Some(string) => { self.push_str(string); Ok(()) },
+            None         => Err(fmt::WriteError)
+        }
+    }
+}
+
+
+/// An iterator over the words of a consumed Tokeniser.
+///
+/// This is created by the `into_iter` method on Tokeniser.
+pub struct TokeniserIter {
+    /// The completed words.
+    words: MoveItems<String>,
+
+    /// The error to yield after the words, if any.
+    error: Option<Error>
+}
+
+impl Iterator<Result<String, Error>> for TokeniserIter {
+    fn next(&mut self) -> Option<Result<String, Error>> {
+        match self.words.next() {
+            Some(w) => Some(Ok(w)),
+            None    => self.error.take().map(|e| Err(e))
+        }
+    }
+}
+
+
+/// An iterator over the words of a consumed Tokeniser, which fails the task
+/// on error.
+///
+/// This is created by the `words` method on Tokeniser.
+pub struct Words {
+    /// The underlying word iterator.
+    inner: TokeniserIter
+}
+
+impl Iterator<String> for Words {
+    fn next(&mut self) -> Option<String> {
+        match self.inner.next() {
+            Some(Ok(w))  => Some(w),
+            Some(Err(e)) => fail!("tokeniser failed: {}", e),
+            None         => None
+        }
+    }
+}
+
+
+/// A lazy iterator over the words of a character iterator.
+///
+/// This is created by the `tokens` method on Tokeniser.
+pub struct Tokens<I, Q, E, S> {
+    /// The Tokeniser reading the words.
+    tokeniser: Tokeniser<Q, E, S>,
+
+    /// The iterator of characters being tokenised.
+    iter: I,
+
+    /// How long the word being read may grow.
+    policy: BufferPolicy,
+
+    /// Whether the iterator has yielded its last item.
+    finished: bool
+}
+
+impl<I, Q, E, S> Tokens<I, Q, E, S>
+    where Q: Map<char, ( char, QuoteMode<S> )>,
+          E: Map<char, S>,
+          S: EscapeScheme,
+          Q: Clone,
+          E: Clone,
+          S: Clone,
+          Q: Collection {
+    /// Retrieves the number of bytes allocated for the word currently being
+    /// read.
+    pub fn buffer_capacity(&self) -> uint {
+        self.tokeniser.buffer_capacity()
+    }
+}
+
+impl<I, Q, E, S> Iterator<Result<String, Error>> for Tokens<I, Q, E, S>
+    where I: Iterator<char>,
+          Q: Map<char, ( char, QuoteMode<S> )>,
+          E: Map<char, S>,
+          S: EscapeScheme,
+          Q: Clone,
+          E: Clone,
+          S: Clone,
+          Q: Collection {
+    fn next(&mut self) -> Option<Result<String, Error>> {
+        loop {
+            if self.finished { return None; }
+
+            // The last string in the Tokeniser is the word in progress, so
+            // any before it are complete.
+            if self.tokeniser.vec.len() > 1 {
+                self.tokeniser.held -= 1;
+                self.tokeniser.info.remove(0);
+                return self.tokeniser.vec.remove(0).map(|w| Ok(w));
+            }
+
+            match self.tokeniser.error.clone() {
+                Some(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                },
+                None => ()
+            }
+
+            match self.iter.next() {
+                Some(chr) => {
+                    self.tokeniser.push_char(chr);
+                    let policy = &self.policy;
+                    match self.tokeniser.enforce_buffer_policy(policy) {
+                        Err(e) => return Some(Err(e)),
+                        Ok(()) => ()
+                    }
+                },
+                None => {
+                    self.finished = true;
+                    self.tokeniser.finish_escape();
+                    return match self.tokeniser.current_error() {
+                        Some(e) => Some(Err(e)),
+                        None    => {
+                            self.tokeniser.drop_empty_current_string();
+                            self.tokeniser.vec.pop().map(|w| Ok(w))
+                        }
+                    };
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::hashmap::HashMap;
+    use std::default::Default;
+    use std::error::Error;
+    use std::fmt::FormatWriter;
+    use std::io::{ BufferedReader, File, MemReader, TempDir };
+    use std::fmt;
+    use std::iter::Repeat;
+    use std::os;
+
+    use quickcheck::{ Arbitrary, Gen, QuickCheck, Shrinker };
+    use test::Bencher;
+    use time::precise_time_ns;
+
+    use builders::{
+        c_style_tokeniser,
+        colon_split_tokeniser,
+        csv_tokeniser,
+        shell_join,
+        shell_quote,
+        shell_style_tokeniser,
+        shell_style_tokeniser_with_comments,
+        tab_split_tokeniser,
+        whitespace_split_tokeniser
+    };
+    use builders::types::{ StockEscapeMap, StockEscapeScheme, StockQuoteMap };
+    use builders::types::StockTokeniser;
+    use chunked::ChunkedTokeniser;
+    use escape_scheme::{ MapEscape, c_escapes, posix_escapes };
+    use super::{ Tokeniser, IgnoreEscapes, ParseEscapes, FoldWhitespace };
+    use super::{ Custom, DoubledEscape, KeepDelimiters, QuoteMode };
+    use super::{ UnmatchedQuote, UnfinishedEscape, BadEscape, LineTooLong };
+    use super::{ InvalidUtf8, TotalLengthExceeded, LimitExceeded, NulInPath };
+    use super::DeadlineExceeded;
+    use super::{ Bounded, Unbounded };
+    use super::{ Bytes, Chars, Span };
+    use super::{ CaseSensitive, CaseInsensitive };
+    use super::{ LexicalOrder, NaturalOrder };
+    use super::SeparatorChars;
+    use super::{ SeparatorBeforeQuote, QuoteNotAtFieldStart };
+    use super::LiteralQuoteInWord;
+    use super::{ SeparatorAfterQuote, TrailingGarbageAfterQuote };
+    use policy::{ Policy, ForbiddenBare, ControlEscape, Malformed };
+    use policy::{ TooManyWords, WordTooLong };
+    use token::{ Token, TokenKind };
+    use token::{ Bare, SingleQuoted, DoubleQuoted, Escaped, Mixed };
+
+    #[test]
+    fn error_show() {
+        assert_eq!(format!("{}", UnmatchedQuote(3)),
+                   "unmatched opening quote at character 3".into_string());
+        assert_eq!(format!("{}", UnfinishedEscape(10)),
+                   "incomplete escape sequence at end of input at character 10"
+                   .into_string());
+        assert_eq!(format!("{}", BadEscape),
+                   "unknown escape sequence".into_string());
+    }
+
+    #[test]
+    fn error_has_no_cause() {
+        assert!(UnmatchedQuote(0).cause().is_none());
+        assert!(UnfinishedEscape(0).cause().is_none());
+        assert!(BadEscape.cause().is_none());
+    }
+
+    #[test]
+    fn invalid_utf8_show() {
+        assert_eq!(format!("{}", InvalidUtf8(5)),
+                   "invalid UTF-8 at byte 5".into_string());
+    }
+
+    #[test]
+    fn total_length_exceeded_show() {
+        assert_eq!(format!("{}", TotalLengthExceeded(4, 7)),
+                   "words too long in total (limit 4, total 7)".into_string());
+    }
+
+    #[test]
+    fn limit_exceeded_show() {
+        assert_eq!(format!("{}", LimitExceeded(64)),
+                   "word too long for buffer (limit 64)".into_string());
+    }
+
+    #[test]
+    fn max_line_len_at_limit() {
+        let tok = whitespace_split_tokeniser().with_max_line_len(Some(7),
+                                                                  Chars);
+        assert_eq!(tok.add_line("abc def").into_strings(),
+                   Ok(vec![ "abc".into_string(), "def".into_string() ]));
+    }
+
+    #[test]
+    fn max_line_len_over_limit() {
+        let tok = whitespace_split_tokeniser().with_max_line_len(Some(7),
+                                                                  Chars);
+        assert_eq!(tok.add_line("abc defg").into_strings(),
+                   Err(LineTooLong(7, 7)));
+    }
+
+    #[test]
+    fn max_line_len_fails_fast() {
+        // The unmatched quote after the overlong line is never seen.
+        let tok = shell_style_tokeniser().with_max_line_len(Some(3), Chars);
+        assert_eq!(tok.add_string("abcd \"efg").into_strings(),
+                   Err(LineTooLong(3, 3)));
+    }
+
+    #[test]
+    fn max_line_len_resets_on_newline() {
+        let tok = whitespace_split_tokeniser().with_max_line_len(Some(3),
+                                                                  Chars);
+        assert_eq!(tok.add_string("abc\ndef").into_strings(),
+                   Ok(vec![ "abc".into_string(), "def".into_string() ]));
+    }
+
+    #[test]
+    fn max_line_len_bytes() {
+        // 'é' is two bytes of UTF-8.
+        let chars = whitespace_split_tokeniser().with_max_line_len(Some(3),
+                                                                    Chars);
+        assert_eq!(chars.add_line("aéb").into_strings(),
+                   Ok(vec![ "aéb".into_string() ]));
+
+        let bytes = whitespace_split_tokeniser().with_max_line_len(Some(3),
+                                                                    Bytes);
+        assert_eq!(bytes.add_line("aéb").into_strings(),
+                   Err(LineTooLong(3, 2)));
+    }
+
+    /// Checks that the consuming and in-place APIs agree on `input` for the
+    /// given tokeniser builder.
+    fn apis_agree(builder: fn() -> StockTokeniser,
+                  input: &str) -> bool {
+        let consumed = builder().add_string(input).into_strings();
+
+        let mut fed = builder();
+        fed.feed_str(input);
+
+        consumed == fed.into_strings()
+    }
+
+    #[test]
+    fn feed_matches_add() {
+        let inputs = [ "the quick brown fox",
+                       "  \"quoted words\" and 'single quotes'  ",
+                       "escaped\\ space \"abc\\nde\"",
+                       "\"unmatched quote",
+                       "unfinished escape\\",
+                       "bad \\q escape",
+                       "" ];
+
+        for input in inputs.iter() {
+            assert!(apis_agree(whitespace_split_tokeniser, *input));
+            assert!(apis_agree(shell_style_tokeniser, *input));
+            assert!(apis_agree(c_style_tokeniser, *input));
+        }
+    }
+
+    #[test]
+    fn push_char_matches_add_char() {
+        let input = "a \"b c\" d\\ e";
+
+        let consumed = input.chars().fold(shell_style_tokeniser(),
+                                          |t, c| t.add_char(c));
+
+        let mut pushed = shell_style_tokeniser();
+        for c in input.chars() {
+            pushed.push_char(c);
+        }
+
+        assert_eq!(consumed.into_strings(), pushed.into_strings());
+    }
+
+    #[test]
+    fn push_line_matches_add_line() {
+        let lines = [ "  ls -l  ", "'a b' \"c", "d\" e" ];
+
+        let consumed = lines.iter().fold(c_style_tokeniser(),
+                                         |t, l| t.add_line(*l));
+
+        let mut pushed = c_style_tokeniser();
+        pushed.push_line(lines[0]).push_line(lines[1]).push_line(lines[2]);
+
+        assert_eq!(consumed.into_strings(), pushed.into_strings());
+    }
+
+    #[test]
+    fn feed_char_unmatched_quote() {
+        let mut tok = shell_style_tokeniser();
+        for c in "abc 'def".chars() {
+            tok.feed_char(c);
+        }
+        assert_eq!(tok.into_strings(), Err(UnmatchedQuote(4)));
+    }
+
+    #[test]
+    fn feed_iter_matches_add_iter() {
+        let mut tok = c_style_tokeniser();
+        tok.feed_iter("a \"b c\"".chars());
+        assert_eq!(tok.into_strings(),
+                   c_style_tokeniser().add_iter("a \"b c\"".chars())
+                                      .into_strings());
+    }
+
+    #[test]
+    fn unmatched_quote_position() {
+        assert_eq!(shell_style_tokeniser().add_line("ab 'cd' \"ef")
+                                          .into_strings(),
+                   Err(UnmatchedQuote(8)));
+        // The position is that of the quote that was left open.
+        assert_eq!(shell_style_tokeniser().add_line("ab\"c'd")
+                                          .into_strings(),
+                   Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn unfinished_escape_position() {
+        assert_eq!(c_style_tokeniser().add_line("a \\n \"b\\").into_strings(),
+                   Err(UnfinishedEscape(7)));
+    }
+
+    #[test]
+    fn unmatched_quote_start_middle_end() {
+        let positions = [ ( "'ab cd ef",  0 ),
+                          ( "ab c'd ef",  4 ),
+                          ( "ab cd ef'",  8 ) ];
+        for &( line, at ) in positions.iter() {
+            assert_eq!(shell_style_tokeniser().add_line(line).into_strings(),
+                       Err(UnmatchedQuote(at)));
+        }
+    }
+
+    #[test]
+    fn unfinished_escape_start_end() {
+        // An unfinished escape can only be at the end of the input, but it
+        // can still be the whole input or the end of a later word.
+        let positions = [ ( "\\",         0 ),
+                          ( "ab cd\\",    5 ),
+                          ( "ab cd ef\\", 8 ) ];
+        for &( line, at ) in positions.iter() {
+            assert_eq!(shell_style_tokeniser().add_line(line).into_strings(),
+                       Err(UnfinishedEscape(at)));
+        }
+    }
+
+    #[test]
+    fn positions_span_several_strings() {
+        assert_eq!(shell_style_tokeniser().add_string("abc ")
+                                          .add_string("de'f")
+                                          .into_strings(),
+                   Err(UnmatchedQuote(6)));
+    }
+
+    #[test]
+    fn into_strings_lossy_unmatched_quote() {
+        let tok = shell_style_tokeniser().add_line("abc \"de f\" \"gh ij");
+        assert_eq!(tok.into_strings_lossy(),
+                   ( vec![ "abc".into_string(),
+                           "de f".into_string(),
+                           "gh ij".into_string() ],
+                     Some(UnmatchedQuote(11)) ));
+    }
+
+    #[test]
+    fn into_strings_lossy_unfinished_escape() {
+        let tok = shell_style_tokeniser().add_line("abc de\\");
+        assert_eq!(tok.into_strings_lossy(),
+                   ( vec![ "abc".into_string(), "de".into_string() ],
+                     Some(UnfinishedEscape(6)) ));
+
+        // An escape leader on its own starts no word.
+        let tok2 = shell_style_tokeniser().add_line("abc \\");
+        assert_eq!(tok2.into_strings_lossy(),
+                   ( vec![ "abc".into_string() ],
+                     Some(UnfinishedEscape(4)) ));
+    }
+
+    #[test]
+    fn into_strings_lossy_clean() {
+        let tok = shell_style_tokeniser().add_line("abc 'de f'");
+        assert_eq!(tok.into_strings_lossy(),
+                   ( vec![ "abc".into_string(), "de f".into_string() ],
+                     None ));
+    }
+
+    #[test]
+    fn reset_mid_quote() {
+        let tok = shell_style_tokeniser().add_line("abc \"def").reset();
+        assert_eq!(tok.add_line("ghi 'j k'").into_strings(),
+                   Ok(vec![ "ghi".into_string(), "j k".into_string() ]));
+    }
+
+    #[test]
+    fn clear_after_error() {
+        let mut tok = c_style_tokeniser().add_line("\\q");
+        tok.clear();
+        tok.feed_str("abc");
+        assert_eq!(tok.into_strings(), Ok(vec![ "abc".into_string() ]));
+    }
+
+    /// Resetting a Tokeniser should make it equivalent to a fresh one,
+    /// whatever state it was in.
+    #[quickcheck]
+    fn reset_equivalent_to_fresh(a: String, b: String) -> bool {
+        let reset = shell_style_tokeniser().add_line(a.as_slice())
+                                           .reset()
+                                           .add_line(b.as_slice());
+        let fresh = shell_style_tokeniser().add_line(b.as_slice());
+
+        reset.into_strings() == fresh.into_strings()
+    }
+
+    /// The observable states of the Tokeniser, for the transition matrix.
+    #[deriving(Clone, Eq, PartialEq, Show)]
+    enum MatrixState {
+        Idle,
+        InWord,
+        InLiteralQuote,
+        InParsingQuote,
+        Escaping,
+        EscapingInQuote,
+        Failed
+    }
+
+    /// The observable effects of a character on the current word.
+    #[deriving(Clone, Eq, PartialEq, Show)]
+    enum MatrixEffect {
+        Nothing,
+        Emits(char),
+        EndsWord
+    }
+
+    /// Builds the canonical tokeniser for the transition matrix.
+    ///
+    /// Its quotes have distinct openers and closers, so that each character
+    /// class can be told apart: `<`...`>` ignores escapes, and `(`...`)`
+    /// parses them.  The only escape sequence is `\n`.
+    fn matrix_tokeniser() -> StockTokeniser {
+        let quote_map: StockQuoteMap =
+            vec![ ( '<', ( '>', IgnoreEscapes ) ),
+                  ( '(', ( ')', ParseEscapes ) ) ].move_iter().collect();
+        let escape_pairs: HashMap<char, char> =
+            vec![ ( 'n', '\n' ) ].move_iter().collect();
+        let escape_map: StockEscapeMap =
+            vec![ ( '\\', MapEscape(escape_pairs) ) ].move_iter().collect();
+        Tokeniser::new(quote_map, escape_map)
+    }
+
+    fn matrix_state(tok: &StockTokeniser) -> MatrixState {
+        match ( tok.error.is_some(), tok.escape.is_some(), &tok.quote ) {
+            ( true, _, _ )                        => Failed,
+            ( _, true, &None )                    => Escaping,
+            ( _, true, &Some(_) )                 => EscapingInQuote,
+            ( _, _, &Some(( _, IgnoreEscapes )) ) => InLiteralQuote,
+            ( _, _, &Some(( _, ParseEscapes )) )  => InParsingQuote,
+            ( _, _, &None ) if tok.in_word        => InWord,
+            _                                     => Idle
+        }
+    }
+
+    fn matrix_effect(before: &StockTokeniser,
+                     after: &StockTokeniser) -> MatrixEffect {
+        if after.vec.len() > before.vec.len() {
+            EndsWord
+        } else if after.vec.last() != before.vec.last() {
+            Emits(after.vec.last().unwrap().as_slice().chars().last().unwrap())
+        } else {
+            Nothing
+        }
+    }
+
+    /// Every (state, character class) combination for the canonical
+    /// tokeniser, and the state and effect that should result.
+    ///
+    /// Each state is reached by feeding its prefix into a fresh tokeniser.
+    /// The character classes are: separator (` `), escape-parsing quote
+    /// opener (`(`) and closer (`)`), literal quote opener (`<`) and closer
+    /// (`>`), escape leader (`\`), mapped escape character (`n`), and
+    /// unmapped character (`x`).
+    ///
+    /// Changing the behaviour of the tokeniser should mean changing this
+    /// table.
+    #[test]
+    fn transition_matrix() {
+        let matrix = [
+            // Idle
+            ( "",     ' ',  Idle,            Nothing      ),
+            ( "",     '(',  InParsingQuote,  Nothing      ),
+            ( "",     ')',  InWord,          Emits(')')   ),
+            ( "",     '<',  InLiteralQuote,  Nothing      ),
+            ( "",     '>',  InWord,          Emits('>')   ),
+            ( "",     '\\', Escaping,        Nothing      ),
+            ( "",     'n',  InWord,          Emits('n')   ),
+            ( "",     'x',  InWord,          Emits('x')   ),
+
+            // In an unquoted word
+            ( "a",    ' ',  Idle,            EndsWord     ),
+            ( "a",    '(',  InParsingQuote,  Nothing      ),
+            ( "a",    ')',  InWord,          Emits(')')   ),
+            ( "a",    '<',  InLiteralQuote,  Nothing      ),
+            ( "a",    '>',  InWord,          Emits('>')   ),
+            ( "a",    '\\', Escaping,        Nothing      ),
+            ( "a",    'n',  InWord,          Emits('n')   ),
+            ( "a",    'x',  InWord,          Emits('x')   ),
+
+            // In an escape-ignoring quote
+            ( "<",    ' ',  InLiteralQuote,  Emits(' ')   ),
+            ( "<",    '(',  InLiteralQuote,  Emits('(')   ),
+            ( "<",    ')',  InLiteralQuote,  Emits(')')   ),
+            ( "<",    '<',  InLiteralQuote,  Emits('<')   ),
+            ( "<",    '>',  InWord,          Nothing      ),
+            ( "<",    '\\', InLiteralQuote,  Emits('\\')  ),
+            ( "<",    'n',  InLiteralQuote,  Emits('n')   ),
+            ( "<",    'x',  InLiteralQuote,  Emits('x')   ),
+
+            // In an escape-parsing quote
+            ( "(",    ' ',  InParsingQuote,  Emits(' ')   ),
+            ( "(",    '(',  InParsingQuote,  Emits('(')   ),
+            ( "(",    ')',  InWord,          Nothing      ),
+            ( "(",    '<',  InParsingQuote,  Emits('<')   ),
+            ( "(",    '>',  InParsingQuote,  Emits('>')   ),
+            ( "(",    '\\', EscapingInQuote, Nothing      ),
+            ( "(",    'n',  InParsingQuote,  Emits('n')   ),
+            ( "(",    'x',  InParsingQuote,  Emits('x')   ),
+
+            // Escaping outside quotes
+            ( "\\",   ' ',  Failed,          Nothing      ),
+            ( "\\",   '(',  Failed,          Nothing      ),
+            ( "\\",   ')',  Failed,          Nothing      ),
+            ( "\\",   '<',  Failed,          Nothing      ),
+            ( "\\",   '>',  Failed,          Nothing      ),
+            ( "\\",   '\\', Failed,          Nothing      ),
+            ( "\\",   'n',  InWord,          Emits('\n')  ),
+            ( "\\",   'x',  Failed,          Nothing      ),
+
+            // Escaping inside an escape-parsing quote
+            ( "(\\",  ' ',  Failed,          Nothing      ),
+            ( "(\\",  '(',  Failed,          Nothing      ),
+            ( "(\\",  ')',  Failed,          Nothing      ),
+            ( "(\\",  '<',  Failed,          Nothing      ),
+            ( "(\\",  '>',  Failed,          Nothing      ),
+            ( "(\\",  '\\', Failed,          Nothing      ),
+            ( "(\\",  'n',  InParsingQuote,  Emits('\n')  ),
+            ( "(\\",  'x',  Failed,          Nothing      )
+        ];
+
+        for &( prefix, chr, state, effect ) in matrix.iter() {
+            let before = matrix_tokeniser().add_string(prefix);
+            let after = before.clone().add_char(chr);
+
+            assert!(matrix_state(&after) == state,
+                    "{} then {}: expected state {}, got {}",
+                    prefix, chr, state, matrix_state(&after));
+            assert!(matrix_effect(&before, &after) == effect,
+                    "{} then {}: expected effect {}, got {}",
+                    prefix, chr, effect, matrix_effect(&before, &after));
+        }
+    }
+
+    /// Each prefix in the transition matrix should put the canonical
+    /// tokeniser into the state it is listed under.
+    #[test]
+    fn transition_matrix_prefixes() {
+        let prefixes = [ ( "",    Idle            ),
+                         ( "a",   InWord          ),
+                         ( "<",   InLiteralQuote  ),
+                         ( "(",   InParsingQuote  ),
+                         ( "\\",  Escaping        ),
+                         ( "(\\", EscapingInQuote ) ];
+
+        for &( prefix, state ) in prefixes.iter() {
+            assert_eq!(matrix_state(&matrix_tokeniser().add_string(prefix)),
+                       state);
+        }
+    }
+
+    #[test]
+    fn replace_word_out_of_bounds() {
+        let tok = whitespace_split_tokeniser().add_string("abc def ");
+        assert_eq!(tok.replace_word(2, "ghi").into_strings(),
+                   Ok(vec![ "abc".into_string(), "def".into_string() ]));
+    }
+
+    #[test]
+    fn replace_word_ignores_current_word() {
+        let tok = whitespace_split_tokeniser().add_string("abc def");
+        assert_eq!(tok.replace_word(1, "ghi").into_strings(),
+                   Ok(vec![ "abc".into_string(), "def".into_string() ]));
+    }
+
+    #[test]
+    fn tokens_in_order() {
+        let tok = whitespace_split_tokeniser();
+        let tokens: Vec<Result<String, super::Error>> =
+            tok.tokens(" the  quick brown ".chars(), Unbounded).collect();
+        assert_eq!(tokens, vec![ Ok("the".into_string()),
+                                 Ok("quick".into_string()),
+                                 Ok("brown".into_string()) ]);
+    }
+
+    #[test]
+    fn tokens_quoted_separators() {
+        let tok = shell_style_tokeniser();
+        let tokens: Vec<Result<String, super::Error>> =
+            tok.tokens("a \"b c\" 'd e'f".chars(), Unbounded).collect();
+        assert_eq!(tokens, vec![ Ok("a".into_string()),
+                                 Ok("b c".into_string()),
+                                 Ok("d ef".into_string()) ]);
+    }
+
+    #[test]
+    fn tokens_fuse_after_error() {
+        let tok = shell_style_tokeniser();
+        let mut tokens = tok.tokens("a 'b".chars(), Unbounded);
+        assert_eq!(tokens.next(), Some(Ok("a".into_string())));
+        assert_eq!(tokens.next(), Some(Err(UnmatchedQuote(2))));
+        assert_eq!(tokens.next(), None);
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn tokens_yield_words_before_error() {
+        // Words completed before a failure are still yielded.
+        let tok = c_style_tokeniser();
+        let mut tokens = tok.tokens("a \\q".chars(), Unbounded);
+        assert_eq!(tokens.next(), Some(Ok("a".into_string())));
+        assert_eq!(tokens.next(), Some(Err(BadEscape)));
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn tokens_bounded_resume_after_limit() {
+        let line = "a 'bcdefgh ij\nk lmnop";
+        let tokens: Vec<Result<String, super::Error>> =
+            shell_style_tokeniser().tokens(line.chars(), Bounded(4)).collect();
+        assert_eq!(tokens, vec![ Ok("a".into_string()),
+                                 Err(LimitExceeded(4)),
+                                 Ok("k".into_string()),
+                                 Err(LimitExceeded(4)) ]);
+    }
+
+    #[test]
+    fn tokens_bounded_endless_quote() {
+        let endless = "'".chars().chain(Repeat::new('x'));
+        let mut tokens = shell_style_tokeniser().tokens(endless, Bounded(16));
+        assert_eq!(tokens.next(), Some(Err(LimitExceeded(16))));
+        assert_eq!(tokens.buffer_capacity(), 0);
+    }
+
+    #[test]
+    fn json_array_escapes() {
+        let tok = c_style_tokeniser().add_line("a\\nb \"c\\\\d\" \\x01");
+        let json = "[\"a\\nb\", \"c\\\\d\", \"\\u0001\"]";
+        assert_eq!(tok.into_strings_to_json_array(), Ok(json.into_string()));
+    }
+
+    #[test]
+    fn json_array_empty() {
+        assert_eq!(c_style_tokeniser().into_strings_to_json_array(),
+                   Ok("[]".into_string()));
+    }
+
+    /// Builds a multi-kilobyte line exercising quotes and escapes.
+    fn long_line() -> String {
+        let mut line = String::new();
+        for i in range(0u, 200) {
+            line.push_str(format!("w{} \"q u\\\"o\" 'é s' e\\ sc ",
+                                  i).as_slice());
+        }
+        line
+    }
+
+    #[test]
+    fn add_reader_matches_add_string() {
+        let line = long_line();
+        let reader = MemReader::new(line.as_bytes().to_vec());
+
+        assert_eq!(shell_style_tokeniser().add_reader(reader, Unbounded)
+                                          .unwrap()
+                                          .into_strings(),
+                   shell_style_tokeniser().add_string(line.as_slice())
+                                          .into_strings());
+    }
+
+    #[test]
+    fn add_reader_one_byte_at_a_time() {
+        let line = long_line();
+        let reader = BufferedReader::with_capacity(
+            1, MemReader::new(line.as_bytes().to_vec()));
+
+        assert_eq!(shell_style_tokeniser().add_reader(reader, Unbounded)
+                                          .unwrap()
+                                          .into_strings(),
+                   shell_style_tokeniser().add_string(line.as_slice())
+                                          .into_strings());
+    }
+
+    #[test]
+    fn add_reader_invalid_utf8() {
+        let reader = MemReader::new(vec![ b'a', 0xFF, b'b' ]);
+        assert!(shell_style_tokeniser().add_reader(reader, Unbounded)
+                                       .is_err());
+    }
+
+    #[test]
+    fn add_reader_bounded() {
+        let reader = MemReader::new(b"ab 'cdefgh ij".to_vec());
+        let tok = shell_style_tokeniser().add_reader(reader, Bounded(4));
+        assert_eq!(tok.unwrap().into_strings(), Err(LimitExceeded(4)));
+
+        let reader = MemReader::new(b"ab cdef".to_vec());
+        let tok = shell_style_tokeniser().add_reader(reader, Bounded(4));
+        assert_eq!(tok.unwrap().into_strings(),
+                   Ok(vec![ "ab".into_string(), "cdef".into_string() ]));
+    }
+
+    #[test]
+    fn feed_reader_stops_at_line_limit() {
+        let mut reader = MemReader::new(b"abcdef ghi".to_vec());
+        let mut tok = whitespace_split_tokeniser().with_max_line_len(Some(3),
+                                                                      Chars);
+        assert!(tok.feed_reader(&mut reader, Unbounded).is_ok());
+        assert_eq!(tok.into_strings(), Err(LineTooLong(3, 3)));
+        assert_eq!(reader.read_to_string().unwrap(), "ef ghi".into_string());
+    }
+
+    #[test]
+    fn shell_line_round_trip() {
+        let line = "a 'b c' \"d'e\" f\\ g \\$h";
+        let words = shell_style_tokeniser().add_line(line).into_strings();
+        let quoted = shell_style_tokeniser().add_line(line)
+                                            .into_strings_to_shell_line()
+                                            .unwrap();
+
+        assert_eq!(quoted, "a b\\ c d\\'e f\\ g \\$h".into_string());
+        assert_eq!(shell_style_tokeniser().add_line(quoted.as_slice())
+                                          .into_strings(),
+                   words);
+    }
+
+    #[test]
+    fn separator_before_quote() {
+        let strict = || {
+            shell_style_tokeniser().with_before_quote(SeparatorBeforeQuote)
+        };
+
+        assert_eq!(strict().add_line("x\"a\"").into_strings(),
+                   Err(QuoteNotAtFieldStart(1)));
+        assert_eq!(strict().add_line("'a''b'").into_strings(),
+                   Err(QuoteNotAtFieldStart(3)));
+        assert_eq!(strict().add_line("x \"a\" 'b'").into_strings(),
+                   Ok(vec![ "x".into_string(),
+                            "a".into_string(),
+                            "b".into_string() ]));
+        // Only the character before the quote is constrained.
+        assert_eq!(strict().add_line("\"a\"x").into_strings(),
+                   Ok(vec![ "ax".into_string() ]));
+    }
+
+    #[test]
+    fn any_before_quote_is_default() {
+        assert_eq!(shell_style_tokeniser().add_line("x\"a\"'b'")
+                                          .into_strings(),
+                   Ok(vec![ "xab".into_string() ]));
+    }
+
+    #[test]
+    fn current_word_plain() {
+        let tok = shell_style_tokeniser().add_string("abc de");
+        assert_eq!(tok.current_word(), Some("de"));
+    }
+
+    #[test]
+    fn current_word_quoted() {
+        let tok = shell_style_tokeniser().add_string("abc \"de f\\\"g");
+        assert_eq!(tok.current_word(), Some("de f\"g"));
+    }
+
+    #[test]
+    fn current_word_after_separator() {
+        let tok = shell_style_tokeniser().add_string("abc ");
+        assert_eq!(tok.current_word(), None);
+        assert_eq!(shell_style_tokeniser().current_word(), None);
+    }
+
+    #[test]
+    fn current_word_unfinished_escape() {
+        let tok = c_style_tokeniser().add_string("ab\\tc\\x4");
+        assert_eq!(tok.current_word(), Some("ab\tc"));
+    }
+
+    #[test]
+    fn into_iter_words() {
+        let words: Vec<Result<String, super::Error>> =
+            shell_style_tokeniser().add_line("a 'b c' d").into_iter().collect();
+        assert_eq!(words, vec![ Ok("a".into_string()),
+                                Ok("b c".into_string()),
+                                Ok("d".into_string()) ]);
+    }
+
+    #[test]
+    fn into_iter_stops_early() {
+        let mut it = shell_style_tokeniser().add_line("a b c").into_iter();
+        assert_eq!(it.next(), Some(Ok("a".into_string())));
+    }
+
+    #[test]
+    fn into_iter_unfinished_escape() {
+        let words: Vec<Result<String, super::Error>> =
+            shell_style_tokeniser().add_line("a bc\\").into_iter().collect();
+        assert_eq!(words, vec![ Ok("a".into_string()),
+                                Err(UnfinishedEscape(4)) ]);
+    }
+
+    #[test]
+    fn words_valid() {
+        let words: Vec<String> =
+            shell_style_tokeniser().add_line("a 'b c'").words().collect();
+        assert_eq!(words, vec![ "a".into_string(), "b c".into_string() ]);
+    }
+
+    #[test]
+    #[should_fail]
+    fn words_invalid() {
+        let words: Vec<String> =
+            shell_style_tokeniser().add_line("a 'b c").words().collect();
+        assert_eq!(words.len(), 1);
+    }
+
+    #[test]
+    fn null_delimited() {
+        let tok = shell_style_tokeniser().add_line("a 'b c' d");
+        assert_eq!(tok.into_strings_to_null_delimited(),
+                   Ok(b"a\0b c\0d\0".to_vec()));
+    }
+
+    #[test]
+    fn null_delimited_empty() {
+        let tok = shell_style_tokeniser().add_line("   ");
+        assert_eq!(tok.into_strings_to_null_delimited(), Ok(vec![]));
+    }
+
+    #[test]
+    fn null_delimited_error() {
+        let tok = shell_style_tokeniser().add_line("a 'b");
+        assert_eq!(tok.into_strings_to_null_delimited(),
+                   Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn state_transitions() {
+        let expected = [ ( 'a',  super::InWord ),
+                         ( ' ',  super::Ready ),
+                         ( '"',  super::InQuote('"', ParseEscapes) ),
+                         ( 'b',  super::InQuote('"', ParseEscapes) ),
+                         ( '\\', super::InEscape ),
+                         ( 'n',  super::InQuote('"', ParseEscapes) ),
+                         ( '"',  super::InWord ),
+                         ( '\'', super::InQuote('\'', IgnoreEscapes) ),
+                         ( '\'', super::InWord ),
+                         ( '\\', super::InEscape ),
+                         ( ' ',  super::InWord ),
+                         ( '\t', super::Ready ) ];
+
+        let mut tok = shell_style_tokeniser();
+        assert_eq!(tok.state(), super::Ready);
+        for &( c, ref state ) in expected.iter() {
+            tok.push_char(c);
+            assert_eq!(&tok.state(), state);
+        }
+    }
+
+    #[test]
+    fn state_failed() {
+        let tok = c_style_tokeniser().add_string("a \\q b");
+        assert_eq!(tok.state(), super::Failed);
+    }
+
+    #[test]
+    fn add_line_strict_valid() {
+        let tok = shell_style_tokeniser().add_line_strict("a 'b c'");
+        assert_eq!(tok.ok().map(|t| t.into_strings()),
+                   Some(Ok(vec![ "a".into_string(), "b c".into_string() ])));
+    }
+
+    #[test]
+    fn add_line_strict_open_quote() {
+        let tok = shell_style_tokeniser()
+                  .add_line_strict("a \"b")
+                  .and_then(|t| t.add_line_strict("c\""));
+        assert_eq!(tok.ok().map(|t| t.into_strings()),
+                   Some(Ok(vec![ "a".into_string(), "b\nc".into_string() ])));
+    }
+
+    #[test]
+    fn add_line_strict_unfinished_escape() {
+        let tok = shell_style_tokeniser().add_line_strict("a b\\\n");
+        assert_eq!(tok.err(), Some(UnfinishedEscape(3)));
+    }
+
+    #[test]
+    fn add_line_keeps_spaces_at_edges() {
+        let tok = shell_style_tokeniser().add_line("\"  padded  \"\n");
+        assert_eq!(tok.into_strings(), Ok(vec![ "  padded  ".into_string() ]));
+
+        let tok = shell_style_tokeniser().add_line("a\\ \r\n");
+        assert_eq!(tok.into_strings(), Ok(vec![ "a ".into_string() ]));
+
+        let tok = csv_tokeniser().add_line(" a , b \n");
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ " a ".into_string(), " b ".into_string() ]));
+    }
+
+    #[test]
+    fn add_line_strict_bad_escape() {
+        let tok = c_style_tokeniser().add_line_strict("a \\q");
+        assert_eq!(tok.err(), Some(BadEscape));
+    }
+
+    #[test]
+    fn add_line_strict_escape_ended_by_line() {
+        let quote_map: StockQuoteMap = HashMap::new();
+        let escape_map: StockEscapeMap =
+            vec![ ( '\\', posix_escapes() ) ].move_iter().collect();
+        let tok = Tokeniser::new(quote_map, escape_map);
+        assert_eq!(tok.add_line_strict("a\\101").ok().map(|t| t.into_strings()),
+                   Some(Ok(vec![ "aA".into_string() ])));
+    }
+
+    #[test]
+    fn write_macro() {
+        let mut tok = shell_style_tokeniser();
+        assert!(write!(tok, "hello {}", "world").is_ok());
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "hello".into_string(), "world".into_string() ]));
+    }
+
+    #[test]
+    fn write_macro_quoted() {
+        let mut tok = shell_style_tokeniser();
+        assert!(write!(tok, "'{}' {}", "a b", 42u).is_ok());
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "a b".into_string(), "42".into_string() ]));
+    }
+
+    /// Pairs `word` with the Span from `start` to `end`.
+    fn spanned(word: &str, start: uint, end: uint) -> ( String, Span ) {
+        ( word.into_string(), Span { start: start, end: end } )
+    }
+
+    #[test]
+    fn spans_quoted() {
+        let tok = shell_style_tokeniser().add_line("a 'b c' d");
+        assert_eq!(tok.into_spans(),
+                   Ok(vec![ spanned("a", 0, 1),
+                            spanned("b c", 2, 7),
+                            spanned("d", 8, 9) ]));
+    }
+
+    #[test]
+    fn spans_escaped() {
+        let tok = c_style_tokeniser().add_line("ab c\\nd");
+        assert_eq!(tok.into_spans(),
+                   Ok(vec![ spanned("ab", 0, 2), spanned("c\nd", 3, 7) ]));
+    }
+
+    #[test]
+    fn spans_over_lines() {
+        let tok = shell_style_tokeniser().add_line("ab 'c").add_line("d' e");
+        assert_eq!(tok.into_spans(),
+                   Ok(vec![ spanned("ab", 0, 2),
+                            spanned("cd", 3, 7),
+                            spanned("e", 8, 9) ]));
+    }
+
+    #[test]
+    fn spans_multibyte() {
+        let tok = whitespace_split_tokeniser().add_line("é b");
+        assert_eq!(tok.into_spans(),
+                   Ok(vec![ spanned("é", 0, 2), spanned("b", 3, 4) ]));
+    }
+
+    #[test]
+    fn spans_error() {
+        let tok = shell_style_tokeniser().add_line("a 'b");
+        assert_eq!(tok.into_spans(), Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn utf16() {
+        let tok = shell_style_tokeniser().add_line("ab 'é c'");
+        assert_eq!(tok.into_strings_utf16(),
+                   Ok(vec![ vec![ 0x61, 0x62 ],
+                            vec![ 0xE9, 0x20, 0x63 ] ]));
+    }
+
+    #[test]
+    fn utf16_error() {
+        let tok = shell_style_tokeniser().add_line("ab \"c");
+        assert_eq!(tok.into_strings_utf16(), Err(UnmatchedQuote(3)));
+    }
+
+    #[test]
+    fn extend_chars() {
+        let mut tok = shell_style_tokeniser();
+        tok.extend("a 'b c'".chars());
+        tok.extend(" d".chars());
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "a".into_string(),
+                            "b c".into_string(),
+                            "d".into_string() ]));
+    }
+
+    #[test]
+    fn extend_lines() {
+        let mut tok = shell_style_tokeniser();
+        tok.extend(vec![ "  a 'b", "c' d  " ].move_iter());
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "a".into_string(),
+                            "b\nc".into_string(),
+                            "d".into_string() ]));
+    }
+
+    #[test]
+    fn collect_chars() {
+        let tok: StockTokeniser = " a 'b c' ".chars().collect();
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "a".into_string(),
+                            "'b".into_string(),
+                            "c'".into_string() ]));
+    }
+
+    #[test]
+    fn default_splits_whitespace() {
+        let tok: StockTokeniser = Default::default();
+        assert_eq!(tok.add_line("a\\ b").into_strings(),
+                   whitespace_split_tokeniser().add_line("a\\ b")
+                                               .into_strings());
+    }
+
+    #[test]
+    fn find_word_quoted() {
+        let tok = shell_style_tokeniser();
+        assert_eq!(tok.find_word("sudo \"rm -rf\" x", "rm", CaseSensitive),
+                   Ok(None));
+        assert_eq!(tok.find_word("sudo \"rm\" -rf", "rm", CaseSensitive),
+                   Ok(Some(( 1, Span { start: 5, end: 9 } ))));
+        assert_eq!(tok.find_word("sudo rm\\ -rf rm", "rm", CaseSensitive),
+                   Ok(Some(( 2, Span { start: 13, end: 15 } ))));
+    }
+
+    #[test]
+    fn find_word_case() {
+        let tok = shell_style_tokeniser();
+        assert_eq!(tok.contains_word("ls -L", "-l", CaseSensitive),
+                   Ok(false));
+        assert_eq!(tok.contains_word("ls -L", "-l", CaseInsensitive),
+                   Ok(true));
+    }
+
+    #[test]
+    fn find_word_stops_at_match() {
+        // The unmatched quote comes after the match, so is never read.
+        let tok = shell_style_tokeniser();
+        assert_eq!(tok.contains_word("a b 'c", "a", CaseSensitive),
+                   Ok(true));
+        assert_eq!(tok.contains_word("a b 'c", "x", CaseSensitive),
+                   Err(UnmatchedQuote(4)));
+    }
+
+    #[test]
+    fn find_word_ignores_state() {
+        let tok = shell_style_tokeniser().add_string("x '");
+        assert_eq!(tok.contains_word("x", "x", CaseSensitive), Ok(true));
+    }
+
+    #[test]
+    fn feed_sink_holds_no_words() {
+        let mut tok = shell_style_tokeniser();
+        let mut sink: Vec<String> = vec![];
+        assert_eq!(tok.feed_sink("a ", &mut sink), Ok(()));
+        let info_cap = tok.info.capacity();
+
+        let line = "b 'c d' e\\ f ".repeat(100);
+        assert_eq!(tok.feed_sink(line.as_slice(), &mut sink), Ok(()));
+        assert_eq!(sink.len(), 301);
+
+        // The words went straight into the sink; the Tokeniser's own
+        // buffers neither held them nor grew.
+        assert_eq!(tok.vec, vec![ String::new() ]);
+        assert_eq!(tok.vec.get(0).capacity(), 0);
+        assert_eq!(tok.info.capacity(), info_cap);
+    }
+
+    #[test]
+    fn drain_completed_fresh() {
+        let mut tok = shell_style_tokeniser();
+        assert_eq!(tok.drain_completed(), vec![]);
+        assert_eq!(tok.into_strings(), Ok(vec![]));
+    }
+
+    #[test]
+    fn drain_completed_mid_escape() {
+        let mut tok = c_style_tokeniser();
+        tok.push_str("a b c\\");
+        assert_eq!(tok.drain_completed(),
+                   vec![ "a".into_string(), "b".into_string() ]);
+        tok.push_str("nd e");
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "c\nd".into_string(), "e".into_string() ]));
+    }
+
+    #[test]
+    fn drain_completed_mid_quote() {
+        let mut tok = shell_style_tokeniser();
+        tok.push_str("a 'b c");
+        assert_eq!(tok.drain_completed(), vec![ "a".into_string() ]);
+        assert_eq!(tok.drain_completed(), vec![]);
+        assert_eq!(tok.into_strings(), Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn drain_completed_keeps_spans() {
+        let mut tok = shell_style_tokeniser();
+        tok.push_str("a b ");
+        tok.drain_completed();
+        tok.push_str("cd");
+        assert_eq!(tok.into_spans(), Ok(vec![ spanned("cd", 4, 6) ]));
+    }
+
+    #[test]
+    fn separator_chars_quoted() {
+        let tok = c_style_tokeniser().with_separators(SeparatorChars(vec![
+            ','
+        ]));
+        assert_eq!(tok.add_line("a,\"b,c\",d").into_strings(),
+                   Ok(vec![ "a".into_string(),
+                            "b,c".into_string(),
+                            "d".into_string() ]));
+    }
+
+    #[test]
+    fn separator_chars_replace_whitespace() {
+        let tok = shell_style_tokeniser().with_separators(SeparatorChars(vec![
+            ',', ';'
+        ]));
+        assert_eq!(tok.add_line("a b;c\\,d,e").into_strings(),
+                   Ok(vec![ "a b".into_string(),
+                            "c,d".into_string(),
+                            "e".into_string() ]));
+    }
+
+    /// A tokeniser whose double quotes fold whitespace, with C escapes.
+    fn folding_tokeniser() -> StockTokeniser {
+        let quote_map: StockQuoteMap =
+            vec![ ( '\"', ( '\"', FoldWhitespace ) ) ].move_iter().collect();
+        let escape_map: StockEscapeMap =
+            vec![ ( '\\', c_escapes() ) ].move_iter().collect();
+        Tokeniser::new(quote_map, escape_map)
+    }
+
+    #[test]
+    fn fold_whitespace_paragraph() {
+        let tok = folding_tokeniser().add_string(concat!(
+            "x \"A quoted\n",
+            "   paragraph, over \n",
+            "\tthree lines.\" y"));
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "x".into_string(),
+                            "A quoted paragraph, over three lines."
+                                .into_string(),
+                            "y".into_string() ]));
+    }
+
+    #[test]
+    fn fold_whitespace_escaped() {
+        let tok = folding_tokeniser().add_string("\"a\\t\\t b \\n  c\"");
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "a\t\t b \n c".into_string() ]));
+    }
+
+    #[test]
+    fn fold_whitespace_off() {
+        let line = "\"a  b\n\t c\"";
+        assert_eq!(c_style_tokeniser().add_string(line).into_strings(),
+                   Ok(vec![ "a  b\n\t c".into_string() ]));
+    }
+
+    #[test]
+    fn fold_whitespace_parses_escapes() {
+        let modes: [QuoteMode<StockEscapeScheme>, ..3] =
+            [ IgnoreEscapes, ParseEscapes, FoldWhitespace ];
+        assert!(!modes[0].parses_escapes());
+        assert!(modes[1].parses_escapes());
+        assert!(modes[2].parses_escapes());
+    }
+
+    #[test]
+    fn fold_words_joins() {
+        let tok = shell_style_tokeniser().add_line("a 'b c' d");
+        let joined = tok.fold_words(String::new(), |mut acc, w| {
+            if !acc.is_empty() { acc.push_char('|'); }
+            acc.push_str(w.as_slice());
+            acc
+        });
+        assert_eq!(joined, Ok("a|b c|d".into_string()));
+    }
+
+    #[test]
+    fn fold_words_error() {
+        let tok = shell_style_tokeniser().add_line("a 'b");
+        assert_eq!(tok.fold_words(0u, |n, _| n + 1), Err(UnmatchedQuote(2)));
+    }
+
+    /// Separates words on semicolons and whitespace.
+    fn is_semicolon_or_space(c: char) -> bool {
+        c == ';' || c.is_whitespace()
+    }
+
+    /// Separates words on spaces only.
+    fn is_space(c: char) -> bool {
+        c == ' '
+    }
+
+    #[test]
+    fn separator_fn_semicolons() {
+        let tok = shell_style_tokeniser()
+                  .with_separator_fn(is_semicolon_or_space);
+        assert_eq!(tok.add_line("a;b 'c;d' e\\;f;;g").into_strings(),
+                   Ok(vec![ "a".into_string(),
+                            "b".into_string(),
+                            "c;d".into_string(),
+                            "e;f".into_string(),
+                            "g".into_string() ]));
+    }
+
+    #[test]
+    fn separator_fn_spaces_only() {
+        let tok = shell_style_tokeniser().with_separator_fn(is_space);
+        assert_eq!(tok.add_line("a\tb  c\n\"d e\"").into_strings(),
+                   Ok(vec![ "a\tb".into_string(),
+                            "c\nd e".into_string() ]));
+    }
+
+    /// A tokeniser whose backquotes escape only their own closer, with `\`,
+    /// and whose double quotes use the C escapes.
+    fn custom_quote_tokeniser() -> StockTokeniser {
+        let closer_only: HashMap<char, char> =
+            vec![ ( '`', '`' ) ].move_iter().collect();
+        let quote_map: StockQuoteMap =
+            vec![ ( '`', ( '`', Custom('\\', MapEscape(closer_only)) ) ),
+                  ( '"', ( '"', ParseEscapes ) ) ].move_iter().collect();
+        let escape_map: StockEscapeMap =
+            vec![ ( '\\', c_escapes() ) ].move_iter().collect();
+        Tokeniser::new(quote_map, escape_map)
+    }
+
+    #[test]
+    fn custom_quote_escapes_closer() {
+        let tok = custom_quote_tokeniser().add_line("`a\\`b` \"c\\nd\"");
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "a`b".into_string(), "c\nd".into_string() ]));
+    }
+
+    #[test]
+    fn custom_quote_ignores_escape_map() {
+        let tok = custom_quote_tokeniser().add_line("`a\\nb`");
+        assert_eq!(tok.into_strings(), Err(BadEscape));
+    }
+
+    #[test]
+    fn custom_quote_state() {
+        let tok = custom_quote_tokeniser().add_string("`a\\");
+        assert_eq!(tok.state(), super::InEscape);
+        assert!(!Custom('\\', c_escapes()).parses_escapes());
+    }
+
+    #[test]
+    fn max_total_length() {
+        let line = "ab 'c d' é";
+        // The words are "ab", "c d" and "é", which total 7 bytes.
+        assert_eq!(shell_style_tokeniser().add_line(line)
+                                          .into_strings_max_total_length(7),
+                   shell_style_tokeniser().add_line(line).into_strings());
+        assert_eq!(shell_style_tokeniser().add_line(line)
+                                          .into_strings_max_total_length(6),
+                   Err(TotalLengthExceeded(6, 7)));
+    }
+
+    #[test]
+    fn max_total_length_other_errors_first() {
+        assert_eq!(shell_style_tokeniser().add_line("abcdef 'g")
+                                          .into_strings_max_total_length(1),
+                   Err(UnmatchedQuote(7)));
+    }
+
+    #[test]
+    fn max_words_remainder_after_whitespace() {
+        let tok = shell_style_tokeniser().with_max_words(Some(2))
+                                         .add_string("say   \t  a  b\\ c ");
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "say".into_string(), "a  b\\ c ".into_string() ]));
+    }
+
+    #[test]
+    fn max_words_unmatched_quote_in_remainder() {
+        let tok = shell_style_tokeniser().with_max_words(Some(2))
+                                         .add_line("say \"don't");
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "say".into_string(),
+                            "\"don't".into_string() ]));
+    }
+
+    #[test]
+    fn max_words_fewer_words() {
+        let tok = shell_style_tokeniser().with_max_words(Some(3))
+                                         .add_line("say 'a b'");
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "say".into_string(), "a b".into_string() ]));
+    }
+
+    #[test]
+    fn max_words_one() {
+        let line = " 'a' \\b ";
+        let whole = shell_style_tokeniser().with_max_words(Some(1))
+                                           .add_string(line);
+        assert_eq!(whole.into_strings(), Ok(vec![ "'a' \\b ".into_string() ]));
+        let zero = shell_style_tokeniser().with_max_words(Some(0))
+                                          .add_string(line);
+        assert_eq!(zero.into_strings(), Ok(vec![ "'a' \\b ".into_string() ]));
+    }
+
+    /// A shell-style tokeniser whose double quotes are kept in words.
+    fn keep_double_quotes_tokeniser() -> StockTokeniser {
+        let quote_map: StockQuoteMap =
+            vec![ ( '"', ( '"', KeepDelimiters(box ParseEscapes) ) ),
+                  ( '\'', ( '\'', IgnoreEscapes ) ) ].move_iter().collect();
+        let escape_map: StockEscapeMap =
+            vec![ ( '\\', posix_escapes() ) ].move_iter().collect();
+        Tokeniser::new(quote_map, escape_map)
+    }
+
+    #[test]
+    fn keep_delimiters_per_quote() {
+        let tok = keep_double_quotes_tokeniser();
+        let line = "say \"a \\\"b\\\"\" 'c \"d\"' e\"f\"g";
+        assert_eq!(tok.add_line(line).into_strings(),
+                   Ok(vec![ "say".into_string(),
+                            "\"a \"b\"\"".into_string(),
+                            "c \"d\"".into_string(),
+                            "e\"f\"g".into_string() ]));
+    }
+
+    #[test]
+    fn keep_delimiters_unmatched_quote() {
+        let tok = keep_double_quotes_tokeniser().add_line("a \"b");
+        assert_eq!(tok.state(),
+                   super::InQuote('"', KeepDelimiters(box ParseEscapes)));
+        assert_eq!(tok.into_strings(), Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn keep_delimiters_base() {
+        let mode: QuoteMode<StockEscapeScheme> =
+            KeepDelimiters(box KeepDelimiters(box FoldWhitespace));
+        assert!(mode.keeps_delimiters());
+        assert!(mode.parses_escapes());
+        assert_eq!(mode.base(), &FoldWhitespace);
+        assert!(!FoldWhitespace.keeps_delimiters());
+    }
+
+    #[test]
+    fn interned_words_shared() {
+        let tok = shell_style_tokeniser().add_line("a 'b' \"a\" b c");
+        let words = tok.into_strings_interned().unwrap();
+        let strings: Vec<String> =
+            words.iter().map(|w| w.as_slice().into_string()).collect();
+        let expected = shell_style_tokeniser().add_line("a b a b c");
+        assert_eq!(Ok(strings), expected.into_strings());
+
+        let ptr = |i: uint| &**words.get(i) as *const String;
+        assert_eq!(ptr(0), ptr(2));
+        assert_eq!(ptr(1), ptr(3));
+        assert!(ptr(0) != ptr(1));
+        assert!(ptr(0) != ptr(4));
+    }
+
+    #[test]
+    fn interned_error() {
+        let tok = shell_style_tokeniser().add_line("a 'b");
+        assert_eq!(tok.into_strings_interned().err(), Some(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn keep_delimiters_shell_style() {
+        let tok = shell_style_tokeniser().with_keep_delimiters(true);
+        assert_eq!(tok.add_line("say \"hello world\"").into_strings(),
+                   Ok(vec![ "say".into_string(),
+                            "\"hello world\"".into_string() ]));
+
+        let tok = shell_style_tokeniser().with_keep_delimiters(true);
+        let line = "'a \"b\" c' \"it's\" \"d\\\"e\" 'f\\'";
+        assert_eq!(tok.add_line(line).into_strings(),
+                   Ok(vec![ "'a \"b\" c'".into_string(),
+                            "\"it's\"".into_string(),
+                            "\"d\"e\"".into_string(),
+                            "'f\\'".into_string() ]));
+    }
+
+    #[test]
+    fn keep_delimiters_off() {
+        let line = "a 'b c' \"d\"";
+        let kept = shell_style_tokeniser().with_keep_delimiters(false);
+        assert_eq!(kept.add_line(line).into_strings(),
+                   shell_style_tokeniser().add_line(line).into_strings());
+    }
+
+    /// Maps semicolons onto spaces.
+    fn semicolon_to_space(c: char) -> char {
+        if c == ';' { ' ' } else { c }
+    }
+
+    #[test]
+    fn input_transform_before_reading() {
+        let tok = shell_style_tokeniser().with_input_transform(
+            semicolon_to_space
+        );
+        assert_eq!(tok.add_line("a;b 'c;d'").into_strings(),
+                   Ok(vec![ "a".into_string(),
+                            "b".into_string(),
+                            "c d".into_string() ]));
+    }
+
+    #[test]
+    fn input_transform_keeps_offsets() {
+        let tok = shell_style_tokeniser().with_input_transform(
+            semicolon_to_space
+        );
+        assert_eq!(tok.add_line("é;'f").into_spans(),
+                   Err(UnmatchedQuote(2)));
+
+        let tok = shell_style_tokeniser().with_input_transform(
+            semicolon_to_space
+        );
+        assert_eq!(tok.add_line("é;f").into_spans(),
+                   Ok(vec![ spanned("é", 0, 2), spanned("f", 3, 4) ]));
+    }
+
+    #[test]
+    fn add_char_with_transform() {
+        let tok = shell_style_tokeniser().with_input_transform(
+            semicolon_to_space
+        );
+        let tok = tok.add_char_with_transform('a', |_| ';')
+                     .add_char_with_transform('b', |c| c)
+                     .add_char('c');
+        assert_eq!(tok.into_strings(), Ok(vec![ "bc".into_string() ]));
+    }
+
+    /// Tokenises `line` with `tok`, keeping only the kinds of the words.
+    fn kinds(tok: StockTokeniser, line: &str)
+      -> Result<Vec<TokenKind>, super::Error> {
+        tok.add_line(line).into_tokens().map(|ts| {
+            ts.move_iter().map(|t| t.kind).collect()
+        })
+    }
+
+    #[test]
+    fn tokens_kinds() {
+        assert_eq!(kinds(shell_style_tokeniser(),
+                         "a 'b c' \"d\\\"e\" f\\ g 'h'\"i\" j'k' \\l''"),
+                   Ok(vec![ Bare, SingleQuoted, DoubleQuoted, Escaped,
+                            Mixed, Mixed, Mixed ]));
+    }
+
+    #[test]
+    fn tokens_values() {
+        let tok = shell_style_tokeniser().add_line("a 'b c' d\\ e");
+        assert_eq!(tok.into_tokens(),
+                   Ok(vec![ Token { value: "a".into_string(),
+                                    kind: Bare,
+                                    had_escape: false },
+                            Token { value: "b c".into_string(),
+                                    kind: SingleQuoted,
+                                    had_escape: false },
+                            Token { value: "d e".into_string(),
+                                    kind: Escaped,
+                                    had_escape: true } ]));
+    }
+
+    #[test]
+    fn tokens_kinds_custom_and_folding() {
+        assert_eq!(kinds(custom_quote_tokeniser(), "`a\\`b` \"c\""),
+                   Ok(vec![ DoubleQuoted, DoubleQuoted ]));
+        assert_eq!(kinds(folding_tokeniser(), "\"a  b\" x\\ny"),
+                   Ok(vec![ DoubleQuoted, Escaped ]));
+    }
+
+    #[test]
+    fn tokens_kinds_after_drain() {
+        let mut tok = shell_style_tokeniser();
+        tok.push_str("'a' b \"c");
+        tok.drain_completed();
+        assert_eq!(tok.add_string("\" d").into_tokens(),
+                   Ok(vec![ Token { value: "c".into_string(),
+                                    kind: DoubleQuoted,
+                                    had_escape: false },
+                            Token { value: "d".into_string(),
+                                    kind: Bare,
+                                    had_escape: false } ]));
+    }
+
+    #[test]
+    fn tokens_error() {
+        assert_eq!(kinds(shell_style_tokeniser(), "a 'b"),
+                   Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn tokens_flags() {
+        let line = "a \"b\" c\\ d foo\"bar\" *";
+        let tok = shell_style_tokeniser().add_line(line);
+        let flags: Result<Vec<( bool, bool )>, super::Error> =
+            tok.into_tokens().map(|ts| {
+                ts.iter().map(|t| ( t.was_quoted(), t.had_escape )).collect()
+            });
+        assert_eq!(flags, Ok(vec![ ( false, false ),
+                                   ( true,  false ),
+                                   ( false, true  ),
+                                   ( true,  false ),
+                                   ( false, false ) ]));
     }
 
-    /// Switches on escape mode.
-    /// This automatically sets the Tokeniser to be in a word, if it isn't
-    /// already.
-    fn start_escaping(&mut self, c: char) {
-        self.escape = self.escape_map.find(&c).map(|a| a.clone());
-        self.in_word = true;
+    #[test]
+    fn tokens_escapes_in_quotes() {
+        let tok = custom_quote_tokeniser().add_line("\"a\\nb\" `c\\`d` \"e\"");
+        let escapes: Result<Vec<bool>, super::Error> =
+            tok.into_tokens().map(|ts| {
+                ts.iter().map(|t| t.had_escape).collect()
+            });
+        assert_eq!(escapes, Ok(vec![ true, true, false ]));
     }
 
-    /// Drops the current working string, if it is empty.
-    fn drop_empty_current_string(&mut self) {
-        if self.vec.last().map(|s| s.is_empty()).unwrap_or(false) {
-            self.vec.pop();
+    #[test]
+    fn char_spans() {
+        let tok = c_style_tokeniser().add_line("é \"ü v\" \\x41ö \"\\n\"x");
+        assert_eq!(tok.into_char_spans(),
+                   Ok(vec![ spanned("é", 0, 1),
+                            spanned("ü v", 2, 7),
+                            spanned("Aö", 8, 13),
+                            spanned("\nx", 14, 19) ]));
+    }
+
+    #[test]
+    fn char_spans_after_drain() {
+        let mut tok = shell_style_tokeniser();
+        tok.push_str("ab 'é");
+        tok.drain_completed();
+        tok.push_str("f' ü");
+        assert_eq!(tok.into_char_spans(),
+                   Ok(vec![ spanned("éf", 3, 7), spanned("ü", 8, 9) ]));
+    }
+
+    #[test]
+    fn whitespace_normalised() {
+        let line = "\"a\u00A0\u3000b\" c\\\u2028d \"e\tf\"";
+        let tok = shell_style_tokeniser().add_line(line);
+        assert_eq!(tok.into_strings_with_whitespace_normalised(),
+                   Ok(vec![ "a  b".into_string(),
+                            "c d".into_string(),
+                            "e\tf".into_string() ]));
+    }
+
+    #[test]
+    fn whitespace_normalised_error() {
+        let tok = shell_style_tokeniser().add_line("'a\u00A0");
+        assert_eq!(tok.into_strings_with_whitespace_normalised(),
+                   Err(UnmatchedQuote(0)));
+    }
+
+    #[test]
+    fn continuation_check() {
+        let check = |line: &str| {
+            let ( _, more ) =
+                shell_style_tokeniser().add_line_with_continuation_check(line);
+            more
+        };
+        assert!(!check("echo a b"));
+        assert!(check("echo 'a"));
+        assert!(check("echo \"a\\\" b"));
+        assert!(check("echo a\\"));
+        assert!(!check("echo 'a' \\\\"));
+    }
+
+    #[test]
+    fn continuation_check_failed() {
+        let ( tok, more ) =
+            c_style_tokeniser().add_line_with_continuation_check("\"\\q");
+        assert!(!more);
+        assert_eq!(tok.into_strings(), Err(BadEscape));
+    }
+
+    #[test]
+    fn into_strings_inspect_errors() {
+        let mut seen = vec![];
+        assert_eq!(shell_style_tokeniser().add_line("a 'b")
+                                          .into_strings_inspect_errors(|e| {
+                                              seen.push(e.clone())
+                                          }),
+                   Err(UnmatchedQuote(2)));
+        assert_eq!(shell_style_tokeniser().add_line("a b")
+                                          .into_strings_inspect_errors(|e| {
+                                              seen.push(e.clone())
+                                          }),
+                   Ok(vec![ "a".into_string(), "b".into_string() ]));
+        assert_eq!(seen, vec![ UnmatchedQuote(2) ]);
+    }
+
+    #[test]
+    fn current_token_started_at() {
+        let mut tok = shell_style_tokeniser();
+        assert_eq!(tok.current_token_started_at(), None);
+        tok.push_str("ab ");
+        assert_eq!(tok.current_token_started_at(), None);
+        tok.push_str("\"c");
+        assert_eq!(tok.current_token_started_at(), Some(3));
+        tok.push_str(" d\" e");
+        assert_eq!(tok.current_token_started_at(), Some(9));
+        assert_eq!(tok.chars_fed(), 10);
+    }
+
+    /// Builds a shell-style Tokeniser ending statements at newlines and
+    /// semicolons.
+    fn statement_tokeniser() -> StockTokeniser {
+        shell_style_tokeniser().with_statement_terminators(vec![ '\n', ';' ])
+    }
+
+    #[test]
+    fn into_statements_quoted_newline() {
+        let tok = statement_tokeniser().add_string("a b\nc \"d\ne\"");
+        assert_eq!(tok.into_statements(),
+                   Ok(vec![ vec![ "a".into_string(), "b".into_string() ],
+                            vec![ "c".into_string(),
+                                  "d\ne".into_string() ] ]));
+    }
+
+    #[test]
+    fn into_statements_separators() {
+        let tok = statement_tokeniser().add_string("\n a;b\\;c ';' ;;\n\nd;");
+        assert_eq!(tok.into_statements(),
+                   Ok(vec![ vec![ "a".into_string() ],
+                            vec![ "b;c".into_string(), ";".into_string() ],
+                            vec![ "d".into_string() ] ]));
+    }
+
+    #[test]
+    fn into_statements_comments() {
+        let tok = statement_tokeniser().with_comment_chars(vec![ '#' ])
+                                       .add_string("a # b; c\nd");
+        assert_eq!(tok.into_statements(),
+                   Ok(vec![ vec![ "a".into_string() ],
+                            vec![ "d".into_string() ] ]));
+    }
+
+    #[test]
+    fn into_statements_without_terminators() {
+        let tok = shell_style_tokeniser().add_string("a\nb; c");
+        assert_eq!(tok.into_statements(),
+                   Ok(vec![ vec![ "a".into_string(),
+                                  "b;".into_string(),
+                                  "c".into_string() ] ]));
+        assert_eq!(statement_tokeniser().add_string("a 'b").into_statements(),
+                   Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn into_strings_annotated() {
+        let mut calls = 0u;
+        let tok = shell_style_tokeniser().add_line("a 'b c'");
+        assert_eq!(tok.into_strings_annotated(|s| { calls += 1; s.len() }),
+                   Ok(vec![ ( "a".into_string(), 1 ),
+                            ( "b c".into_string(), 3 ) ]));
+        assert_eq!(calls, 2);
+        assert_eq!(shell_style_tokeniser().add_line("a 'b")
+                                          .into_strings_annotated(|_| ()),
+                   Err(UnmatchedQuote(2)));
+    }
+
+    /// Builds a shell-style Tokeniser splitting on colons and keeping empty
+    /// fields.
+    fn passwd_tokeniser() -> StockTokeniser {
+        shell_style_tokeniser().with_separators(SeparatorChars(vec![ ':' ]))
+                               .with_preserve_empty_fields(true)
+    }
+
+    #[test]
+    fn preserve_empty_fields_spans() {
+        let tok = passwd_tokeniser().add_line("ab::c");
+        assert_eq!(tok.into_spans(),
+                   Ok(vec![ ( "ab".into_string(), Span { start: 0, end: 2 } ),
+                            ( "".into_string(), Span { start: 3, end: 3 } ),
+                            ( "c".into_string(),
+                              Span { start: 4, end: 5 } ) ]));
+    }
+
+    #[test]
+    fn doubled_escape() {
+        let quote_map: StockQuoteMap =
+            vec![ ( '\"', ( '\"', DoubledEscape ) ) ].move_iter().collect();
+        let tok = Tokeniser::new(quote_map, HashMap::new());
+        assert_eq!(tok.clone().add_line("\"a\"\"b\" \"\"\"\"c").into_strings(),
+                   Ok(vec![ "a\"b".into_string(), "\"c".into_string() ]));
+        assert_eq!(tok.with_keep_delimiters(true)
+                      .add_line("\"a\"\"b\"")
+                      .into_strings(),
+                   Ok(vec![ "\"a\"\"b\"".into_string() ]));
+    }
+
+    #[test]
+    fn empty_quoted_words() {
+        let empty = "".into_string();
+        assert_eq!(c_style_tokeniser.line("\"\" a"),
+                   Ok(vec![ empty.clone(), "a".into_string() ]));
+        assert_eq!(c_style_tokeniser.line("set \"\" title"),
+                   Ok(vec![ "set".into_string(), empty.clone(),
+                            "title".into_string() ]));
+        assert_eq!(c_style_tokeniser.line("set title \"\""),
+                   Ok(vec![ "set".into_string(), "title".into_string(),
+                            empty.clone() ]));
+        assert_eq!(c_style_tokeniser.line("\"\""), Ok(vec![ empty.clone() ]));
+        assert_eq!(shell_style_tokeniser.line("a '' b ''"),
+                   Ok(vec![ "a".into_string(), empty.clone(),
+                            "b".into_string(), empty.clone() ]));
+        assert_eq!(shell_style_tokeniser.line("''\"\""),
+                   Ok(vec![ empty.clone() ]));
+    }
+
+    #[test]
+    fn empty_quoted_word_token() {
+        let tok = shell_style_tokeniser().add_line("a ''");
+        assert_eq!(tok.into_tokens(),
+                   Ok(vec![ Token { value: "a".into_string(),
+                                    kind: Bare,
+                                    had_escape: false },
+                            Token { value: "".into_string(),
+                                    kind: SingleQuoted,
+                                    had_escape: false } ]));
+    }
+
+    /// Builds a Policy forbidding bare `;`, `|` and `$(`, and control
+    /// character escapes.
+    fn strict_policy() -> Policy {
+        Policy { forbidden_bare: vec![ ";".into_string(), "|".into_string(),
+                                       "$(".into_string() ],
+                 forbid_control_escapes: true,
+                 ..Default::default() }
+    }
+
+    #[test]
+    fn check_policy_forbidden_bare() {
+        let violation = shell_style_tokeniser()
+            .check_policy("ls; rm -rf /", &strict_policy())
+            .unwrap_err();
+        assert_eq!(violation.rule, ForbiddenBare(";".into_string()));
+        assert_eq!(violation.span, Span { start: 2, end: 3 });
+
+        assert_eq!(shell_style_tokeniser().check_policy("echo ';' a\\|b",
+                                                        &strict_policy()),
+                   Ok(vec![ "echo".into_string(), ";".into_string(),
+                            "a|b".into_string() ]));
+    }
+
+    #[test]
+    fn check_policy_forbidden_bare_string() {
+        let violation = shell_style_tokeniser()
+            .check_policy("echo $(whoami)", &strict_policy())
+            .unwrap_err();
+        assert_eq!(violation.rule, ForbiddenBare("$(".into_string()));
+        assert_eq!(violation.span, Span { start: 5, end: 7 });
+
+        // Only the whole string is forbidden, and only when all of it is
+        // bare; forbidding `$` alone would reject these too.
+        assert_eq!(shell_style_tokeniser().check_policy("echo $HOME '$(' $\(",
+                                                        &strict_policy()),
+                   Ok(vec![ "echo".into_string(), "$HOME".into_string(),
+                            "$(".into_string(), "$(".into_string() ]));
+    }
+
+    #[test]
+    fn check_policy_control_escape() {
+        let violation = c_style_tokeniser()
+            .check_policy("echo \"a\\x07\"", &strict_policy())
+            .unwrap_err();
+        assert_eq!(violation.rule, ControlEscape('\x07'));
+        assert_eq!(violation.span, Span { start: 7, end: 11 });
+
+        assert!(c_style_tokeniser().check_policy("echo \"a\\x07\"",
+                                                 &Default::default())
+                                   .is_ok());
+    }
+
+    #[test]
+    fn check_policy_control_escape_at_end() {
+        let quote_map: StockQuoteMap = HashMap::new();
+        let escape_map: StockEscapeMap =
+            vec![ ( '\\', posix_escapes() ) ].move_iter().collect();
+        let tok = Tokeniser::new(quote_map, escape_map);
+
+        for line in [ "echo \\7", "echo \\07" ].iter() {
+            let violation = tok.check_policy(*line, &strict_policy())
+                               .unwrap_err();
+            assert_eq!(violation.rule, ControlEscape('\x07'));
+            assert_eq!(violation.span,
+                       Span { start: 5, end: line.char_len() });
+        }
+
+        assert_eq!(tok.check_policy("echo \\101", &strict_policy()),
+                   Ok(vec![ "echo".into_string(), "A".into_string() ]));
+    }
+
+    #[test]
+    fn check_policy_limits() {
+        let policy = Policy { max_words: Some(2),
+                              max_word_len: Some(4),
+                              ..Default::default() };
+        let tok = shell_style_tokeniser();
+
+        let violation = tok.check_policy("a b c", &policy).unwrap_err();
+        assert_eq!(violation.rule, TooManyWords(2));
+        assert_eq!(violation.span, Span { start: 4, end: 5 });
+
+        let violation = tok.check_policy("a 'bcdef'", &policy).unwrap_err();
+        assert_eq!(violation.rule, WordTooLong(4));
+        assert_eq!(violation.span, Span { start: 2, end: 9 });
+
+        assert_eq!(tok.check_policy("a 'b", &policy).unwrap_err().rule,
+                   Malformed(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn check_policy_clean_line() {
+        assert_eq!(shell_style_tokeniser().check_policy("  git log -n 3 ",
+                                                        &strict_policy()),
+                   Ok(vec![ "git".into_string(), "log".into_string(),
+                            "-n".into_string(), "3".into_string() ]));
+    }
+
+    #[test]
+    fn into_strings_to_arc() {
+        let tok = shell_style_tokeniser().add_line("a 'b c' a");
+        let words = tok.into_strings_to_arc().unwrap();
+        let values: Vec<&str> = words.iter().map(|w| w.as_slice()).collect();
+        assert_eq!(values, vec![ "a", "b c", "a" ]);
+        assert!(&**words.get(0) as *const String
+                != &**words.get(2) as *const String);
+
+        assert_eq!(shell_style_tokeniser().add_line("'a")
+                                          .into_strings_to_arc()
+                                          .err(),
+                   Some(UnmatchedQuote(0)));
+    }
+
+    /// Converts string slices into Strings.
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.into_string()).collect()
+    }
+
+    #[test]
+    fn preserve_empty_fields_leading_and_trailing() {
+        let line = |l: &str| passwd_tokeniser().add_line(l).into_strings();
+        assert_eq!(line(":a"), Ok(strings([ "", "a" ])));
+        assert_eq!(line("a:"), Ok(strings([ "a", "" ])));
+        assert_eq!(line(":"), Ok(strings([ "", "" ])));
+        assert_eq!(line(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn preserve_empty_fields_runs() {
+        let line = |l: &str| passwd_tokeniser().add_line(l).into_strings();
+        assert_eq!(line("a:::b"), Ok(strings([ "a", "", "", "b" ])));
+        assert_eq!(line(":::"), Ok(strings([ "", "", "", "" ])));
+        assert_eq!(line("root:x:0:0::/root:/bin/sh"),
+                   Ok(strings([ "root", "x", "0", "0", "", "/root",
+                                "/bin/sh" ])));
+    }
+
+    #[test]
+    fn preserve_empty_fields_quoted_empty() {
+        let line = |l: &str| passwd_tokeniser().add_line(l).into_strings();
+        assert_eq!(line("a:'':b"), Ok(strings([ "a", "", "b" ])));
+        assert_eq!(line("'':\"\""), Ok(strings([ "", "" ])));
+        assert_eq!(line("''"), Ok(strings([ "" ])));
+        assert_eq!(line("a'':b"), Ok(strings([ "a", "b" ])));
+    }
+
+    #[test]
+    fn preserve_empty_fields_off() {
+        let tok = shell_style_tokeniser()
+            .with_separators(SeparatorChars(vec![ ':' ]));
+        assert_eq!(tok.add_line(":a:::b:").into_strings(),
+                   Ok(strings([ "a", "b" ])));
+    }
+
+    #[test]
+    fn preserve_empty_fields_streaming() {
+        let tokens = passwd_tokeniser().tokens("a::b:".chars(), Unbounded);
+        let words: Vec<String> = tokens.map(|w| w.unwrap()).collect();
+        assert_eq!(words, strings([ "a", "", "b", "" ]));
+    }
+
+    /// The builders the agreement property picks from, with their names.
+    static AGREEMENT_BUILDERS: [( &'static str,
+                                  fn() -> StockTokeniser ), ..7] = [
+        ( "whitespace_split_tokeniser", whitespace_split_tokeniser ),
+        ( "shell_style_tokeniser", shell_style_tokeniser ),
+        ( "shell_style_tokeniser_with_comments",
+          shell_style_tokeniser_with_comments ),
+        ( "c_style_tokeniser", c_style_tokeniser ),
+        ( "csv_tokeniser", csv_tokeniser ),
+        ( "colon_split_tokeniser", colon_split_tokeniser ),
+        ( "tab_split_tokeniser", tab_split_tokeniser )
+    ];
+
+    /// The characters the agreement property's inputs are made of.
+    ///
+    /// Random Strings rarely contain quotes or escapes, so inputs are drawn
+    /// from the characters the builders treat specially, plus a few others.
+    static AGREEMENT_ALPHABET: [char, ..15] = [
+        ' ', 'a', 'b', 'n', 'x', '4', '\'', '"', '\\', ',', ':', '#', '\n',
+        'é', '\t'
+    ];
+
+    /// A tokeniser configuration and input for the agreement property.
+    #[deriving(Clone)]
+    struct AgreementCase {
+        builder: uint,
+        keep_delimiters: bool,
+        preserve_empty_fields: bool,
+        input: Vec<uint>,
+        chunk: uint
+    }
+
+    impl AgreementCase {
+        /// Builds the case from the parts quickcheck generates and shrinks.
+        fn from_parts(( ( builder, keep_delimiters, preserve_empty_fields ),
+                        input,
+                        chunk ): ( ( uint, bool, bool ), Vec<uint>, uint ))
+          -> AgreementCase {
+            AgreementCase {
+                builder: builder % AGREEMENT_BUILDERS.len(),
+                keep_delimiters: keep_delimiters,
+                preserve_empty_fields: preserve_empty_fields,
+                input: input,
+                chunk: chunk
+            }
+        }
+
+        /// Splits the case into the parts quickcheck generates and shrinks.
+        fn to_parts(&self) -> ( ( uint, bool, bool ), Vec<uint>, uint ) {
+            ( ( self.builder, self.keep_delimiters,
+                self.preserve_empty_fields ),
+              self.input.clone(),
+              self.chunk )
+        }
+
+        /// Builds the tokeniser the case describes.
+        fn tokeniser(&self) -> StockTokeniser {
+            let ( _, builder ) = AGREEMENT_BUILDERS[self.builder];
+            builder().with_keep_delimiters(self.keep_delimiters)
+                     .with_preserve_empty_fields(self.preserve_empty_fields)
+        }
+
+        /// Builds the input the case describes.
+        fn input(&self) -> String {
+            let len = AGREEMENT_ALPHABET.len();
+            self.input.iter().map(|&i| AGREEMENT_ALPHABET[i % len]).collect()
+        }
+
+        /// Retrieves the size, at least one, of the chunks the streaming
+        /// paths are fed.
+        fn chunk_size(&self) -> uint {
+            self.chunk % 8 + 1
+        }
+    }
+
+    /// Prints the case as the builder expression that reproduces it.
+    impl fmt::Show for AgreementCase {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let ( name, _ ) = AGREEMENT_BUILDERS[self.builder];
+            write!(f, "{}().with_keep_delimiters({})\
+                         .with_preserve_empty_fields({})\
+                         .add_string(\"{}\") (chunks of {})",
+                   name, self.keep_delimiters, self.preserve_empty_fields,
+                   self.input().escape_default(), self.chunk_size())
+        }
+    }
+
+    impl Arbitrary for AgreementCase {
+        fn arbitrary<G: Gen>(g: &mut G) -> AgreementCase {
+            AgreementCase::from_parts(Arbitrary::arbitrary(g))
+        }
+
+        fn shrink(&self) -> Box<Shrinker<AgreementCase>+'static> {
+            box AgreementShrinker { parts: self.to_parts().shrink() }
+                as Box<Shrinker<AgreementCase>+'static>
+        }
+    }
+
+    /// Shrinks an AgreementCase by shrinking its parts.
+    struct AgreementShrinker {
+        parts: Box<Shrinker<( ( uint, bool, bool ), Vec<uint>, uint )>+'static>
+    }
+
+    impl Shrinker<AgreementCase> for AgreementShrinker {
+        fn next_shrink(&mut self) -> Option<AgreementCase> {
+            self.parts.next_shrink().map(AgreementCase::from_parts)
+        }
+    }
+
+    /// Collects the words yielded by an Iterator, stopping at any Error.
+    fn collect_words<I: Iterator<Result<String, super::Error>>>(mut it: I)
+      -> Result<Vec<String>, super::Error> {
+        let mut words = vec![];
+        for item in it {
+            words.push(try!(item));
+        }
+        Ok(words)
+    }
+
+    /// Reads the words of an agreement case through each extraction path,
+    /// giving each path's name and result.
+    fn extractions(case: &AgreementCase)
+      -> Vec<( &'static str, Result<Vec<String>, super::Error> )> {
+        let input = case.input();
+        let chars: Vec<char> = input.as_slice().chars().collect();
+        let size = case.chunk_size();
+        let mut results = vec![];
+
+        results.push(( "into_strings",
+                       case.tokeniser().add_string(input.as_slice())
+                                       .into_strings() ));
+
+        let mut fed = case.tokeniser();
+        fed.feed_str(input.as_slice());
+        results.push(( "feed_str", fed.into_strings() ));
+
+        let reader = MemReader::new(input.as_bytes().to_vec());
+        results.push(( "add_reader",
+                       case.tokeniser().add_reader(reader, Unbounded)
+                                       .unwrap()
+                                       .into_strings() ));
+
+        let mut reader = MemReader::new(input.as_bytes().to_vec());
+        results.push(( "add_read",
+                       case.tokeniser().add_read(&mut reader, Unbounded)
+                                       .unwrap()
+                                       .into_strings() ));
+
+        results.push(( "into_iter",
+                       collect_words(case.tokeniser()
+                                         .add_string(input.as_slice())
+                                         .into_iter()) ));
+
+        results.push(( "tokens",
+                       collect_words(case.tokeniser()
+                                         .tokens(input.as_slice().chars(),
+                                                 Unbounded)) ));
+
+        let tokens = case.tokeniser().add_string(input.as_slice())
+                                     .into_tokens();
+        results.push(( "into_tokens",
+                       tokens.map(|ts| ts.move_iter()
+                                         .map(|t| t.value)
+                                         .collect()) ));
+
+        let spans = case.tokeniser().add_string(input.as_slice())
+                                    .into_spans();
+        results.push(( "into_spans",
+                       spans.map(|ss| ss.move_iter()
+                                        .map(|( word, _ )| word)
+                                        .collect()) ));
+
+        let spans = case.tokeniser().add_string(input.as_slice())
+                                    .into_char_spans();
+        results.push(( "into_char_spans",
+                       spans.map(|ss| ss.move_iter()
+                                        .map(|( word, _ )| word)
+                                        .collect()) ));
+
+        results.push(( "fold_words",
+                       case.tokeniser()
+                           .add_string(input.as_slice())
+                           .fold_words(vec![], |mut ws, w| {
+                               ws.push(w);
+                               ws
+                           }) ));
+
+        // The default Policy allows everything, so check_policy should only
+        // fail on malformed input.
+        let checked = case.tokeniser().check_policy(input.as_slice(),
+                                                    &Default::default());
+        results.push(( "check_policy",
+                       checked.map_err(|v| match v.rule {
+                           Malformed(e) => e,
+                           rule => fail!("unexpected violation {}", rule)
+                       }) ));
+
+        let far = precise_time_ns() + 60000000000;
+        results.push(( "tokenise_with_deadline",
+                       case.tokeniser().tokenise_with_deadline(input.as_slice(),
+                                                               far) ));
+
+        // tokenise_into_sink reads a line, so give it a line ending to
+        // remove.
+        let mut line = input.clone();
+        line.push_char('\n');
+        let mut sunk = vec![];
+        let sink_result = case.tokeniser().tokenise_into_sink(line.as_slice(),
+                                                              &mut sunk);
+        results.push(( "tokenise_into_sink", sink_result.and(Ok(sunk)) ));
+
+        let mut drained = case.tokeniser();
+        let mut words = vec![];
+        for chunk in chars.as_slice().chunks(size) {
+            for &chr in chunk.iter() {
+                drained.push_char(chr);
+            }
+            words.push_all_move(drained.drain_completed());
+        }
+        results.push(( "drain_completed",
+                       drained.into_strings().map(|rest| {
+                           words.push_all_move(rest);
+                           words
+                       }) ));
+
+        let mut chunked = ChunkedTokeniser::new(case.tokeniser(), Unbounded);
+        let mut items = vec![];
+        for chunk in input.as_bytes().chunks(size) {
+            items.push_all_move(chunked.feed(chunk));
+        }
+        match chunked.finish() {
+            Ok(rest) => items.push_all_move(rest.move_iter()
+                                                .map(|w| Ok(w))
+                                                .collect()),
+            Err(e)   => items.push(Err(e))
+        }
+        results.push(( "chunked", collect_words(items.move_iter()) ));
+
+        // words() fails the task on an error, so it can only be compared
+        // when the words are valid.
+        let valid = match *results.get(0) {
+            ( _, Ok(..) ) => true,
+            _             => false
+        };
+        if valid {
+            let words = case.tokeniser().add_string(input.as_slice()).words();
+            results.push(( "words", Ok(words.collect()) ));
+        }
+
+        results
+    }
+
+    /// Every extraction path should read the same words, or fail with the
+    /// same Error, whatever the configuration, input and chunking.
+    fn extraction_paths_agree(case: AgreementCase) -> bool {
+        let results = extractions(&case);
+        let ( _, ref expected ) = *results.get(0);
+
+        results.iter().all(|&( _, ref result )| result == expected)
+    }
+
+    /// Runs `extraction_paths_agree` as many times as the
+    /// RUSSET_AGREEMENT_TESTS environment variable asks, or 100 times.
+    #[test]
+    fn extraction_paths_agree_on_random_cases() {
+        let tests = os::getenv("RUSSET_AGREEMENT_TESTS")
+                        .and_then(|n| from_str(n.as_slice()))
+                        .unwrap_or(100u);
+        QuickCheck::new().tests(tests)
+                         .max_tests(tests * 10)
+                         .quickcheck(extraction_paths_agree
+                                     as fn(AgreementCase) -> bool);
+    }
+
+    #[test]
+    fn extraction_paths_agree_on_edge_cases() {
+        // Indices into AGREEMENT_ALPHABET: 0 is a space, 1 is 'a', 6 a
+        // single quote, 7 a double quote, 8 a backslash, 9 a comma and 12
+        // a newline.
+        let inputs = [ vec![], vec![ 0 ], vec![ 6, 6 ], vec![ 7, 1 ],
+                       vec![ 1, 8 ], vec![ 9, 9, 1 ], vec![ 7, 7, 7, 7 ],
+                       vec![ 1, 8, 12, 1 ] ];
+        for builder in range(0, AGREEMENT_BUILDERS.len()) {
+            for input in inputs.iter() {
+                for &preserve in [ false, true ].iter() {
+                    let case = AgreementCase {
+                        builder: builder,
+                        keep_delimiters: false,
+                        preserve_empty_fields: preserve,
+                        input: input.clone(),
+                        chunk: 0
+                    };
+                    assert!(extraction_paths_agree(case.clone()),
+                            "paths disagree on {}", case);
+                }
+            }
         }
     }
+
+    /// A map without the leader mapped to itself cannot escape the leader:
+    /// `\\` is a bad escape, in or out of quotes, rather than losing a
+    /// character.
+    #[test]
+    fn leader_without_self_mapping_is_bad_escape() {
+        let line = |l: &str| matrix_tokeniser().add_line(l).into_strings();
+        assert_eq!(line("a\\\\b"), Err(BadEscape));
+        assert_eq!(line("(a\\\\b)"), Err(BadEscape));
+        assert_eq!(line("<a\\\\b>"), Ok(vec![ "a\\\\b".into_string() ]));
+        assert_eq!(line("(a\\nb)"), Ok(vec![ "a\nb".into_string() ]));
+    }
+
+    /// Creates a shell-style Tokeniser that splits adjacent quotes.
+    fn split_tokeniser() -> StockTokeniser {
+        shell_style_tokeniser().with_split_adjacent_quotes(true)
+    }
+
+    #[test]
+    fn split_adjacent_quotes() {
+        let line = |l: &str| split_tokeniser().add_line(l).into_strings();
+        assert_eq!(line("\"a\"'b'c"), Ok(strings([ "a", "b", "c" ])));
+        assert_eq!(line("x\"y\""), Ok(strings([ "x", "y" ])));
+        assert_eq!(line("x\"y\"z w"), Ok(strings([ "x", "y", "z", "w" ])));
+        assert_eq!(line("''\"\""), Ok(strings([ "", "" ])));
+        assert_eq!(line("\"a\"\\ b"), Ok(strings([ "a", " b" ])));
+        assert_eq!(line("a\\\"b"), Ok(strings([ "a\"b" ])));
+    }
+
+    #[test]
+    fn split_adjacent_quotes_spans_and_kinds() {
+        let tok = split_tokeniser().add_line("x\"y\"z");
+        assert_eq!(tok.clone().into_spans(),
+                   Ok(vec![ ( "x".into_string(), Span { start: 0, end: 1 } ),
+                            ( "y".into_string(), Span { start: 1, end: 4 } ),
+                            ( "z".into_string(),
+                              Span { start: 4, end: 5 } ) ]));
+        let kinds: Vec<TokenKind> =
+            tok.into_tokens().unwrap().iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, vec![ Bare, DoubleQuoted, Bare ]);
+    }
+
+    #[test]
+    fn split_adjacent_quotes_keeps_doubled_quotes() {
+        let tok = csv_tokeniser().with_split_adjacent_quotes(true)
+                                 .add_string("\"a\"\"b\"c,d");
+        assert_eq!(tok.into_strings(), Ok(strings([ "a\"b", "c", "d" ])));
+    }
+
+    /// By default, adjacent quoted parts make up one word; when splitting
+    /// adjacent quotes, each is a word of its own.
+    #[quickcheck]
+    fn adjacent_quotes_join_unless_split(a: String, b: String) -> bool {
+        let mut line = shell_quote(a.as_slice());
+        line.push_str(shell_quote(b.as_slice()).as_slice());
+
+        let joined = shell_style_tokeniser().add_line(line.as_slice())
+                                            .into_strings();
+        let split = split_tokeniser().add_line(line.as_slice())
+                                     .into_strings();
+
+        // shell_quote writes a single quote outside quotes, which splits.
+        let quoteless = !a.as_slice().contains_char('\'')
+                        && !b.as_slice().contains_char('\'');
+        joined == Ok(vec![ a.clone().append(b.as_slice()) ])
+            && ( !quoteless || split == Ok(vec![ a, b ]) )
+    }
+
+    #[test]
+    fn into_strings_to_path_components() {
+        let tok = colon_split_tokeniser().add_line("/usr/bin::a/../b:");
+        assert_eq!(tok.into_strings_to_path_components(),
+                   Ok(vec![ Path::new("/usr/bin"), Path::new("b") ]));
+
+        let bad = colon_split_tokeniser().add_line("/bin:'/usr");
+        assert_eq!(bad.into_strings_to_path_components(),
+                   Err(UnmatchedQuote(5)));
+    }
+
+    #[test]
+    fn into_strings_to_path_components_nul() {
+        let tok = c_style_tokeniser().add_line("/bin \"/usr\\x00/bin\"");
+        assert_eq!(tok.into_strings_to_path_components(),
+                   Err(NulInPath(1)));
+    }
+
+    /// Creates a shell-style Tokeniser reading quotes only at word starts.
+    fn apostrophe_tokeniser() -> StockTokeniser {
+        shell_style_tokeniser().with_before_quote(LiteralQuoteInWord)
+    }
+
+    #[test]
+    fn literal_quote_in_word() {
+        let line = |l: &str| apostrophe_tokeniser().add_line(l).into_strings();
+        assert_eq!(line("don't panic"), Ok(strings([ "don't", "panic" ])));
+        assert_eq!(shell_style_tokeniser().add_line("don't panic")
+                                          .into_strings(),
+                   Err(UnmatchedQuote(3)));
+
+        assert_eq!(line("\"quoted\" don't"),
+                   Ok(strings([ "quoted", "don't" ])));
+        assert_eq!(line("'O''Brien' a\"b\""),
+                   Ok(strings([ "O'Brien'", "a\"b\"" ])));
+        assert_eq!(line("x 'y"), Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn literal_quote_in_word_is_bare() {
+        let tokens = apostrophe_tokeniser().add_line("O'Brien 'a b'")
+                                           .into_tokens()
+                                           .unwrap();
+        let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, vec![ Bare, SingleQuoted ]);
+    }
+
+    #[test]
+    fn deadline_far_away_matches_into_strings() {
+        let line = "a 'b c' \"d\\\"e\" f\\ g";
+        let far = precise_time_ns() + 60000000000;
+        assert_eq!(shell_style_tokeniser().tokenise_with_deadline(line, far),
+                   shell_style_tokeniser().add_string(line).into_strings());
+        assert_eq!(shell_style_tokeniser().tokenise_with_deadline("'a", far),
+                   Err(UnmatchedQuote(0)));
+    }
+
+    #[test]
+    fn deadline_passed_reads_nothing() {
+        let tok = shell_style_tokeniser().add_line("already read");
+        assert_eq!(tok.tokenise_with_deadline("a b", 0),
+                   Err(DeadlineExceeded(0)));
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "already".into_string(), "read".into_string() ]));
+    }
+
+    #[test]
+    fn deadline_stops_slow_configuration() {
+        // A clock advancing a millisecond per check, as if each character
+        // took that long to read, so the clock is checked every character.
+        let line = String::from_char(200, 'a');
+        let mut now = 0u64;
+        let result = whitespace_split_tokeniser().tokenise_with_clock(
+            line.as_slice(), 20000000, || { now += 1000000; now });
+        assert_eq!(result, Err(DeadlineExceeded(19)));
+    }
+
+    #[test]
+    fn deadline_checks_rarely_when_fast() {
+        // A clock advancing a nanosecond per check, as if reading took no
+        // time, so the checks should spread out to a small fraction of the
+        // characters read.
+        let line = bench_line();
+        let mut checks = 0u;
+        let result = shell_style_tokeniser().tokenise_with_clock(
+            line.as_slice(), 60000000000, || { checks += 1; checks as u64 });
+        assert_eq!(result,
+                   shell_style_tokeniser().add_string(line.as_slice())
+                                          .into_strings());
+        assert!(checks * 100 < line.as_slice().char_len());
+    }
+
+    /// A long line of shell-style words, for the deadline benchmarks.
+    fn bench_line() -> String {
+        String::from_str("word 'quoted words' \"more\\\" words\" esc\\ aped ")
+            .repeat(200)
+    }
+
+    #[bench]
+    fn bench_without_deadline(b: &mut Bencher) {
+        let line = bench_line();
+        b.iter(|| {
+            shell_style_tokeniser().add_string(line.as_slice()).into_strings()
+        });
+    }
+
+    /// Checking the clock should cost little against the benchmark above.
+    #[bench]
+    fn bench_with_far_deadline(b: &mut Bencher) {
+        let line = bench_line();
+        let tok = shell_style_tokeniser();
+        b.iter(|| {
+            let far = precise_time_ns() + 60000000000;
+            tok.tokenise_with_deadline(line.as_slice(), far)
+        });
+    }
+
+    #[test]
+    fn add_read_file() {
+        let script = "cd \"$HOME/my files\"\n\
+                      grep -r 'TODO: fix' src \\\n    | sort -u\n\
+                      echo \"café\" done\n";
+        let dir = TempDir::new("russet").unwrap();
+        let path = dir.path().join("script.sh");
+        File::create(&path).write_str(script).unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        let tok = shell_style_tokeniser().add_read(&mut file, Unbounded)
+                                         .unwrap();
+        assert_eq!(tok.into_strings(),
+                   shell_style_tokeniser().add_line(script).into_strings());
+    }
+
+    #[test]
+    fn add_read_across_chunks() {
+        // The line has two-byte characters at odd offsets, so some are split
+        // between 4096-byte reads.
+        let line = "éé a ".repeat(1500);
+        let mut reader = MemReader::new(line.as_bytes().to_vec());
+        assert_eq!(shell_style_tokeniser().add_read(&mut reader, Unbounded)
+                                          .unwrap()
+                                          .into_strings(),
+                   shell_style_tokeniser().add_string(line.as_slice())
+                                          .into_strings());
+    }
+
+    #[test]
+    fn add_read_errors() {
+        let mut reader = MemReader::new(vec![ b'a', 0xFF, b'b' ]);
+        assert!(shell_style_tokeniser().add_read(&mut reader, Unbounded)
+                                       .is_err());
+
+        let mut reader = MemReader::new(b"a 'b".to_vec());
+        assert_eq!(shell_style_tokeniser().add_read(&mut reader, Unbounded)
+                                          .unwrap()
+                                          .into_strings(),
+                   Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn add_read_bounded() {
+        let mut reader = MemReader::new(b"ab 'cdefgh ij".to_vec());
+        assert_eq!(shell_style_tokeniser().add_read(&mut reader, Bounded(4))
+                                          .unwrap()
+                                          .into_strings(),
+                   Err(LimitExceeded(4)));
+    }
+
+    #[test]
+    fn into_strings_sort_key() {
+        let numeric = |l: &str| {
+            whitespace_split_tokeniser().add_line(l).into_strings_sort_key(
+                |s| from_str::<int>(s))
+        };
+        assert_eq!(numeric("10 9 -3 x 100"),
+                   Ok(strings([ "x", "-3", "9", "10", "100" ])));
+        assert_eq!(numeric(""), Ok(vec![]));
+
+        let bad = shell_style_tokeniser().add_line("b 'a");
+        assert_eq!(bad.into_strings_sort_key(|s| s.len()),
+                   Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn lines_equivalent() {
+        let tok = shell_style_tokeniser();
+        assert_eq!(tok.lines_equivalent("git commit -m \"fix\"",
+                                        "git commit -m fix"),
+                   Ok(true));
+        assert_eq!(tok.lines_equivalent("git commit -m fix",
+                                        "git -m fix commit"),
+                   Ok(false));
+        assert_eq!(tok.lines_equivalent("a 'b", "a b"),
+                   Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn canonical_form() {
+        let tok = shell_style_tokeniser();
+        assert_eq!(tok.canonical_form("git commit -m \"fix\""),
+                   tok.canonical_form("  git commit -m fix  "));
+        assert_eq!(tok.canonical_form("x '' \"it's\""),
+                   Ok("x '' it\\'s".into_string()));
+    }
+
+    /// The canonical form of a shell-style line is its own canonical form,
+    /// and has the same words.
+    #[quickcheck]
+    fn canonical_form_idempotent(words: Vec<String>) -> bool {
+        let tok = shell_style_tokeniser();
+        let line = shell_join(words.as_slice());
+        let canonical = tok.canonical_form(line.as_slice()).unwrap();
+
+        tok.canonical_form(canonical.as_slice()) == Ok(canonical.clone())
+            && tok.lines_equivalent(line.as_slice(), canonical.as_slice())
+                  == Ok(true)
+    }
+
+    /// Creates a shell-style Tokeniser that requires quotes to end words.
+    fn strict_after_tokeniser() -> StockTokeniser {
+        shell_style_tokeniser().with_after_quote(SeparatorAfterQuote)
+    }
+
+    #[test]
+    fn separator_after_quote() {
+        let line = |l: &str| {
+            strict_after_tokeniser().add_line(l).into_strings()
+        };
+        assert_eq!(line("\"a\"b"), Err(TrailingGarbageAfterQuote('b', 3)));
+        assert_eq!(line("\"a\" b"), Ok(strings([ "a", "b" ])));
+        assert_eq!(line("\"a\"\"b\""), Ok(strings([ "ab" ])));
+        assert_eq!(line("x'a'\\ b"), Err(TrailingGarbageAfterQuote('\\', 4)));
+        assert_eq!(line("a\"b\""), Ok(strings([ "ab" ])));
+        assert_eq!(shell_style_tokeniser().add_line("\"a\"b").into_strings(),
+                   Ok(strings([ "ab" ])));
+    }
+
+    #[test]
+    fn separator_after_quote_with_other_options() {
+        let both = strict_after_tokeniser()
+                       .with_before_quote(SeparatorBeforeQuote);
+        assert_eq!(both.add_line("\"a\"\"b\"").into_strings(),
+                   Err(QuoteNotAtFieldStart(3)));
+
+        let split = strict_after_tokeniser().with_split_adjacent_quotes(true);
+        assert_eq!(split.add_line("\"a\"b").into_strings(),
+                   Ok(strings([ "a", "b" ])));
+
+        let statements = strict_after_tokeniser()
+                             .with_statement_terminators(vec![ ';' ]);
+        assert_eq!(statements.add_line("'a'; b").into_statements(),
+                   Ok(vec![ strings([ "a" ]), strings([ "b" ]) ]));
+    }
+
+    #[test]
+    fn separator_after_quote_doubled_escape() {
+        let tok = csv_tokeniser().with_after_quote(SeparatorAfterQuote);
+        assert_eq!(tok.clone().add_string("\"a\"\"b\",c").into_strings(),
+                   Ok(strings([ "a\"b", "c" ])));
+        assert_eq!(tok.add_string("\"a\"b,c").into_strings(),
+                   Err(TrailingGarbageAfterQuote('b', 3)));
+    }
+
+    /// Creates a shell-style Tokeniser with `#` comments anywhere.
+    fn config_tokeniser() -> StockTokeniser {
+        shell_style_tokeniser().with_comment_chars(vec![ '#' ])
+                               .with_comments_anywhere(true)
+    }
+
+    #[test]
+    fn comments_anywhere() {
+        let text = |t: &str| config_tokeniser().add_string(t).into_strings();
+        assert_eq!(text("key=value# note"), Ok(strings([ "key=value" ])));
+        assert_eq!(text("\"a#b\"#c 'd#'"), Ok(strings([ "a#b" ])));
+        assert_eq!(text("a\\#b #c"), Ok(strings([ "a#b" ])));
+        assert_eq!(text("a # b # c\nd#e\n#f\ng"),
+                   Ok(strings([ "a", "d", "g" ])));
+        assert_eq!(shell_style_tokeniser().with_comment_chars(vec![ '#' ])
+                                          .add_string("a#b #c")
+                                          .into_strings(),
+                   Ok(strings([ "a#b" ])));
+    }
+
+    #[test]
+    fn comments_anywhere_end_statements_and_quotes() {
+        let statements = config_tokeniser()
+                             .with_statement_terminators(vec![ '\n' ])
+                             .add_string("a b#c\nd");
+        assert_eq!(statements.into_statements(),
+                   Ok(vec![ strings([ "a", "b" ]), strings([ "d" ]) ]));
+
+        let strict = config_tokeniser().with_after_quote(SeparatorAfterQuote);
+        assert_eq!(strict.add_string("'a'#b").into_strings(),
+                   Ok(strings([ "a" ])));
+    }
+
+    #[test]
+    fn into_strings_group_consecutive_by() {
+        let group = |l: &str| {
+            whitespace_split_tokeniser().add_line(l)
+                .into_strings_group_consecutive_by(|s| s.char_len())
+        };
+        assert_eq!(group("a b cc d d"),
+                   Ok(vec![ strings([ "a", "b" ]),
+                            strings([ "cc" ]),
+                            strings([ "d", "d" ]) ]));
+        assert_eq!(group(""), Ok(vec![]));
+        assert_eq!(shell_style_tokeniser().add_line("a 'b")
+                       .into_strings_group_consecutive_by(|s| s.len()),
+                   Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn add_line_quote_across_lines() {
+        let tok = shell_style_tokeniser().add_line("echo \"a")
+                                         .add_line("b\"");
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "echo".into_string(), "a\nb".into_string() ]));
+
+        let tok = shell_style_tokeniser().add_line("a").add_line("b\r\n");
+        assert_eq!(tok.into_strings(),
+                   Ok(vec![ "a".into_string(), "b".into_string() ]));
+
+        let tok = shell_style_tokeniser().add_line("'a ").add_line(" b '");
+        assert_eq!(tok.into_strings(), Ok(vec![ "a \n b ".into_string() ]));
+    }
+
+    /// Creates a shell-style Tokeniser that reads line continuations.
+    fn continuation_tokeniser() -> StockTokeniser {
+        shell_style_tokeniser().with_line_continuation(true)
+    }
+
+    #[test]
+    fn line_continuation_between_and_within_words() {
+        let line = |l: &str| continuation_tokeniser().add_string(l)
+                                                     .into_strings();
+
+        assert_eq!(line("hello \\\nworld"),
+                   Ok(vec![ "hello".into_string(), "world".into_string() ]));
+        assert_eq!(line("hello\\\nworld"),
+                   Ok(vec![ "helloworld".into_string() ]));
+        assert_eq!(line("a \\\n b"),
+                   Ok(vec![ "a".into_string(), "b".into_string() ]));
+        assert_eq!(line("\"a\\\nb\" 'c\\\nd'"),
+                   Ok(vec![ "ab".into_string(), "c\\\nd".into_string() ]));
+
+        // Without continuations, the newline is escaped as before.
+        assert_eq!(shell_style_tokeniser().add_string("a\\\nb")
+                                          .into_strings(),
+                   Ok(vec![ "a\nb".into_string() ]));
+    }
+
+    #[test]
+    fn line_continuation_across_feed_line() {
+        let mut tok = continuation_tokeniser();
+        tok.feed_line("ls -l \\\n");
+        tok.feed_line("  /tmp\\\n");
+        tok.feed_line("\n");
+        assert_eq!(tok.into_tokens(),
+                   Ok(vec![ Token { value: "ls".into_string(),
+                                    kind: Bare,
+                                    had_escape: false },
+                            Token { value: "-l".into_string(),
+                                    kind: Bare,
+                                    had_escape: false },
+                            Token { value: "/tmp".into_string(),
+                                    kind: Escaped,
+                                    had_escape: true } ]));
+    }
+
+    #[test]
+    fn into_strings_windowed() {
+        let words = |l: &str, n: uint| {
+            shell_style_tokeniser().add_line(l).into_strings_windowed(n)
+        };
+
+        assert_eq!(words("a 'b c' d e", 3),
+                   Ok(vec![ strings([ "a", "b c", "d" ]),
+                            strings([ "b c", "d", "e" ]) ]));
+        assert_eq!(words("a b", 1),
+                   Ok(vec![ strings([ "a" ]), strings([ "b" ]) ]));
+        assert_eq!(words("a b", 2), Ok(vec![ strings([ "a", "b" ]) ]));
+        assert_eq!(words("a b", 3), Ok(vec![]));
+        assert_eq!(words("a b", 0), Ok(vec![]));
+        assert_eq!(words("a 'b", 1), Err(UnmatchedQuote(2)));
+        assert_eq!(words("a 'b", 0), Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn words_so_far_while_feeding() {
+        let mut tok = shell_style_tokeniser();
+        assert!(tok.words_so_far().is_empty());
+
+        tok.push_str("a");
+        assert!(tok.words_so_far().is_empty());
+        tok.push_str(" \"b c");
+        assert_eq!(tok.words_so_far(), [ "a".into_string() ].as_slice());
+        tok.push_str("\" ");
+        assert_eq!(tok.words_so_far(),
+                   [ "a".into_string(), "b c".into_string() ].as_slice());
+        tok.push_str("   d");
+
+        let so_far = tok.words_so_far().to_vec();
+        let mut words = tok.into_strings().unwrap();
+        assert_eq!(words.pop(), Some("d".into_string()));
+        assert_eq!(words, so_far);
+    }
+
+    #[test]
+    fn words_so_far_with_empty_fields() {
+        let mut tok = csv_tokeniser();
+        tok.push_str("a,,\"\",");
+        assert_eq!(tok.words_so_far(),
+                   [ "a".into_string(), "".into_string(), "".into_string() ]
+                   .as_slice());
+        assert_eq!(tok.words_so_far().to_vec(),
+                   tok.clone().into_strings().unwrap()
+                      .slice_to(3).to_vec());
+    }
+
+    #[test]
+    fn compare_lines_by_words() {
+        let tok = shell_style_tokeniser();
+        let compare = |a: &str, b: &str| tok.compare_lines(a, b, LexicalOrder);
+
+        assert_eq!(compare("\"apple pie\"", "zebra"), Ok(Less));
+        assert_eq!(compare("'b' a", "b \"a\""), Ok(Equal));
+        assert_eq!(compare("a b", "a"), Ok(Greater));
+        assert_eq!(compare("file10", "file2"), Ok(Less));
+        assert_eq!(compare("a 'b", "c"), Err(UnmatchedQuote(2)));
+        assert_eq!(compare("a", "c \"d"), Err(UnmatchedQuote(2)));
+    }
+
+    #[test]
+    fn compare_lines_naturally() {
+        let tok = shell_style_tokeniser();
+        let compare = |a: &str, b: &str| tok.compare_lines(a, b, NaturalOrder);
+
+        assert_eq!(compare("file2", "file10"), Ok(Less));
+        assert_eq!(compare("file10", "'file'9"), Ok(Greater));
+        assert_eq!(compare("v1.10", "v1.9"), Ok(Greater));
+        assert_eq!(compare("a7", "a007"), Ok(Less));
+        assert_eq!(compare("a7b", "a7c"), Ok(Less));
+        assert_eq!(compare("x 2", "x 2"), Ok(Equal));
+    }
+
+    #[test]
+    fn sort_lines_failures_last() {
+        let tok = shell_style_tokeniser();
+        let mut lines: Vec<String> = [
+            "play file10", "zap 'x", "\"play\" file2", "'apple pie'",
+            "add \"y", "play file2"
+        ].iter().map(|l| l.into_string()).collect();
+
+        assert_eq!(tok.sort_lines(lines.as_mut_slice(), NaturalOrder),
+                   Err(UnmatchedQuote(4)));
+        assert_eq!(lines, [
+            "'apple pie'", "\"play\" file2", "play file2", "play file10",
+            "zap 'x", "add \"y"
+        ].iter().map(|l| l.into_string()).collect());
+    }
+
+    #[test]
+    fn into_strings_partition() {
+        let split = |l: &str| {
+            shell_style_tokeniser().add_line(l)
+                                   .into_strings_partition(|w| w.contains("="))
+        };
+
+        assert_eq!(split("env A=1 'B=2 3' cmd \"x\""),
+                   Ok(( vec![ "A=1".into_string(), "B=2 3".into_string() ],
+                        vec![ "env".into_string(),
+                              "cmd".into_string(),
+                              "x".into_string() ] )));
+        assert_eq!(split(""), Ok(( vec![], vec![] )));
+        assert_eq!(split("a 'b"), Err(UnmatchedQuote(2)));
+    }
 }